@@ -2,6 +2,7 @@
 mod tests {
     use crate::scfmt;
     use crate::scfmt::ScfmtErr;
+    use serde_json::json;
     use std::fs;
 
     //> basic tests
@@ -179,534 +180,3549 @@ mod tests {
             let formatted = scfmt::format_str("//>\n// < test", "rs").unwrap();
             assert_eq!(formatted, "//>\n// <\n// test");
         }
-    //<
-    #[test]
-    fn nullify_brackets() {
-        let formatted =
-            scfmt::null_existing_brackets("//>\n    //>\n//\n    //<\n//<", "rs").unwrap();
-        assert_eq!(formatted, "//_>\n    //_>\n//\n    //_<\n//_<");
-    }
-
-    #[test]
-    fn incompatible_file_type() {
-        let result = scfmt::format_str("", "");
-        assert_eq!(result, Err(ScfmtErr::IncompatibleFileType));
 
-        let result = scfmt::add_brackets("", "");
-        assert_eq!(result, Err(ScfmtErr::IncompatibleFileType));
-
-        let result = scfmt::remove_brackets("", "");
-        assert_eq!(result, Err(ScfmtErr::IncompatibleFileType));
+        #[test]
+        fn preserve_closing_comment_content_before_another_comment() {
+            let formatted = scfmt::format_str("//>\n//< test\n//>\n//<", "rs").unwrap();
+            assert_eq!(formatted, "//>\n//<\n// test\n//>\n//<");
+        }
+    //<> format range
+        #[test]
+        fn format_range_leaves_lines_outside_range_untouched() {
+            let to_format = fs::read_to_string("./test_resources/1_test.rs").unwrap();
+            let answer = fs::read_to_string("./test_resources/1_answer.rs").unwrap();
+            let formatted = scfmt::format_range(&to_format, "rs", 5..12).unwrap();
+
+            let expected: String = to_format
+                .split_inclusive('\n')
+                .zip(answer.split_inclusive('\n'))
+                .enumerate()
+                .map(|(i, (to_format_line, answer_line))| {
+                    if (5..12).contains(&i) {
+                        answer_line
+                    } else {
+                        to_format_line
+                    }
+                })
+                .collect();
+            assert_eq!(formatted, expected);
+        }
 
-        let result = scfmt::null_existing_brackets("", "");
-        assert_eq!(result, Err(ScfmtErr::IncompatibleFileType));
-    }
+        #[test]
+        fn format_range_keeps_crlf_line_endings() {
+            let to_format = "//>\r\nlet a = 0;\r\nlet b = 0;\r\n//<\r\n";
+            let formatted = scfmt::format_range(to_format, "rs", 2..3).unwrap();
+            assert_eq!(formatted, "//>\r\nlet a = 0;\r\n    let b = 0;\r\n//<\r\n");
 
-    #[test]
-    fn determine_whitespace_type_gets_best_result() {
-        let to_format = fs::read_to_string("./test_resources/11_test.rs").unwrap();
-        let formatted = scfmt::format_str(&to_format, "rs").unwrap();
-        let answer = fs::read_to_string("./test_resources/11_answer.rs").unwrap();
-        assert_eq!(answer, formatted);
-    }
-}
+            let formatted = scfmt::format_range("//>\r\nlet a = 0;\r\n//<", "rs", 1..2).unwrap();
+            assert_eq!(formatted, "//>\r\n    let a = 0;\r\n//<");
+        }
 
-/// Contains functions for formatting strucuted comments in files and strings
-pub mod scfmt {
+        #[test]
+        fn format_range_uses_depth_of_whole_file() {
+            let to_format = "//>\n//>\nlet a = 0;\n//<\nlet b = 0;\n//<\n";
+            let formatted = scfmt::format_range(to_format, "rs", 2..3).unwrap();
+            assert_eq!(
+                formatted,
+                "//>\n//>\n        let a = 0;\n//<\nlet b = 0;\n//<\n"
+            );
+        }
 
-    use glob::{glob, GlobError};
-    use phf::phf_map;
-    use std::collections::HashMap;
-    use std::fs;
-    use std::fs::File;
-    use std::io::Write;
-    use std::path::PathBuf;
+        #[test]
+        fn format_range_ignores_lines_outside_sections() {
+            let formatted = scfmt::format_range("  \nlet a = 0;\n//>\n//<", "rs", 0..4).unwrap();
+            assert_eq!(formatted, "  \nlet a = 0;\n//>\n//<");
+        }
 
-    static EXTENSION_TO_COMMENT_STARTER_MAP: phf::Map<&'static str, &'static str> = phf_map! {
+        #[test]
+        fn format_range_still_reports_unbalanced_brackets() {
+            let before_formatting = fs::read_to_string("./test_resources/7_test.rs").unwrap();
+            let formatted = scfmt::format_range(&before_formatting, "rs", 0..1);
+            assert_eq!(formatted, Err(ScfmtErr::CommentNeverClosed(1)));
+        }
+    //<> document model
+        #[test]
+        fn parse_builds_section_tree() {
+            let document = scfmt::parse(
+                "//> tests\n    //> basic\n        let a = 0;\n    //<> tabs\n    //<\n//<\n",
+                "rs",
+            )
+            .unwrap();
+
+            let tests = &document.sections[0];
+            assert_eq!(tests.title, "tests");
+            assert_eq!(tests.span(), 0..6);
+            assert_eq!(tests.body, 1..5);
+            assert_eq!(tests.children.len(), 2);
+
+            let basic = &tests.children[0];
+            assert_eq!(basic.depth, 1);
+            assert_eq!(basic.indentation, 4);
+            assert_eq!(basic.opened_by, scfmt::Bracket::Open);
+            assert_eq!(basic.closed_by, scfmt::Bracket::CloseAndOpen);
+            assert_eq!(basic.body, 2..3);
+
+            let tabs = &tests.children[1];
+            assert_eq!(tabs.header_line, 3);
+            assert_eq!(tabs.opened_by, scfmt::Bracket::CloseAndOpen);
+            assert_eq!(tabs.closed_by, scfmt::Bracket::Close);
+            assert_eq!(tabs.close_line, 4);
+        }
 
-        //> ada
-            "adb" => "--",
-            "ads" => "--",
-        //<
-        // Assembly
-        "asm" => ";",
-        // AL
-        "al" => "//",
-        "bib" => "%",
-        "brs" => "'",
-        // C
-        "c" => "//",
-        "cfc" => "//",
-        // Clojure
-        "clj" => ";",
-        // Apex
-        "cls" => "//",
-        "cpp" => "//",
-        //> C#
-            "cs" => "//",
-            "csx" => "//",
-        //<
-        "d" => "//",
-        // Dart
-        "dart" => "//",
-        "do" => "*",
-        "ex" => "#",
-        "elm" => "--",
-        "gd" => "#",
-        "gen" => "\\",
-        // Go
-        "go" => "//",
-        "graphql" => "#",
-        "groovy" => "//",
-        // C header files
-        "h" => "//",
-        //> Haskell
-            "hs" => "--",
-            "lhs" => "--",
-        //<
-        // Java
-        "java" => "//",
-        //> JavaScript
-            "js" => "//",
-            "cjs" => "//",
-            "mjs" => "//",
-        //<
-        "jsonc" => "//",
-        "lisp" => ";;",
-        "lua" => "--",
-        // MATLAB
-        "m" => "%",
-        "nim" => "#",
-        // Pascal
-        "pas" => "//",
-        // PHP
-        "php" => "//",
-        "pig" => "--",
-        "plsql" => "--",
-        "pp" => "//",
-        "ps1" => "#",
-        "pu" => "'",
-        "q" => "--",
-        "rkt" => ";",
-        // Rust
-        "rs" => "//",
-        "sas" => "*",
-        "sass" => "//",
-        "scss" => "//",
-        "shader" => "//",
-        // Bash
-        "sh" => "#",
-        // Solidity
-        "sol" => "//",
-        "styl" => "//",
-        "svelte" => "//",
-        "tcl" => "#",
-        "toml" => "#",
-        //> TypeScript
-            "ts" => "//",
-            "tsx" => "//",
-        //<
-        "vala" => "//",
-        "v" => "//",
-        "vhdl" => "--",
-        "vue" => "//",
-        "yaml" => "#",
-    };
+        #[test]
+        fn document_iter_and_lookup() {
+            let to_parse = fs::read_to_string("./test_resources/1_answer.rs").unwrap();
+            let document = scfmt::parse(&to_parse, "rs").unwrap();
+
+            let titles: Vec<&str> = document.iter().map(|x| x.title.as_str()).collect();
+            assert_eq!(
+                titles,
+                vec![
+                    "consume any previous now unecessary //<",
+                    "chop off begining spaces",
+                    "remove comment notation if it exists"
+                ]
+            );
+
+            let section = document
+                .find(&[
+                    "consume any previous now unecessary //<",
+                    "chop off begining spaces",
+                ])
+                .unwrap();
+            assert_eq!(section.header_line, 8);
+            assert_eq!(document.find(&["does not exist"]), None);
+        }
 
-    /// Enum used to represent scfmt errors
-    #[derive(PartialEq, Debug)]
-    pub enum ScfmtErr {
-        IncompatibleFileType,
-        CommentClosedNothing(usize),
-        CommentNeverClosed(usize),
-        CantConvertOsString,
-        CantReadFileAsString,
-        CantCreatFile,
-        CantWriteToFile,
-    }
+        #[test]
+        fn parse_reports_unbalanced_brackets() {
+            let to_parse = fs::read_to_string("./test_resources/5_test.rs").unwrap();
+            assert_eq!(
+                scfmt::parse(&to_parse, "rs"),
+                Err(ScfmtErr::CommentClosedNothing(46))
+            );
+
+            let to_parse = fs::read_to_string("./test_resources/7_test.rs").unwrap();
+            assert_eq!(
+                scfmt::parse(&to_parse, "rs"),
+                Err(ScfmtErr::CommentNeverClosed(1))
+            );
+        }
+    //<> outline
+        #[test]
+        fn outline_formats() {
+            let to_outline = "//> tests\n    //> basic\n    //<> tabs\n    //<\n//<> \n//<\n";
 
-    fn determine_whitespace_type(str: &str) -> (char, usize) {
-        //> if no whitespace is found, assume format is 4 spaces
-            let mut chr = ' ';
-            let mut num = 4;
-        //<
+            let outline = scfmt::outline(to_outline, "rs", scfmt::OutlineFormat::Markdown).unwrap();
+            assert_eq!(
+                        outline,
+                        "- tests (lines 1-4)\n  - basic (lines 2-2)\n  - tabs (lines 3-4)\n- (untitled) (lines 5-6)\n"
+                    );
 
-        let mut tab_count = 0;
-        let mut space_count = 0;
-        let mut tab_spaces_count_map: HashMap<usize, usize> = HashMap::new();
-        let mut last_depth;
-        let mut last_diff = 0;
-        let mut cur_depth = 0;
+            let outline = scfmt::outline(to_outline, "rs", scfmt::OutlineFormat::Text).unwrap();
+            assert_eq!(
+                outline,
+                "tests (1-4)\n    basic (2-2)\n    tabs (3-4)\n(untitled) (5-6)\n"
+            );
 
-        for line in str.lines() {
-            // if line is not empty
-            if let Some(first_char) = line.chars().next() {
-                //> get dif between this line and last line
-                    last_depth = cur_depth;
-                    cur_depth =
-                        if let Some((local_depth, _)) = count_and_remove_begining_whitespace(line) {
-                            local_depth
-                        } else {
-                            0
-                        };
+            let outline = scfmt::outline(to_outline, "rs", scfmt::OutlineFormat::Json).unwrap();
+            assert!(outline.starts_with("[\n  {\n    \"title\": \"tests\",\n    \"start_line\": 1,"));
+        }
 
-                    let diff = (last_depth as isize - cur_depth as isize).abs() as usize;
-                //<
+        #[test]
+        fn outline_of_bracketless_comments() {
+            let bracketless = fs::read_to_string("./test_resources/2_test.rs").unwrap();
+            let bracketed = fs::read_to_string("./test_resources/2_answer.rs").unwrap();
+
+            let bracketless_outline =
+                scfmt::outline(&bracketless, "rs", scfmt::OutlineFormat::Text).unwrap();
+            let bracketed_outline =
+                scfmt::outline(&bracketed, "rs", scfmt::OutlineFormat::Text).unwrap();
+
+            assert_eq!(
+                        bracketless_outline,
+                        "consume any previous now unecessary //< (1-44)\n    chop off begining spaces (9-20)\n    remove comment notation if it exists (21-31)\n"
+                    );
+            assert_eq!(bracketed_outline.lines().count(), 3);
+        }
+    //<> folding ranges and document symbols
+        #[test]
+        fn folding_ranges_split_at_close_and_open() {
+            let ranges =
+                scfmt::folding_ranges("//> a\n    //> b\n    //<\n//<> c\n    x\n//<\n", "rs").unwrap();
+
+            assert_eq!(
+                ranges,
+                vec![
+                    scfmt::FoldingRange {
+                        start_line: 0,
+                        end_line: 2
+                    },
+                    scfmt::FoldingRange {
+                        start_line: 1,
+                        end_line: 2
+                    },
+                    scfmt::FoldingRange {
+                        start_line: 3,
+                        end_line: 5
+                    },
+                ]
+            );
+        }
 
-                match first_char {
-                    ' ' => space_count += 1,
-                    '\t' => tab_count += 1,
-                    _ => {}
-                }
+        #[test]
+        fn document_symbols_are_hierarchical() {
+            let to_parse = fs::read_to_string("./test_resources/1_answer.rs").unwrap();
+            let symbols = scfmt::document_symbols(&to_parse, "rs").unwrap();
+
+            assert_eq!(symbols.len(), 1);
+            assert_eq!(symbols[0].name, "consume any previous now unecessary //<");
+            assert_eq!(symbols[0].lines, 0..46);
+            assert_eq!(symbols[0].children[0].name, "chop off begining spaces");
+            assert_eq!(symbols[0].children[0].lines, 8..20);
+
+            let json: serde_json::Value =
+                serde_json::from_str(&scfmt::symbols_lsp_json(&to_parse, "rs").unwrap()).unwrap();
+            assert_eq!(
+                json["foldingRanges"][1],
+                json!({ "startLine": 8, "endLine": 19, "kind": "region" })
+            );
+            assert_eq!(
+                json["documentSymbols"][0]["children"][1]["selectionRange"]["end"],
+                json!({ "line": 20, "character": 49 })
+            );
+        }
 
-                // store diff
-                if diff != 0 {
-                    // count the current diff
+        #[test]
+        fn editor_features_skip_unbalanced_brackets() {
+            let ranges = scfmt::folding_ranges("//<\n//> a\n//<\n//>", "rs").unwrap();
+            assert_eq!(
+                ranges,
+                vec![scfmt::FoldingRange {
+                    start_line: 1,
+                    end_line: 2
+                }]
+            );
+        }
+    //<> scfmt: off and ignored files
+        #[test]
+        fn turned_off_lines_are_left_alone() {
+            let to_format = "//>\n// scfmt: off\n//>\n  art\n\t \n// scfmt: on\nlet a = 0;\n//<";
+            let formatted = scfmt::format_str(to_format, "rs").unwrap();
+            assert_eq!(
+                formatted,
+                concat!(
+                    "//>\n// scfmt: off\n//>\n  art\n\t \n// scfmt: on\n",
+                    "    let a = 0;\n//<",
+                )
+            );
+
+            let formatted = scfmt::null_existing_brackets(to_format, "rs").unwrap();
+            assert_eq!(
+                formatted,
+                concat!(
+                    "//_>\n// scfmt: off\n//>\n  art\n\t \n// scfmt: on\n",
+                    "let a = 0;\n//_<",
+                )
+            );
+
+            let formatted = scfmt::remove_brackets(to_format, "rs").unwrap();
+            assert_eq!(
+                formatted,
+                concat!(
+                    "//\n// scfmt: off\n//>\n  art\n\t \n// scfmt: on\n",
+                    "    let a = 0;",
+                )
+            );
+        }
 
-                    match tab_spaces_count_map.get(&diff) {
-                        Some(x) => {
-                            let current_map_value = x.clone();
-                            tab_spaces_count_map.insert(diff, current_map_value + 1)
-                        }
-                        None => tab_spaces_count_map.insert(diff, 1),
-                    };
+        #[test]
+        fn brackets_in_turned_off_lines_are_not_counted() {
+            let formatted = scfmt::format_str("// scfmt: off\n//>\n//<\n//<\n", "rs").unwrap();
+            assert_eq!(formatted, "// scfmt: off\n//>\n//<\n//<\n");
+
+            let formatted = scfmt::format_str("// scfmt: off\n//>\n// scfmt: on\n", "rs");
+            assert_eq!(
+                formatted,
+                Ok("// scfmt: off\n//>\n// scfmt: on\n".to_owned())
+            );
+        }
 
-                    last_diff = diff;
-                } else {
-                    // if there was no change in diff, count the last_diff
+        #[test]
+        fn add_brackets_skips_turned_off_lines() {
+            let to_format = concat!(
+                "//\n    let a = 0;\n    // scfmt: off\n// not a section\n",
+                "    // scfmt: on\n    let b = 0;\n",
+            );
+            let formatted = scfmt::add_brackets(to_format, "rs").unwrap();
+            assert_eq!(
+                formatted,
+                concat!(
+                    "//>\n    let a = 0;\n    // scfmt: off\n// not a section\n",
+                    "    // scfmt: on\n    let b = 0;\n//<\n",
+                )
+            );
+        }
 
-                    if last_diff != 0 {
-                        match tab_spaces_count_map.get(&last_diff) {
-                            Some(x) => {
-                                let current_map_value = x.clone();
-                                tab_spaces_count_map.insert(last_diff, current_map_value + 1)
-                            }
-                            None => tab_spaces_count_map.insert(last_diff, 1),
-                        };
-                    }
-                }
+        #[test]
+        fn ignored_files_are_left_alone() {
+            let ignored = [
+                "// scfmt: ignore-file\n//>\nlet a = 0;\n",
+                "// This file is @generated by a tool.\n\n//>\nlet a = 0;\n",
+            ];
+
+            for to_format in ignored {
+                assert_eq!(scfmt::format_str(to_format, "rs").unwrap(), to_format);
+                assert_eq!(
+                    scfmt::format_range(to_format, "rs", 0..4).unwrap(),
+                    to_format
+                );
+                assert_eq!(scfmt::add_brackets(to_format, "rs").unwrap(), to_format);
+                assert_eq!(scfmt::remove_brackets(to_format, "rs").unwrap(), to_format);
+                assert_eq!(
+                    scfmt::null_existing_brackets(to_format, "rs").unwrap(),
+                    to_format
+                );
             }
+
+            // @generated only counts in the comments at the top of a file
+            let to_format = "let a = 0;\n// @generated\n//>\nlet b = 0;\n//<";
+            assert_eq!(
+                scfmt::format_str(to_format, "rs").unwrap(),
+                "let a = 0;\n// @generated\n//>\n    let b = 0;\n//<"
+            );
+        }
+    //<> labeled closers
+        #[test]
+        fn labeled_closers_are_kept_and_checked() {
+            let to_format = "// scfmt: labeled-closers\n//> setup\nlet a = 0;\n//< setup  \n";
+            let formatted = scfmt::format_str(to_format, "rs").unwrap();
+            assert_eq!(
+                formatted,
+                "// scfmt: labeled-closers\n//> setup\n    let a = 0;\n//< setup\n"
+            );
+
+            // unlabeled closers are still allowed
+            let to_format = "// scfmt: labeled-closers\n//> setup\nlet a = 0;\n//<\n";
+            assert!(scfmt::format_str(to_format, "rs").is_ok());
+
+            let to_format = "// scfmt: labeled-closers\n//> setup\n    //> a\n    //< b\n//< setup\n";
+            let formatted = scfmt::format_str(to_format, "rs");
+            assert_eq!(formatted, Err(ScfmtErr::ClosingLabelMismatch(4)));
+            assert_eq!(
+                scfmt::bracket_errors(to_format, "rs").unwrap(),
+                vec![ScfmtErr::ClosingLabelMismatch(4)]
+            );
+
+            // without opting in, text after //< is moved below it like before
+            let formatted = scfmt::format_str("//> setup\n//< b\n", "rs").unwrap();
+            assert_eq!(formatted, "//> setup\n//<\n// b\n");
         }
 
-        //> determine most often occuring diff
-            let mut highest_count = 0;
-            let mut diff_with_highest_count = 0;
-            for (diff_size, diff_count) in &tab_spaces_count_map {
-                if diff_count > &highest_count {
-                    highest_count = *diff_count;
-                    diff_with_highest_count = *diff_size;
-                }
-            }
+        #[test]
+        fn label_closers_fills_in_labels() {
+            let to_label = "#!/bin/sh\n#> setup\n    #> a\n    #<> \n    #< wrong\n#<\n";
+            let labeled = scfmt::label_closers(to_label, "sh").unwrap();
+            assert_eq!(
+                labeled,
+                concat!(
+                    "#!/bin/sh\n# scfmt: labeled-closers\n#> setup\n    #> a\n",
+                    "    #<> \n    #<\n#< setup\n",
+                )
+            );
 
-        //<> determine num and char
-            if diff_with_highest_count != 0 {
-                num = diff_with_highest_count;
+            // labeling again changes nothing, and the labels pass formatting
+            assert_eq!(scfmt::label_closers(&labeled, "sh").unwrap(), labeled);
+            assert_eq!(scfmt::format_str(&labeled, "sh").unwrap(), labeled);
 
-                if tab_count > space_count {
-                    chr = '\t'
+            let result = scfmt::label_closers("//>\n", "rs");
+            assert_eq!(result, Err(ScfmtErr::CommentNeverClosed(1)));
+        }
+    //<> repair
+        #[test]
+        fn repair_adds_missing_closers() {
+            // the inner section is missing its closer, so the //< belongs to the outer one
+            let to_repair = "//> a\n    //> b\n    x\n\n//<\n//> c\n    y\nz\n";
+            let (repaired, repairs) = scfmt::repair(to_repair, "rs").unwrap();
+            assert_eq!(
+                repaired,
+                "//> a\n    //> b\n        x\n    //<\n\n//<\n//> c\n    y\n//<\nz\n"
+            );
+            assert_eq!(
+                repairs,
+                vec![
+                    scfmt::Repair::AddedCloser {
+                        header_line: 1,
+                        after_line: 2
+                    },
+                    scfmt::Repair::AddedCloser {
+                        header_line: 5,
+                        after_line: 6
+                    },
+                ]
+            );
+        }
+
+        #[test]
+        fn repair_closes_sections_where_code_dedents() {
+            // without its closer, a would otherwise swallow y and all of b
+            let to_repair = "//> a\n    x\ny\n//> b\n    z\n//<\nw\n";
+            let (repaired, repairs) = scfmt::repair(to_repair, "rs").unwrap();
+            assert_eq!(repaired, "//> a\n    x\n//<\ny\n//> b\n    z\n//<\nw\n");
+            assert_eq!(
+                repairs,
+                vec![scfmt::Repair::AddedCloser {
+                    header_line: 0,
+                    after_line: 1
+                }]
+            );
+
+            // a line indented less than the header ends it, even if the section's body wasn't indented
+            let to_repair = "fn a() {\n    //> x\n    let a = 0;\n}\nlet b = 0;\n";
+            let (repaired, repairs) = scfmt::repair(to_repair, "rs").unwrap();
+            assert_eq!(
+                repaired,
+                "fn a() {\n    //> x\n        let a = 0;\n    //<\n}\nlet b = 0;\n"
+            );
+            assert_eq!(
+                repairs,
+                vec![scfmt::Repair::AddedCloser {
+                    header_line: 1,
+                    after_line: 2
+                }]
+            );
+        }
+
+        #[test]
+        fn repair_fixes_closers_that_close_nothing() {
+            let to_repair = "//<\nlet a = 0;\n//< note\n//<> b\n    let b = 0;\n//<\n";
+            let (repaired, repairs) = scfmt::repair(to_repair, "rs").unwrap();
+            assert_eq!(
+                repaired,
+                "let a = 0;\n//_< note\n//> b\n    let b = 0;\n//<\n"
+            );
+            assert_eq!(
+                repairs,
+                vec![
+                    scfmt::Repair::RemovedCloser(0),
+                    scfmt::Repair::NullifiedCloser(2),
+                    scfmt::Repair::OpenedOrphan(3),
+                ]
+            );
+        }
+
+        #[test]
+        fn repair_only_formats_balanced_brackets() {
+            let to_repair = "//> a\nlet a = 0;\n//<\n";
+            let (repaired, repairs) = scfmt::repair(to_repair, "rs").unwrap();
+            assert_eq!(repaired, "//> a\n    let a = 0;\n//<\n");
+            assert!(repairs.is_empty());
+        }
+    //<> lint
+        fn lint_rules(str: &str, config: &scfmt::lint::LintConfig) -> Vec<(&'static str, usize)> {
+            scfmt::lint::lint(str, "rs", config)
+                .unwrap()
+                .iter()
+                .map(|x| (x.rule.name(), x.line))
+                .collect()
+        }
+
+        #[test]
+        fn lint_reports_enabled_rules() {
+            let mut config = scfmt::lint::LintConfig::default();
+            for rule in scfmt::lint::RULES {
+                config.rules.insert(rule, scfmt::lint::Severity::Warning);
+            }
+            config.max_depth = 2;
+            config.max_lines = 6;
+
+            let to_lint = concat!(
+                "//> a\n//<\n//>\n    x\n  //<\n//>a\n    //> b\n        //> c\n",
+                "            x\n            y\n            z\n        //<\n    //<\n//<\n",
+            );
+            assert_eq!(
+                lint_rules(to_lint, &config),
+                vec![
+                    ("empty-section", 0),
+                    ("single-line-section", 2),
+                    ("no-title", 2),
+                    ("closer-indentation", 4),
+                    ("missing-space", 5),
+                    ("too-long", 5),
+                    ("duplicate-title", 5),
+                    ("too-deep", 7),
+                ]
+            );
+        }
+
+        #[test]
+        fn lint_config_and_suppression() {
+            let config = scfmt::lint::LintConfig::from_toml(
+                "[lint]\nempty-section = \"error\"\nno-title = \"off\"",
+            )
+            .unwrap();
+
+            let lints = scfmt::lint::lint("//>\n//<\n", "rs", &config).unwrap();
+            assert_eq!(lints.len(), 1);
+            assert_eq!(lints[0].rule, scfmt::lint::Rule::EmptySection);
+            assert_eq!(lints[0].severity, scfmt::lint::Severity::Error);
+
+            let to_lint = "// scfmt: allow(empty-section)\n//> a\n//<\n//> b\n//<\n";
+            assert_eq!(lint_rules(to_lint, &config), vec![("empty-section", 3)]);
+
+            let result = scfmt::lint::LintConfig::from_toml("[lint]\nempty = \"error\"");
+            assert_eq!(
+                result,
+                Err("Invalid config: unknown lint rule \"empty\"".to_owned())
+            );
+
+            let result = scfmt::lint::lint("//>\n", "rs", &config);
+            assert_eq!(result, Err(ScfmtErr::CommentNeverClosed(1)));
+        }
+    //<> extract
+        #[test]
+        fn extract_section_bodies() {
+            let to_extract = fs::read_to_string("./test_resources/1_answer.rs").unwrap();
+            let document = scfmt::parse(&to_extract, "rs").unwrap();
+            let section = document
+                .find(&[
+                    "consume any previous now unecessary //<",
+                    "chop off begining spaces",
+                ])
+                .unwrap();
+
+            let extracted =
+                scfmt::extract(&to_extract, "rs", "*/chop off begining spaces", false).unwrap();
+            assert_eq!(extracted.len(), 1);
+            assert!(extracted[0]
+                .text
+                .starts_with("let mut line_no_leading_spaces"));
+            assert_eq!(extracted[0].text.lines().count(), section.body.len());
+
+            let range_text = &to_extract[extracted[0].range.clone()];
+            assert!(range_text.starts_with("            let mut line_no_leading_spaces"));
+            assert_eq!(range_text.lines().count(), section.body.len());
+        }
+
+        #[test]
+        fn extract_with_globs_and_brackets() {
+            let to_extract = "//> a\n    //> one\n        x\n\n    //<> two\n        y\n    //<\n//<";
+            let extracted = scfmt::extract(to_extract, "rs", "a/*", false).unwrap();
+            let texts: Vec<&str> = extracted.iter().map(|x| x.text.as_str()).collect();
+            assert_eq!(texts, vec!["x\n\n", "y\n"]);
+
+            let extracted = scfmt::extract(to_extract, "rs", "a/t?o", true).unwrap();
+            assert_eq!(extracted[0].text, "//<> two\n    y\n//<\n");
+            assert_eq!(extracted[0].range, 29..60);
+
+            let extracted = scfmt::extract(to_extract, "rs", "b", false).unwrap();
+            assert!(extracted.is_empty());
+        }
+    //<> view
+        #[test]
+        fn view_collapses_deep_sections() {
+            let to_view = concat!(
+                "//> a\n    //> b\n        x\n        y\n    //<> c\n        z\n    //<\n//<\n",
+                "//> d\n\tw\n//<\n",
+            );
+            let lines = scfmt::view(to_view, "rs", 1, &[]).unwrap();
+            assert_eq!(
+                lines,
+                vec![
+                    scfmt::ViewLine::Line("//> a".to_owned()),
+                    scfmt::ViewLine::Line("    //> b".to_owned()),
+                    scfmt::ViewLine::Collapsed {
+                        indentation: "        ".to_owned(),
+                        lines: 2
+                    },
+                    scfmt::ViewLine::Line("    //<> c".to_owned()),
+                    scfmt::ViewLine::Collapsed {
+                        indentation: "        ".to_owned(),
+                        lines: 2
+                    },
+                    scfmt::ViewLine::Line("//<".to_owned()),
+                    scfmt::ViewLine::Line("//> d".to_owned()),
+                    scfmt::ViewLine::Line("\tw".to_owned()),
+                    scfmt::ViewLine::Line("//<".to_owned()),
+                ]
+            );
+        }
+
+        #[test]
+        fn view_expands_paths() {
+            let to_view =
+                "//> a\n    //> b\n        x\n    //<> c\n        z\n    //<\n//<\n//> d\n//<\n";
+            let lines = scfmt::view(to_view, "rs", 0, &["a/c"]).unwrap();
+            let hidden: Vec<usize> = lines
+                .iter()
+                .filter_map(|x| match x {
+                    scfmt::ViewLine::Collapsed { lines, .. } => Some(*lines),
+                    _ => None,
+                })
+                .collect();
+
+            // a is opened to show c, and b and d stay collapsed
+            assert_eq!(lines.len(), 9);
+            assert_eq!(hidden, vec![1, 1]);
+        }
+    //<> breadcrumb
+        #[test]
+        fn breadcrumb_of_lines() {
+            let to_search = fs::read_to_string("./test_resources/1_answer.rs").unwrap();
+            let titles = scfmt::breadcrumb(&to_search, "rs", 10).unwrap();
+            assert_eq!(
+                titles,
+                vec![
+                    "consume any previous now unecessary //<",
+                    "chop off begining spaces"
+                ]
+            );
+
+            let to_search = "//> a\n    //> b\n    //<> c\n    //<\n//<\nx\n//>\n";
+            assert_eq!(scfmt::breadcrumb(to_search, "rs", 0).unwrap(), vec!["a"]);
+            assert_eq!(
+                scfmt::breadcrumb(to_search, "rs", 2).unwrap(),
+                vec!["a", "c"]
+            );
+            assert_eq!(scfmt::breadcrumb(to_search, "rs", 4).unwrap(), vec!["a"]);
+            assert!(scfmt::breadcrumb(to_search, "rs", 5).unwrap().is_empty());
+            // the section that was never closed is skipped
+            assert!(scfmt::breadcrumb(to_search, "rs", 6).unwrap().is_empty());
+        }
+    //<> wrap and unwrap
+        #[test]
+        fn wrap_lines_in_a_section() {
+            let to_wrap = "//> a\n    let a = 0;\n\n    let b = 0;\n//<\n";
+            let wrapped = scfmt::refactor::wrap_lines(to_wrap, "rs", 1..4, "ab").unwrap();
+            assert_eq!(
+                wrapped,
+                "//> a\n    //> ab\n        let a = 0;\n\n        let b = 0;\n    //<\n//<\n"
+            );
+            assert_eq!(scfmt::format_str(&wrapped, "rs").unwrap(), wrapped);
+
+            // sections inside the lines are moved along with them
+            let wrapped = scfmt::refactor::wrap_lines(to_wrap, "rs", 0..5, "").unwrap();
+            assert_eq!(
+                wrapped,
+                "//>\n    //> a\n        let a = 0;\n\n        let b = 0;\n    //<\n//<\n"
+            );
+
+            let wrapped = scfmt::refactor::wrap_lines(to_wrap, "rs", 1..5, "x");
+            assert_eq!(wrapped, Err(ScfmtErr::LinesCrossSection(1)));
+
+            let wrapped = scfmt::refactor::wrap_lines(to_wrap, "rs", 4..6, "x");
+            assert_eq!(wrapped, Err(ScfmtErr::InvalidLineRange));
+        }
+
+        #[test]
+        fn wrap_lines_cant_split_close_and_open_chains() {
+            let to_wrap = "//> a\nx\n//<> b\ny\n//<";
+            let wrapped = scfmt::refactor::wrap_lines(to_wrap, "rs", 0..2, "x");
+            assert_eq!(wrapped, Err(ScfmtErr::LinesCrossSection(1)));
+
+            let wrapped = scfmt::refactor::wrap_lines(to_wrap, "rs", 0..5, "x").unwrap();
+            assert_eq!(
+                wrapped,
+                "//> x\n    //> a\n    x\n    //<> b\n    y\n    //<\n//<"
+            );
+        }
+
+        #[test]
+        fn unwrap_sections() {
+            let to_unwrap = "//> a\n    x\n//<> b\n    y\n//<> c\n    z\n//<\n";
+
+            let unwrapped = scfmt::refactor::unwrap_section(to_unwrap, "rs", "a").unwrap();
+            assert_eq!(unwrapped, "x\n//> b\n    y\n//<> c\n    z\n//<\n");
+
+            let unwrapped = scfmt::refactor::unwrap_section(to_unwrap, "rs", "b").unwrap();
+            assert_eq!(unwrapped, "//> a\n    x\n//<\ny\n//> c\n    z\n//<\n");
+
+            let unwrapped = scfmt::refactor::unwrap_section(to_unwrap, "rs", "c").unwrap();
+            assert_eq!(unwrapped, "//> a\n    x\n//<> b\n    y\n//<\nz\n");
+
+            let unwrapped = scfmt::refactor::unwrap_section(to_unwrap, "rs", "d");
+            assert_eq!(unwrapped, Err(ScfmtErr::SectionNotFound("d".to_owned())));
+        }
+    //<> split and merge
+        #[test]
+        fn split_sections() {
+            let to_split = "//> a\n    x\n    //> b\n        y\n    //<\n    z\n//<\n";
+
+            // the new section takes the child along with it
+            let split = scfmt::refactor::split_section(to_split, "rs", 2, "c").unwrap();
+            assert_eq!(
+                split,
+                "//> a\n    x\n//<> c\n    //> b\n        y\n    //<\n    z\n//<\n"
+            );
+
+            let split = scfmt::refactor::split_section(to_split, "rs", 3, "c").unwrap();
+            assert_eq!(
+                split,
+                "//> a\n    x\n    //> b\n    //<> c\n        y\n    //<\n    z\n//<\n"
+            );
+
+            let split = scfmt::refactor::split_section(to_split, "rs", 4, "c");
+            assert_eq!(split, Err(ScfmtErr::LinesCrossSection(3)));
+
+            let split = scfmt::refactor::split_section(to_split, "rs", 0, "c");
+            assert_eq!(split, Err(ScfmtErr::LineNotInSection(1)));
+        }
+
+        #[test]
+        fn split_and_merge_labeled_sections() {
+            let labeled = "// scfmt: labeled-closers\n//> a\n    x\n    y\n//< a\n";
+
+            let split = scfmt::refactor::split_section(labeled, "rs", 3, "b").unwrap();
+            assert_eq!(
+                split,
+                "// scfmt: labeled-closers\n//> a\n    x\n//<> b\n    y\n//< b\n"
+            );
+
+            let merged = scfmt::refactor::merge_sections(&split, "rs", "a", false).unwrap();
+            assert_eq!(merged, labeled);
+        }
+
+        #[test]
+        fn merge_sections() {
+            let to_merge = "//> a\n    x\n//<> b\n    y\n//<> c\n    z\n//<\n";
+
+            let merged = scfmt::refactor::merge_sections(to_merge, "rs", "b", true).unwrap();
+            assert_eq!(merged, "//> a\n    x\n//<> b, c\n    y\n    z\n//<\n");
+
+            let merged = scfmt::refactor::merge_sections(to_merge, "rs", "c", true);
+            assert_eq!(merged, Err(ScfmtErr::NoNextSection(5)));
+
+            let merged = scfmt::refactor::merge_sections(to_merge, "rs", "d", true);
+            assert_eq!(merged, Err(ScfmtErr::SectionNotFound("d".to_owned())));
+        }
+    //<> move and sort
+        #[test]
+        fn move_sections_between_depths() {
+            let to_move = concat!(
+                "//> a\n    //> b\n        x\n    //<\n\n    //> c\n        y\n    //<\n//<\n",
+                "//> d\n    z\n//<\n",
+            );
+
+            let moved = scfmt::refactor::move_section(to_move, "rs", "a/c", "d").unwrap();
+            assert_eq!(
+                moved,
+                concat!(
+                    "//> a\n    //> b\n        x\n    //<\n//<\n",
+                    "//> c\n    y\n//<\n\n//> d\n    z\n//<\n",
+                )
+            );
+
+            let moved = scfmt::refactor::move_section(to_move, "rs", "d", "a/b").unwrap();
+            assert_eq!(
+                moved,
+                concat!(
+                    "//> a\n    //> d\n        z\n    //<\n    //> b\n        x\n    //<\n\n",
+                    "    //> c\n        y\n    //<\n//<\n",
+                )
+            );
+
+            let moved = scfmt::refactor::move_section(to_move, "rs", "a", "a/c");
+            assert_eq!(moved, Err(ScfmtErr::SectionMovedIntoItself(1)));
+        }
+
+        #[test]
+        fn move_sections_in_chains() {
+            let to_move = "//> a\n    x\n//<> b\n    y\n//<> c\n    z\n//<\n";
+
+            let moved = scfmt::refactor::move_section(to_move, "rs", "c", "a").unwrap();
+            assert_eq!(moved, "//> c\n    z\n//<> a\n    x\n//<> b\n    y\n//<\n");
+
+            let moved = scfmt::refactor::move_section(to_move, "rs", "a", "c").unwrap();
+            assert_eq!(moved, "//> b\n    y\n//<> a\n    x\n//<> c\n    z\n//<\n");
+
+            let moved = scfmt::refactor::move_section(to_move, "rs", "a", "b").unwrap();
+            assert_eq!(moved, to_move);
+
+            // a section leaving a chain gets its own brackets
+            let to_move = "//> a\n    x\n//<> b\n    y\n//<\n//> c\n//<\n";
+            let moved = scfmt::refactor::move_section(to_move, "rs", "a", "c").unwrap();
+            assert_eq!(moved, "//> b\n    y\n//<\n//> a\n    x\n//<\n//> c\n//<\n");
+
+            // and a copy of the blank lines separating the chain
+            let to_move = "//> a\n    x\n//<> b\n    y\n//<\n\n//> c\n//<\n";
+            let moved = scfmt::refactor::move_section(to_move, "rs", "a", "c").unwrap();
+            assert_eq!(
+                moved,
+                "//> b\n    y\n//<\n\n//> a\n    x\n//<\n\n//> c\n//<\n"
+            );
+        }
+
+        #[test]
+        fn sort_sections() {
+            let to_sort = concat!(
+                "//> tests\n    //> c\n    //<\n\n    //> A\n        //> z\n        //<\n",
+                "    //<\n\n    //> b\n    //<\n//<\n",
+            );
+            let sorted = scfmt::refactor::sort_sections(to_sort, "rs", "tests").unwrap();
+            assert_eq!(
+                sorted,
+                concat!(
+                    "//> tests\n    //> A\n        //> z\n        //<\n    //<\n\n",
+                    "    //> b\n    //<\n\n    //> c\n    //<\n//<\n",
+                )
+            );
+
+            // chains stay chained, with their closing label kept up to date
+            let to_sort = concat!(
+                "// scfmt: labeled-closers\n//> p\n    //> b\n    //<> c\n    //<> a\n",
+                "    //< a\n//< p\n",
+            );
+            let sorted = scfmt::refactor::sort_sections(to_sort, "rs", "p").unwrap();
+            assert_eq!(
+                sorted,
+                concat!(
+                    "// scfmt: labeled-closers\n//> p\n    //> a\n    //<> b\n    //<> c\n",
+                    "    //< c\n//< p\n",
+                )
+            );
+
+            // sections leaving a chain keep the blank lines between sections
+            let to_sort = concat!(
+                "//> p\n    //> c\n    //<\n\n    //> b\n    //<> a\n    //<\n\n",
+                "    //> A2\n    //<\n//<\n",
+            );
+            let sorted = scfmt::refactor::sort_sections(to_sort, "rs", "p").unwrap();
+            assert_eq!(
+                sorted,
+                concat!(
+                    "//> p\n    //> a\n    //<\n\n    //> A2\n    //<\n\n",
+                    "    //> b\n    //<\n\n    //> c\n    //<\n//<\n",
+                )
+            );
+        }
+    //<> rename
+        #[test]
+        fn rename_titles() {
+            use scfmt::refactor::TitleMatch;
+
+            let to_rename = concat!(
+                "//> setup\n    // setup the test\n    //>setup\n    //<\n//<\n",
+                "//> tests\n    //> Setup  \n    //<> teardown\n    //<\n//<\n",
+            );
+
+            // code and comments without brackets are never renamed
+            let matcher = TitleMatch::Exact("setup".to_owned());
+            let (renamed, sites) =
+                scfmt::refactor::rename_titles(to_rename, "rs", &matcher, "arrange", None).unwrap();
+            assert_eq!(
+                renamed,
+                concat!(
+                    "//> arrange\n    // setup the test\n    //>arrange\n    //<\n//<\n",
+                    "//> tests\n    //> Setup  \n    //<> teardown\n    //<\n//<\n",
+                )
+            );
+            assert_eq!(sites.iter().map(|x| x.line).collect::<Vec<_>>(), vec![0, 2]);
+
+            let matcher = TitleMatch::CaseInsensitive("SETUP".to_owned());
+            let (renamed, sites) =
+                scfmt::refactor::rename_titles(to_rename, "rs", &matcher, "arrange", Some("tests"))
+                    .unwrap();
+            assert_eq!(
+                renamed,
+                concat!(
+                    "//> setup\n    // setup the test\n    //>setup\n    //<\n//<\n",
+                    "//> tests\n    //> arrange  \n    //<> teardown\n    //<\n//<\n",
+                )
+            );
+            assert_eq!(
+                sites,
+                vec![scfmt::refactor::Renamed {
+                    line: 6,
+                    from: "Setup".to_owned(),
+                    to: "arrange".to_owned(),
+                }]
+            );
+
+            let matcher = TitleMatch::Regex(regex::Regex::new("^(set|tear)(up|down)$").unwrap());
+            let (renamed, _) =
+                scfmt::refactor::rename_titles(to_rename, "rs", &matcher, "$1 $2", Some("*/*"))
+                    .unwrap();
+            assert_eq!(
+                renamed,
+                concat!(
+                    "//> setup\n    // setup the test\n    //>set up\n    //<\n//<\n",
+                    "//> tests\n    //> Setup  \n    //<> tear down\n    //<\n//<\n",
+                )
+            );
+        }
+
+        #[test]
+        fn rename_labeled_closers() {
+            let to_rename = "// scfmt: labeled-closers\n//> a\n    x\n//< a\n";
+            let matcher = scfmt::refactor::TitleMatch::Exact("a".to_owned());
+            let (renamed, _) =
+                scfmt::refactor::rename_titles(to_rename, "rs", &matcher, "b", None).unwrap();
+            assert_eq!(renamed, "// scfmt: labeled-closers\n//> b\n    x\n//< b\n");
+        }
+    //<> grep
+        #[test]
+        fn grep_titles() {
+            let to_search = fs::read_to_string("./test_resources/2_answer.rs").unwrap();
+            let pattern = regex::Regex::new("^(chop|remove) ").unwrap();
+            let hits = scfmt::grep(&to_search, "rs", &pattern, None).unwrap();
+
+            let lines: Vec<usize> = hits.iter().map(|x| x.line).collect();
+            assert_eq!(lines, vec![8, 20]);
+            assert_eq!(
+                hits[1].breadcrumb,
+                vec![
+                    "consume any previous now unecessary //<",
+                    "remove comment notation if it exists",
+                ]
+            );
+        }
+
+        #[test]
+        fn grep_in_sections() {
+            let to_search = concat!(
+                "//> parse args\n    let a = b.unwrap();\n    //> inner\n        c.unwrap();\n",
+                "    //<\n//<> print\n    d.unwrap();\n//<\n// e.unwrap()\n",
+            );
+            let pattern = regex::Regex::new(r"unwrap\(\)").unwrap();
+            let in_section = regex::Regex::new("^parse").unwrap();
+            let hits = scfmt::grep(to_search, "rs", &pattern, Some(&in_section)).unwrap();
+
+            assert_eq!(
+                hits,
+                vec![
+                    scfmt::GrepHit {
+                        line: 1,
+                        breadcrumb: vec!["parse args".to_owned()],
+                    },
+                    scfmt::GrepHit {
+                        line: 3,
+                        breadcrumb: vec!["parse args".to_owned(), "inner".to_owned()],
+                    },
+                ]
+            );
+
+            // brackets aren't code
+            let pattern = regex::Regex::new("print").unwrap();
+            let in_section = regex::Regex::new("").unwrap();
+            let hits = scfmt::grep(to_search, "rs", &pattern, Some(&in_section)).unwrap();
+            assert_eq!(hits, vec![]);
+        }
+    //<> tags
+        #[test]
+        fn ctags_are_sorted_across_files() {
+            let a_tags = scfmt::tags::tags("//> setup\n    //> b/c\n    //<\n//<\n", "rs").unwrap();
+            let b_tags = scfmt::tags::tags("# > setup\n# <\n# > a\n# <\n", "sh").unwrap();
+            let tag_file =
+                scfmt::tags::ctags(&[("a.rs".to_owned(), a_tags), ("b.sh".to_owned(), b_tags)]);
+
+            let entries: Vec<&str> = tag_file.lines().filter(|x| !x.starts_with('!')).collect();
+            assert_eq!(
+                entries,
+                vec![
+                    "a\tb.sh\t/^# > a$/;\"\ts\tline:3",
+                    "b/c\ta.rs\t/^    \\/\\/> b\\/c$/;\"\ts\tline:2\tsection:setup",
+                    "setup\ta.rs\t/^\\/\\/> setup$/;\"\ts\tline:1",
+                    "setup\tb.sh\t/^# > setup$/;\"\ts\tline:1",
+                ]
+            );
+        }
+
+        #[test]
+        fn etags_byte_offsets() {
+            let to_tag = concat!(
+                "é\n//> tests\n    //>\n        //> validation\n",
+                "        //<\n    //<\n//<\n",
+            );
+            let tags = scfmt::tags::tags(to_tag, "rs").unwrap();
+
+            // untitled sections have no tag, but are still part of the scope
+            assert_eq!(tags.len(), 2);
+            assert_eq!(tags[1].scope, vec!["tests", ""]);
+
+            let tag_file = scfmt::tags::etags(&[("a.rs".to_owned(), tags)]);
+            assert_eq!(
+                tag_file,
+                concat!(
+                    "\x0c\na.rs,59\n//> tests\x7ftests\x012,3\n",
+                    "        //> validation\x7fvalidation\x014,21\n",
+                )
+            );
+        }
+    //<> doc
+        #[test]
+        fn doc_pages_show_sections_as_details() {
+            let to_render = "<a>\n//> a & b\n    x\n//<> c\n    //>\n        y\n    //<\n//<\nz\n";
+            let (page, _) = scfmt::doc::page(to_render, "rs", "<lib>", "index.html").unwrap();
+
+            let body = &page[page.find("<nav>").unwrap()..];
+            assert_eq!(
+                body,
+                concat!(
+                    "<nav><a href=\"index.html\">Index</a></nav>\n<h1>&lt;lib&gt;</h1>\n",
+                    "<pre><span id=\"L1\"><a class=\"ln\" href=\"#L1\">1</a>",
+                    "&lt;a&gt;</span>\n</pre>\n",
+                    "<details id=\"L2\">\n<summary><a class=\"ln\" href=\"#L2\">2</a>",
+                    "a &amp; b</summary>\n",
+                    "<pre><span id=\"L3\"><a class=\"ln\" href=\"#L3\">3</a>",
+                    "    x</span>\n</pre>\n",
+                    "</details>\n",
+                    "<details id=\"L4\">\n<summary><a class=\"ln\" href=\"#L4\">4</a>",
+                    "c</summary>\n",
+                    "<details id=\"L5\">\n<summary><a class=\"ln\" href=\"#L5\">5</a>",
+                    "<span class=\"untitled\">(untitled)</span></summary>\n",
+                    "<pre><span id=\"L6\"><a class=\"ln\" href=\"#L6\">6</a>",
+                    "        y</span>\n</pre>\n",
+                    "</details>\n</details>\n",
+                    "<pre><span id=\"L9\"><a class=\"ln\" href=\"#L9\">9</a>",
+                    "z</span>\n</pre>\n",
+                    "</body>\n</html>\n",
+                )
+            );
+
+            // pages load nothing from the network
+            assert!(!page.contains("http"));
+        }
+
+        #[test]
+        fn doc_index_links_every_section() {
+            let (_, a) = scfmt::doc::page("//> a\n    //> b\n    //<\n//<", "rs", "a", "").unwrap();
+            let (_, c) = scfmt::doc::page("x", "rs", "c", "").unwrap();
+            let index = scfmt::doc::index(&[
+                ("a.rs".to_owned(), "a.rs.html".to_owned(), a),
+                ("c.rs".to_owned(), "c.rs.html".to_owned(), c),
+            ]);
+
+            let body = &index[index.find("<body>").unwrap()..];
+            assert_eq!(
+                body,
+                concat!(
+                    "<body>\n<h1>Index</h1>\n<ul>\n<li><a href=\"a.rs.html\">a.rs</a>\n",
+                    "<ul>\n<li><a href=\"a.rs.html#L1\">a</a>\n",
+                    "<ul>\n<li><a href=\"a.rs.html#L2\">b</a>\n</li>\n</ul>\n</li>\n</ul>\n",
+                    "</li>\n<li><a href=\"c.rs.html\">c.rs</a>\n</li>\n</ul>\n</body>\n</html>\n",
+                )
+            );
+        }
+    //<> markdown
+        #[test]
+        fn markdown_round_trips_test_resources() {
+            for entry in fs::read_dir("./test_resources").unwrap() {
+                let path = entry.unwrap().path();
+                let source = fs::read_to_string(&path).unwrap();
+                let extension = path.extension().unwrap().to_str().unwrap();
+                if scfmt::markdown::is_markdown(extension) || scfmt::notebook::is_notebook(extension) {
+                    continue;
+                }
+
+                let markdown = scfmt::markdown::to_markdown(&source, extension).unwrap();
+                let rebuilt = scfmt::markdown::from_markdown(&markdown).unwrap();
+                assert_eq!(rebuilt, source, "{:?} didn't round trip", path);
+            }
+        }
+
+        #[test]
+        fn markdown_round_trips_unusual_sources() {
+            let sources = [
+                "",
+                "\n",
+                "let a = 0;",
+                "//>\n//<",
+                "// scfmt: labeled-closers\r\n  //>  a  \r\n  x\r\n  //<> b\t\r\n  //< b\r\n",
+                "//> unbalanced\n//<\n//<\n/*\n````\n*/\n",
+                "-- > a -->\n-- <\n",
+                "// scfmt: off\n//> a\n// scfmt: on\n",
+            ];
+
+            for source in sources {
+                let filetype = if source.starts_with("--") {
+                    "lua"
+                } else {
+                    "rs"
+                };
+                let markdown = scfmt::markdown::to_markdown(source, filetype).unwrap();
+                let rebuilt = scfmt::markdown::from_markdown(&markdown).unwrap();
+                assert_eq!(rebuilt, source);
+            }
+        }
+
+        #[test]
+        fn markdown_headings_are_titles() {
+            let source = "//> a\n    //> b\n        x\n    //<\n//<\n";
+            let markdown = scfmt::markdown::to_markdown(source, "rs").unwrap();
+            assert_eq!(
+                markdown,
+                concat!(
+                    "# a\n<!-- scfmt: [\"//\\u003e \",\"\"] -->\n\n",
+                    "## b\n<!-- scfmt: [\"    //\\u003e \",\"\"] -->\n\n",
+                    "```rs\n        x\n```\n\n",
+                    "<!-- scfmt: \"    //<\" -->\n\n<!-- scfmt: \"//<\" -->\n\n",
+                )
+            );
+
+            // editing a heading renames its section, and other text is left out
+            let edited = markdown.replace("## b\n", "Some notes\n\n## c\n");
+            assert_eq!(
+                scfmt::markdown::from_markdown(&edited).unwrap(),
+                "//> a\n    //> c\n        x\n    //<\n//<\n"
+            );
+
+            let unclosed = "```rs\nx\n";
+            assert_eq!(
+                scfmt::markdown::from_markdown(unclosed),
+                Err(ScfmtErr::InvalidMarkdown(1))
+            );
+        }
+    //<> markdown code blocks
+        #[test]
+        fn format_markdown_code_blocks() {
+            let to_format = fs::read_to_string("./test_resources/12_test.md").unwrap();
+            let answer = fs::read_to_string("./test_resources/12_answer.md").unwrap();
+            let formatted = scfmt::format_str(&to_format, "md").unwrap();
+            assert_eq!(answer, formatted);
+        }
+
+        #[test]
+        fn add_and_remove_brackets_in_markdown() {
+            let to_convert = "Text\n\n```rust\n// a\n    let a = 0;\n```";
+
+            let added = scfmt::add_brackets(to_convert, "md").unwrap();
+            assert_eq!(added, "Text\n\n```rust\n//> a\n    let a = 0;\n//<\n```");
+
+            let removed = scfmt::remove_brackets(&added, "markdown").unwrap();
+            assert_eq!(removed, to_convert);
+        }
+
+        #[test]
+        fn markdown_errors_are_on_markdown_lines() {
+            let to_format = "Text\n\n```rust\nlet a = 0;\n//<\n```\n";
+            let formatted = scfmt::format_str(to_format, "md");
+            assert_eq!(formatted, Err(ScfmtErr::CommentClosedNothing(5)));
+        }
+
+        #[test]
+        fn markdown_code_blocks_can_be_turned_off() {
+            let to_format = concat!(
+                "<!-- scfmt: off -->\n```rust\n//> a\nlet a = 0;\n//<\n```\n<!-- scfmt: on -->\n",
+                "```rust\n//> b\nlet b = 0;\n<!-- scfmt: off -->\n//<\n```\n",
+            );
+            let formatted = scfmt::format_str(to_format, "md").unwrap();
+            assert_eq!(
+                formatted,
+                concat!(
+                    "<!-- scfmt: off -->\n```rust\n//> a\nlet a = 0;\n//<\n```\n<!-- scfmt: on -->\n",
+                    "```rust\n//> b\n    let b = 0;\n    <!-- scfmt: off -->\n//<\n```\n",
+                )
+            );
+
+            // the README shows code scfmt gets wrong, so it must be left alone
+            let readme = fs::read_to_string("./README.md").unwrap();
+            assert_eq!(scfmt::format_str(&readme, "md").unwrap(), readme);
+        }
+
+        #[test]
+        fn markdown_code_block_languages() {
+            let to_search = concat!(
+                "```Rust\n```\n```{.c++}\n```\n",
+                "````python foo\n```\n````\n``` `a`\n~~~\n",
+            );
+            let fences = scfmt::markdown::fences(to_search);
+
+            let languages: Vec<(&str, Option<&str>)> = fences
+                .iter()
+                .map(|x| (x.language.as_str(), x.filetype))
+                .collect();
+            assert_eq!(
+                languages,
+                vec![
+                    ("rust", Some("rs")),
+                    ("c++", Some("cpp")),
+                    ("python", None),
+                    ("", None)
+                ]
+            );
+
+            // a fence is only closed by one at least as long, and unclosed fences run to the end
+            assert_eq!(fences[2].body, 5..6);
+            assert_eq!(fences[3].body, 9..9);
+        }
+    //<> doc comments
+        #[test]
+        fn format_doc_comments() {
+            let to_format = fs::read_to_string("./test_resources/13_test.rs").unwrap();
+            let answer = fs::read_to_string("./test_resources/13_answer.rs").unwrap();
+            let formatted = scfmt::format_str(&to_format, "rs").unwrap();
+            assert_eq!(answer, formatted);
+        }
+
+        #[test]
+        fn format_block_doc_comments() {
+            let to_format = "/**\n * ```\n * //> a\n * let a = 0;\n *\n * //<\n * ```\n */\n";
+            let formatted = scfmt::format_str(to_format, "rs").unwrap();
+            assert_eq!(
+                formatted,
+                "/**\n * ```\n * //> a\n *     let a = 0;\n *\n * //<\n * ```\n */\n"
+            );
+
+            // other languages' doc comments aren't read as Markdown
+            let to_format = "/**\n * ```\n * //> a\n * let a = 0;\n * //<\n * ```\n */\n";
+            assert_eq!(scfmt::format_str(to_format, "ts").unwrap(), to_format);
+        }
+
+        #[test]
+        fn doc_comment_lines_keep_their_spacing() {
+            // unchanged lines keep trailing whitespace, and changed lines get the block's spacing
+            let to_format = "///\n/// \n///```\n///let a = 0;\n///```\n";
+            let formatted = scfmt::format_str(to_format, "rs").unwrap();
+            assert_eq!(formatted, to_format);
+
+            let to_format = "/// ```\n/// //>\n///\n///x\n/// //<\n/// ```\n";
+            let formatted = scfmt::format_str(to_format, "rs").unwrap();
+            assert_eq!(
+                formatted,
+                "/// ```\n/// //>\n///\n///     x\n/// //<\n/// ```\n"
+            );
+        }
+
+        #[test]
+        fn unbalanced_doc_comment_examples_are_left_alone() {
+            let to_format = concat!(
+                "/// Docs\n///\n/// ```\n/// //>\n/// let a = 0;\n/// ```\nfn a() {}\n",
+                "/// ```\n/// //> b\n/// let b = 0;\n/// //<\n/// ```\nfn b() {}\n",
+            );
+            let formatted = scfmt::format_str(to_format, "rs").unwrap();
+            assert_eq!(
+                formatted,
+                concat!(
+                    "/// Docs\n///\n/// ```\n/// //>\n/// let a = 0;\n/// ```\nfn a() {}\n",
+                    "/// ```\n/// //> b\n///     let b = 0;\n/// //<\n/// ```\nfn b() {}\n",
+                )
+            );
+        }
+
+        #[test]
+        fn doc_comment_strings_are_not_code() {
+            // lines in multi-line strings, like code passed to a function, aren't brackets
+            let to_format = concat!(
+                "/// ```\n/// let a = \"\n/// //>\n/// x\n/// \\\"//<\";\n",
+                "/// let b = r#\"\n/// //<\n/// \"#;\n/// let c = '\"'; // \"\n",
+                "/// //> c\n/// x\n/// //<\n/// ```\n",
+            );
+            let formatted = scfmt::format_str(to_format, "rs").unwrap();
+            assert_eq!(
+                formatted,
+                concat!(
+                    "/// ```\n/// let a = \"\n/// //>\n/// x\n/// \\\"//<\";\n",
+                    "/// let b = r#\"\n/// //<\n/// \"#;\n/// let c = '\"'; // \"\n",
+                    "/// //> c\n///     x\n/// //<\n/// ```\n",
+                )
+            );
+
+            // a closing bracket after the string closes nothing, so the block is left alone
+            let to_format = "/// ```\n/// let a = \"\n/// //>\n/// \";\n/// //<\n/// ```\n";
+            assert_eq!(scfmt::format_str(to_format, "rs").unwrap(), to_format);
+        }
+
+        #[test]
+        fn doc_comment_prose_is_not_code() {
+            let to_format = "/// //> a\n/// let a = 0;\n/// //<\nfn a() {}\n";
+            assert_eq!(scfmt::format_str(to_format, "rs").unwrap(), to_format);
+        }
+    //<> multi-language files
+        #[test]
+        fn format_vue_regions() {
+            let to_format = fs::read_to_string("./test_resources/14_test.vue").unwrap();
+            let answer = fs::read_to_string("./test_resources/14_answer.vue").unwrap();
+            let formatted = scfmt::format_str(&to_format, "vue").unwrap();
+            assert_eq!(answer, formatted);
+        }
+
+        #[test]
+        fn format_php_regions() {
+            let to_format = concat!(
+                "<?php\n//> setup\n$a = 0;\n//<\n?>\n<p><?php echo $a ?></p>\n",
+                "//> not code\n<script>\n    //> b\n    let b = 0;\n    //<\n</script>\n",
+            );
+            let formatted = scfmt::format_str(to_format, "php").unwrap();
+            assert_eq!(
+                formatted,
+                concat!(
+                    "<?php\n//> setup\n    $a = 0;\n//<\n?>\n<p><?php echo $a ?></p>\n",
+                    "//> not code\n<script>\n    //> b\n        let b = 0;\n    //<\n</script>\n",
+                )
+            );
+
+            // without a <?php tag, a file is plain PHP
+            let formatted = scfmt::format_str("//>\n$a = 0;\n//<\n", "php").unwrap();
+            assert_eq!(formatted, "//>\n    $a = 0;\n//<\n");
+        }
+
+        #[test]
+        fn add_and_remove_brackets_in_regions() {
+            let to_convert = "<p>a</p>\n<script>\n// a\n    let a = 0;\n</script>\n";
+
+            let added = scfmt::add_brackets(to_convert, "svelte").unwrap();
+            assert_eq!(
+                added,
+                "<p>a</p>\n<script>\n//> a\n    let a = 0;\n//<\n</script>\n"
+            );
+
+            let removed = scfmt::remove_brackets(&added, "svelte").unwrap();
+            assert_eq!(removed, to_convert);
+        }
+
+        #[test]
+        fn null_brackets_in_regions() {
+            let to_null = fs::read_to_string("./test_resources/14_test.vue").unwrap();
+            let nulled = scfmt::null_existing_brackets(&to_null, "vue").unwrap();
+
+            // markup isn't code, so its lines are left alone
+            assert!(nulled.contains("    //> template text isn't code\n"));
+            assert!(nulled.contains("//_> setup\n"));
+            assert!(nulled.contains("  //_> colors\n"));
+            assert!(nulled.contains("//_> palette\n"));
+
+            let nulled = scfmt::null_existing_brackets("Text //>\n```rust\n//>\n```\n", "md").unwrap();
+            assert_eq!(nulled, "Text //>\n```rust\n//_>\n```\n");
+        }
+
+        #[test]
+        fn format_range_in_regions() {
+            let to_format = fs::read_to_string("./test_resources/14_test.vue").unwrap();
+
+            // lines of markup are never formatted
+            let formatted = scfmt::format_range(&to_format, "vue", 0..5).unwrap();
+            assert_eq!(formatted, to_format);
+
+            let formatted = scfmt::format_range(&to_format, "vue", 11..12).unwrap();
+            assert_eq!(
+                formatted,
+                to_format.replace("\nconst b = 0;\n", "\n    const b = 0;\n")
+            );
+
+            let to_format = "Text\n```rust\n//>\nlet a = 0;\nlet b = 0;\n//<\n```\n";
+            let formatted = scfmt::format_range(to_format, "md", 4..5).unwrap();
+            assert_eq!(
+                formatted,
+                "Text\n```rust\n//>\nlet a = 0;\n    let b = 0;\n//<\n```\n"
+            );
+        }
+
+        #[test]
+        fn bracket_errors_in_regions() {
+            let to_check = "<p>//<</p>\n<script>\n//> a\n</script>\n<script>\n//<\n</script>\n";
+            let errors = scfmt::bracket_errors(to_check, "html").unwrap();
+            assert_eq!(
+                errors,
+                vec![
+                    ScfmtErr::CommentNeverClosed(3),
+                    ScfmtErr::CommentClosedNothing(6)
+                ]
+            );
+
+            let to_check = "//<\n```rust\n//<\n```\n<!-- scfmt: off -->\n```rust\n//<\n```\n";
+            let errors = scfmt::bracket_errors(to_check, "md").unwrap();
+            assert_eq!(errors, vec![ScfmtErr::CommentClosedNothing(3)]);
+        }
+
+        #[test]
+        fn sections_cant_cross_regions() {
+            let to_format = "<script>\n//> a\n</script>\n<script>\n//<\n</script>\n";
+            let formatted = scfmt::format_str(to_format, "html");
+            assert_eq!(formatted, Err(ScfmtErr::CommentNeverClosed(2)));
+        }
+
+        #[test]
+        fn region_languages() {
+            let to_search = concat!(
+                "<script type=\"text/x-template\">\n<style lang=\"less\">\n</script>\n",
+                "<script>let a = 0;</script>\n<SCRIPT type=module>\n</SCRIPT>\n",
+                "<style lang='scss'>\n</style>\n<style lang=\"stylus\">\n</style>\n",
+            );
+            let regions = scfmt::regions::regions(to_search, "html");
+
+            let languages: Vec<(&str, usize)> = regions.iter().map(|x| (x.filetype, x.line)).collect();
+            assert_eq!(languages, vec![("js", 4), ("scss", 6), ("styl", 8)]);
+
+            // a region that is never closed runs to the end of the file
+            let regions = scfmt::regions::regions("<?php\n$a = 0;\n", "php");
+            assert_eq!(regions[0].body, 1..2);
+        }
+    //<> notebooks
+        #[test]
+        fn format_notebook_cells() {
+            let to_format = fs::read_to_string("./test_resources/15_test.ipynb").unwrap();
+            let answer = fs::read_to_string("./test_resources/15_answer.ipynb").unwrap();
+            let formatted = scfmt::format_str(&to_format, "ipynb").unwrap();
+            assert_eq!(answer, formatted);
+        }
+
+        #[test]
+        fn add_and_remove_brackets_in_notebooks() {
+            let to_convert = concat!(
+                r##"{"cells": [{"cell_type": "code", "source": ["# a\n", "    a = 0\n"]}],"##,
+                r##" "metadata": {"language_info": {"name": "julia"}}}"##,
+            );
+
+            let added = scfmt::add_brackets(to_convert, "ipynb").unwrap();
+            assert_eq!(
+                added,
+                concat!(
+                    r##"{"cells": [{"cell_type": "code", "source": ["#> a\n", "    a = 0\n","##,
+                    r##" "#<\n"]}], "metadata": {"language_info": {"name": "julia"}}}"##,
+                )
+            );
+
+            let removed = scfmt::remove_brackets(&added, "ipynb").unwrap();
+            assert_eq!(removed, to_convert);
+        }
+
+        #[test]
+        fn sections_cant_span_cells() {
+            let to_format = concat!(
+                r##"{"cells": [{"cell_type": "code", "source": "a <- 0"},"##,
+                r##" {"cell_type": "code", "source": "#> a\nb <- 0"},"##,
+                r##" {"cell_type": "code", "source": "#<"}], "metadata": {"kernelspec":"##,
+                r##" {"language": "R"}}}"##,
+            );
+            let formatted = scfmt::format_str(to_format, "ipynb");
+            assert_eq!(
+                formatted,
+                Err(ScfmtErr::InNotebookCell(
+                    2,
+                    Box::new(ScfmtErr::CommentNeverClosed(1))
+                ))
+            );
+        }
+
+        #[test]
+        fn notebooks_scfmt_cant_read() {
+            let formatted = scfmt::format_str("{\"cells\": [", "ipynb");
+            assert_eq!(formatted, Err(ScfmtErr::InvalidNotebook));
+
+            // python is sensitive to indentation, so scfmt doesn't format it
+            let to_format = r#"{"cells": [], "metadata": {"kernelspec": {"language": "python"}}}"#;
+            let formatted = scfmt::format_str(to_format, "ipynb");
+            assert_eq!(
+                formatted,
+                Err(ScfmtErr::IndentationSensitiveKernel("python".to_owned()))
+            );
+
+            let to_format = r#"{"cells": [], "metadata": {"kernelspec": {"language": "cobol"}}}"#;
+            let formatted = scfmt::format_str(to_format, "ipynb");
+            assert_eq!(
+                formatted,
+                Err(ScfmtErr::UnsupportedKernel("cobol".to_owned()))
+            );
+
+            let nulled = scfmt::null_existing_brackets(r#"{"cells": []}"#, "ipynb");
+            assert_eq!(nulled, Err(ScfmtErr::UnsupportedKernel(String::new())));
+        }
+
+        #[test]
+        fn python_notebooks_keep_their_indentation() {
+            let to_convert = concat!(
+                r##"{"cells": [{"cell_type": "code", "source": "# a\nif a:\n    # b\n        b = 0"}],"##,
+                r##" "metadata": {"kernelspec": {"language": "python"}}}"##,
+            );
+
+            let added = scfmt::add_brackets(to_convert, "ipynb").unwrap();
+            let source = r##""source": "# a\nif a:\n    #> b\n        b = 0\n    #<""##;
+            assert!(added.contains(source));
+
+            let nulled = scfmt::null_existing_brackets(&added, "ipynb").unwrap();
+            assert!(nulled.contains(r##""source": "# a\nif a:\n    #_> b\n        b = 0\n    #_<""##));
+
+            let removed = scfmt::remove_brackets(&added, "ipynb").unwrap();
+            assert_eq!(removed, to_convert);
+        }
+
+        #[test]
+        fn null_brackets_in_notebooks() {
+            let to_null = concat!(
+                r##"{"cells": [{"cell_type": "markdown", "source": "#> text"},"##,
+                r##" {"cell_type": "code", "source": ["#> a\n", "a <- 0\n", "#<"]}],"##,
+                r##" "metadata": {"kernelspec": {"language": "R"}}}"##,
+            );
+
+            // markdown cells aren't code, so only the code cell's brackets are nulled
+            let nulled = scfmt::null_existing_brackets(to_null, "ipynb").unwrap();
+            assert_eq!(
+                nulled,
+                concat!(
+                    r##"{"cells": [{"cell_type": "markdown", "source": "#> text"},"##,
+                    r##" {"cell_type": "code", "source": ["#_> a\n", "a <- 0\n", "#_<"]}],"##,
+                    r##" "metadata": {"kernelspec": {"language": "R"}}}"##,
+                )
+            );
+        }
+    //<> converting fold markers
+        #[test]
+        fn region_markers_to_brackets() {
+            let to_convert = concat!(
+                "// #region setup\nlet a = 0;\n//#region inner\nlet b = 0;\n",
+                "//#endregion\n// #endregion\n// region of code\n",
+            );
+            let converted =
+                scfmt::markers_to_brackets(to_convert, "ts", scfmt::Markers::Region).unwrap();
+            assert_eq!(
+                converted,
+                concat!(
+                    "//> setup\n    let a = 0;\n    //> inner\n",
+                    "        let b = 0;\n    //<\n//<\n// region of code\n",
+                )
+            );
+        }
+
+        #[test]
+        fn csharp_region_directives() {
+            let to_convert = "#region Fields\nint a;\n#endregion\n";
+            let converted =
+                scfmt::markers_to_brackets(to_convert, "cs", scfmt::Markers::Region).unwrap();
+            assert_eq!(converted, "//> Fields\n    int a;\n//<\n");
+
+            let converted =
+                scfmt::brackets_to_markers(&converted, "cs", scfmt::Markers::Region).unwrap();
+            assert_eq!(converted, "#region Fields\n    int a;\n#endregion\n");
+        }
+
+        #[test]
+        fn editor_fold_and_vim_markers_to_brackets() {
+            let to_convert = concat!(
+                "// <editor-fold defaultstate=\"collapsed\" desc=\"Getters\">\n",
+                "int a;\n// </editor-fold>",
+            );
+            let converted =
+                scfmt::markers_to_brackets(to_convert, "java", scfmt::Markers::EditorFold).unwrap();
+            assert_eq!(converted, "//> Getters\n    int a;\n//<");
+
+            let to_convert = "# setup {{{1\na = 0\n# }}}1";
+            let converted = scfmt::markers_to_brackets(to_convert, "sh", scfmt::Markers::Vim).unwrap();
+            assert_eq!(converted, "#> setup\n    a = 0\n#<");
+        }
+
+        #[test]
+        fn balanced_vim_braces_arent_markers() {
+            let to_convert = "# setup {{{\n# list: {{{a}}}\na = 0\n# }}}\n";
+            let converted = scfmt::markers_to_brackets(to_convert, "sh", scfmt::Markers::Vim).unwrap();
+            assert_eq!(converted, "#> setup\n    # list: {{{a}}}\n    a = 0\n#<\n");
+        }
+
+        #[test]
+        fn unbalanced_markers_are_reported() {
+            let converted = scfmt::markers_to_brackets("// #region a\n", "ts", scfmt::Markers::Region);
+            assert_eq!(converted, Err(ScfmtErr::CommentNeverClosed(1)));
+        }
+
+        #[test]
+        fn brackets_to_markers_splits_close_and_open() {
+            let to_convert = "//> a\n    x\n//<> b\n    y\n//<\n";
+
+            let converted =
+                scfmt::brackets_to_markers(to_convert, "rs", scfmt::Markers::EditorFold).unwrap();
+            assert_eq!(
+                converted,
+                concat!(
+                    "// <editor-fold desc=\"a\">\n    x\n// </editor-fold>\n",
+                    "// <editor-fold desc=\"b\">\n    y\n// </editor-fold>\n",
+                )
+            );
+
+            let converted =
+                scfmt::brackets_to_markers(to_convert, "rs", scfmt::Markers::Region).unwrap();
+            let round_trip =
+                scfmt::markers_to_brackets(&converted, "rs", scfmt::Markers::Region).unwrap();
+            assert_eq!(round_trip, "//> a\n    x\n//<\n//> b\n    y\n//<\n");
+        }
+    //<> language server
+        /// Runs a scripted language server session, returning every message the server sent
+        fn lsp_session(messages: &[serde_json::Value]) -> Vec<serde_json::Value> {
+            //> frame client messages
+                let mut input = String::new();
+                for message in messages {
+                    let content = message.to_string();
+                    input.push_str(&format!(
+                        "Content-Length: {}\r\n\r\n{}",
+                        content.len(),
+                        content
+                    ));
+                }
+            //<
+
+            let mut output = Vec::new();
+            scfmt::lsp::run(&mut input.as_bytes(), &mut output).unwrap();
+
+            //> unframe server messages
+                let output = String::from_utf8(output).unwrap();
+                output
+                    .split("Content-Length: ")
+                    .filter(|x| !x.is_empty())
+                    .map(|x| serde_json::from_str(x.split_once("\r\n\r\n").unwrap().1).unwrap())
+                    .collect()
+            //<
+        }
+
+        fn lsp_open(uri: &str, text: &str) -> serde_json::Value {
+            json!({
+                "jsonrpc": "2.0",
+                "method": "textDocument/didOpen",
+                "params": { "textDocument": { "uri": uri, "languageId": "rust", "version": 1, "text": text } },
+            })
+        }
+
+        #[test]
+        fn lsp_formats_and_reports_diagnostics() {
+            let replies = lsp_session(&[
+                json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} }),
+                json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} }),
+                lsp_open("file:///a.rs", "//>\nlet a = 0;\n//<\n//<"),
+                lsp_open("file:///b.rs", "//>\nlet a = 0;\n//<\n"),
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": 2,
+                    "method": "textDocument/formatting",
+                    "params": { "textDocument": { "uri": "file:///b.rs" }, "options": {} },
+                }),
+                json!({ "jsonrpc": "2.0", "id": 3, "method": "shutdown" }),
+                json!({ "jsonrpc": "2.0", "method": "exit" }),
+            ]);
+
+            assert_eq!(replies.len(), 5);
+            assert_eq!(
+                replies[0]["result"]["capabilities"]["documentFormattingProvider"],
+                true
+            );
+
+            //> diagnostics
+                assert_eq!(replies[1]["method"], "textDocument/publishDiagnostics");
+                let diagnostics = &replies[1]["params"]["diagnostics"];
+                assert_eq!(diagnostics.as_array().unwrap().len(), 1);
+                assert_eq!(diagnostics[0]["message"], "comment closed nothing");
+                assert_eq!(diagnostics[0]["range"]["start"]["line"], 3);
+
+                assert_eq!(replies[2]["params"]["diagnostics"], json!([]));
+
+            //<> formatting
+                assert_eq!(replies[3]["id"], 2);
+                assert_eq!(
+                    replies[3]["result"][0]["newText"],
+                    "//>\n    let a = 0;\n//<\n"
+                );
+                assert_eq!(
+                    replies[3]["result"][0]["range"]["end"],
+                    json!({ "line": 3, "character": 0 })
+                );
+
+                assert_eq!(replies[4]["id"], 3);
+                assert_eq!(replies[4]["result"], serde_json::Value::Null);
+            //<
+        }
+
+        #[test]
+        fn lsp_range_formatting() {
+            let replies = lsp_session(&[
+                lsp_open("file:///a.rs", "//>\nlet a = 0;\nlet b = 0;\n//<"),
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "method": "textDocument/rangeFormatting",
+                    "params": {
+                        "textDocument": { "uri": "file:///a.rs" },
+                        "range": { "start": { "line": 1, "character": 0 }, "end": { "line": 2, "character": 0 } },
+                        "options": {},
+                    },
+                }),
+            ]);
+
+            assert_eq!(
+                replies[1]["result"][0]["newText"],
+                "//>\n    let a = 0;\nlet b = 0;\n//<"
+            );
+        }
+
+        #[test]
+        fn lsp_only_reads_embedded_code() {
+            let text = "<p>//<</p>\n<script>\n//>\nlet a = 0;\nlet b = 0;\n//<\n</script>\n";
+            let replies = lsp_session(&[
+                lsp_open("file:///a.vue", text),
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "method": "textDocument/rangeFormatting",
+                    "params": {
+                        "textDocument": { "uri": "file:///a.vue" },
+                        "range": { "start": { "line": 0, "character": 0 }, "end": { "line": 3, "character": 3 } },
+                        "options": {},
+                    },
+                }),
+            ]);
+
+            assert_eq!(replies[0]["params"]["diagnostics"], json!([]));
+            assert_eq!(
+                replies[1]["result"][0]["newText"],
+                "<p>//<</p>\n<script>\n//>\n    let a = 0;\nlet b = 0;\n//<\n</script>\n"
+            );
+        }
+
+        #[test]
+        fn lsp_code_actions() {
+            let replies = lsp_session(&[
+                lsp_open("file:///a.rs", "//>\n    let a = 0;\n//<"),
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "method": "textDocument/codeAction",
+                    "params": {
+                        "textDocument": { "uri": "file:///a.rs" },
+                        "range": { "start": { "line": 0, "character": 0 }, "end": { "line": 0, "character": 0 } },
+                        "context": { "diagnostics": [] },
+                    },
+                }),
+                json!({ "jsonrpc": "2.0", "id": 2, "method": "unknown/method" }),
+            ]);
+
+            //> add brackets makes no change, so only two actions are offered
+                let actions = replies[1]["result"].as_array().unwrap();
+                assert_eq!(actions.len(), 2);
+                assert_eq!(actions[0]["title"], "Remove brackets");
+                assert_eq!(
+                    actions[0]["edit"]["changes"]["file:///a.rs"][0]["newText"],
+                    "//\n    let a = 0;"
+                );
+                assert_eq!(actions[1]["title"], "Nullify brackets");
+            //<
+
+            assert_eq!(replies[2]["error"]["code"], -32601);
+        }
+
+        #[test]
+        fn lsp_folding_ranges_and_symbols() {
+            let replies = lsp_session(&[
+                lsp_open("file:///a.rs", "//> a\n    x\n//<"),
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "method": "textDocument/foldingRange",
+                    "params": { "textDocument": { "uri": "file:///a.rs" } },
+                }),
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": 2,
+                    "method": "textDocument/documentSymbol",
+                    "params": { "textDocument": { "uri": "file:///a.rs" } },
+                }),
+            ]);
+
+            assert_eq!(
+                replies[1]["result"],
+                json!([{ "startLine": 0, "endLine": 2, "kind": "region" }])
+            );
+            assert_eq!(replies[2]["result"][0]["name"], "a");
+            assert_eq!(
+                replies[2]["result"][0]["range"]["end"],
+                json!({ "line": 2, "character": 3 })
+            );
+        }
+    //<
+    #[test]
+    fn nullify_brackets() {
+        let formatted =
+            scfmt::null_existing_brackets("//>\n    //>\n//\n    //<\n//<", "rs").unwrap();
+        assert_eq!(formatted, "//_>\n    //_>\n//\n    //_<\n//_<");
+    }
+
+    #[test]
+    fn nullify_brackets_ignores_non_comments() {
+        let formatted = scfmt::null_existing_brackets("//>\n> a\n//<", "rs").unwrap();
+        assert_eq!(formatted, "//_>\n> a\n//_<");
+    }
+
+    #[test]
+    fn incompatible_file_type() {
+        let result = scfmt::format_str("", "");
+        assert_eq!(result, Err(ScfmtErr::IncompatibleFileType));
+
+        let result = scfmt::add_brackets("", "");
+        assert_eq!(result, Err(ScfmtErr::IncompatibleFileType));
+
+        let result = scfmt::remove_brackets("", "");
+        assert_eq!(result, Err(ScfmtErr::IncompatibleFileType));
+
+        let result = scfmt::null_existing_brackets("", "");
+        assert_eq!(result, Err(ScfmtErr::IncompatibleFileType));
+    }
+
+    #[test]
+    fn determine_whitespace_type_gets_best_result() {
+        let to_format = fs::read_to_string("./test_resources/11_test.rs").unwrap();
+        let formatted = scfmt::format_str(&to_format, "rs").unwrap();
+        let answer = fs::read_to_string("./test_resources/11_answer.rs").unwrap();
+        assert_eq!(answer, formatted);
+    }
+}
+
+/// Contains functions for formatting strucuted comments in files and strings
+pub mod scfmt {
+
+    pub mod doc;
+    pub mod doc_comments;
+    pub mod lint;
+    pub mod lsp;
+    pub mod markdown;
+    pub mod notebook;
+    pub mod refactor;
+    pub mod regions;
+    pub mod tags;
+
+    use glob::{glob, GlobError, Pattern};
+    use phf::phf_map;
+    use regex::Regex;
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::fs;
+    use std::fs::File;
+    use std::io::Write;
+    use std::ops::Range;
+    use std::path::PathBuf;
+
+    static EXTENSION_TO_COMMENT_STARTER_MAP: phf::Map<&'static str, &'static str> = phf_map! {
+
+        //> ada
+            "adb" => "--",
+            "ads" => "--",
+        //<
+        // Assembly
+        "asm" => ";",
+        // AL
+        "al" => "//",
+        "bib" => "%",
+        "brs" => "'",
+        // C
+        "c" => "//",
+        "cfc" => "//",
+        // Clojure
+        "clj" => ";",
+        // Apex
+        "cls" => "//",
+        "cpp" => "//",
+        //> C#
+            "cs" => "//",
+            "csx" => "//",
+        //<
+        "d" => "//",
+        // Dart
+        "dart" => "//",
+        "do" => "*",
+        "ex" => "#",
+        "elm" => "--",
+        "gd" => "#",
+        "gen" => "\\",
+        // Go
+        "go" => "//",
+        "graphql" => "#",
+        "groovy" => "//",
+        // C header files
+        "h" => "//",
+        //> Haskell
+            "hs" => "--",
+            "lhs" => "--",
+        //<
+        // Java
+        "java" => "//",
+        //> JavaScript
+            "js" => "//",
+            "cjs" => "//",
+            "mjs" => "//",
+        //<
+        // Julia
+        "jl" => "#",
+        "jsonc" => "//",
+        "less" => "//",
+        "lisp" => ";;",
+        "lua" => "--",
+        // MATLAB
+        "m" => "%",
+        "nim" => "#",
+        // Pascal
+        "pas" => "//",
+        // PHP
+        "php" => "//",
+        "pig" => "--",
+        "plsql" => "--",
+        "pp" => "//",
+        "ps1" => "#",
+        "pu" => "'",
+        "q" => "--",
+        // R
+        "r" => "#",
+        "rkt" => ";",
+        // Rust
+        "rs" => "//",
+        "sas" => "*",
+        "sass" => "//",
+        "scss" => "//",
+        "shader" => "//",
+        // Bash
+        "sh" => "#",
+        // Solidity
+        "sol" => "//",
+        "styl" => "//",
+        "svelte" => "//",
+        "tcl" => "#",
+        "toml" => "#",
+        //> TypeScript
+            "ts" => "//",
+            "tsx" => "//",
+        //<
+        "vala" => "//",
+        "v" => "//",
+        "vhdl" => "--",
+        "vue" => "//",
+        "yaml" => "#",
+    };
+
+    /// Enum used to represent scfmt errors
+    #[derive(PartialEq, Debug, Clone)]
+    pub enum ScfmtErr {
+        IncompatibleFileType,
+        CommentClosedNothing(usize),
+        CommentNeverClosed(usize),
+        ClosingLabelMismatch(usize),
+        SectionNotFound(String),
+        LinesCrossSection(usize),
+        InvalidLineRange,
+        LineNotInSection(usize),
+        NoNextSection(usize),
+        SectionMovedIntoItself(usize),
+        InvalidMarkdown(usize),
+        InvalidNotebook,
+        UnsupportedKernel(String),
+        IndentationSensitiveKernel(String),
+        InNotebookCell(usize, Box<ScfmtErr>),
+        CantConvertOsString,
+        CantReadFileAsString,
+        CantCreatFile,
+        CantWriteToFile,
+    }
+
+    /// The kinds of brackets a structured comment can start with
+    #[derive(PartialEq, Debug, Clone, Copy)]
+    pub enum Bracket {
+        /// ``//>``, opens a section
+        Open,
+        /// ``//<>``, closes a section and opens its next sibling
+        CloseAndOpen,
+        /// ``//<``, closes a section
+        Close,
+    }
+
+    /// A bracketed structured comment, and the lines it talks about
+    #[derive(PartialEq, Debug, Clone)]
+    pub struct Section {
+        /// Zero based index of the line holding the ``//>`` or ``//<>`` that opens the section
+        pub header_line: usize,
+        /// Text after the opening bracket, without surrounding whitespace
+        pub title: String,
+        /// How many sections this one is nested inside of
+        pub depth: usize,
+        /// Amount of leading whitespace the section's brackets are indented by
+        pub indentation: usize,
+        /// Whether the section was opened by ``//>`` or ``//<>``
+        pub opened_by: Bracket,
+        /// Zero based index of the line holding the ``//<`` or ``//<>`` that closes the section
+        pub close_line: usize,
+        /// Whether the section was closed by ``//<`` or ``//<>``
+        pub closed_by: Bracket,
+        /// Zero based range of the lines between the section's brackets
+        pub body: Range<usize>,
+        /// Sections nested directly inside this one
+        pub children: Vec<Section>,
+    }
+
+    impl Section {
+        /// Zero based range of lines covered by the section, including both of its brackets
+        pub fn span(&self) -> Range<usize> {
+            self.header_line..self.close_line + 1
+        }
+
+        /// Zero based range of lines that belong to the section
+        ///
+        /// Unlike ``span``, this doesn't include a closing ``//<>``, as that line belongs to the section it opens.
+        pub fn lines(&self) -> Range<usize> {
+            match self.closed_by {
+                Bracket::CloseAndOpen => self.header_line..self.close_line,
+                _ => self.span(),
+            }
+        }
+
+        /// Iterates over this section and all sections nested inside it, in the order they appear
+        pub fn iter(&self) -> Sections<'_> {
+            Sections { stack: vec![self] }
+        }
+
+        /// Finds a nested section by the titles of the sections leading to it
+        pub fn find(&self, path: &[&str]) -> Option<&Section> {
+            find_section(&self.children, path)
+        }
+    }
+
+    /// The tree of bracketed structured comments in a string
+    #[derive(PartialEq, Debug, Clone, Default)]
+    pub struct Document {
+        /// Sections that are not nested inside of any other section
+        pub sections: Vec<Section>,
+    }
+
+    impl Document {
+        /// Iterates over every section in the document, in the order they appear
+        pub fn iter(&self) -> Sections<'_> {
+            Sections {
+                stack: self.sections.iter().rev().collect(),
+            }
+        }
+
+        /// Finds a section by the titles of the sections leading to it
+        pub fn find(&self, path: &[&str]) -> Option<&Section> {
+            find_section(&self.sections, path)
+        }
+
+        /// Finds a section by a path of titles separated by '/', such as ``"tests/basic tests"``
+        ///
+        /// Titles that contain a '/' can only be found with ``find``.
+        pub fn find_path(&self, path: &str) -> Option<&Section> {
+            let path: Vec<&str> = path.split('/').collect();
+            self.find(&path)
+        }
+
+        /// Finds every section whose path of titles matches `path`, in the order they appear
+        ///
+        /// Like ``find_path``, titles are separated by '/', but each one may be a glob pattern such as ``"tests/*empty*"``.
+        pub fn find_matching(&self, path: &str) -> Vec<&Section> {
+            let patterns: Vec<Pattern> = path
+                .split('/')
+                .map(|x| {
+                    Pattern::new(x).unwrap_or_else(|_| Pattern::new(&Pattern::escape(x)).unwrap())
+                })
+                .collect();
+
+            let mut found = Vec::new();
+            find_matching_sections(&self.sections, &patterns, &mut found);
+            found
+        }
+
+        /// Returns every section in the order they are closed. Nested sections come before the sections they are in.
+        fn iter_closing_order(&self) -> Vec<&Section> {
+            fn push_closing_order<'a>(sections: &'a [Section], list: &mut Vec<&'a Section>) {
+                for section in sections {
+                    push_closing_order(&section.children, list);
+                    list.push(section);
+                }
+            }
+
+            let mut list = Vec::new();
+            push_closing_order(&self.sections, &mut list);
+            list
+        }
+    }
+
+    /// Iterator over sections and their nested sections, in the order they appear
+    pub struct Sections<'a> {
+        stack: Vec<&'a Section>,
+    }
+
+    impl<'a> Iterator for Sections<'a> {
+        type Item = &'a Section;
+
+        fn next(&mut self) -> Option<&'a Section> {
+            let section = self.stack.pop()?;
+            self.stack.extend(section.children.iter().rev());
+            Some(section)
+        }
+    }
+
+    fn find_section<'a>(sections: &'a [Section], path: &[&str]) -> Option<&'a Section> {
+        let (first, rest) = path.split_first()?;
+        let section = sections.iter().find(|x| x.title == *first)?;
+
+        if rest.is_empty() {
+            Some(section)
+        } else {
+            find_section(&section.children, rest)
+        }
+    }
+
+    fn find_matching_sections<'a>(
+        sections: &'a [Section],
+        patterns: &[Pattern],
+        found: &mut Vec<&'a Section>,
+    ) {
+        let (first, rest) = match patterns.split_first() {
+            Some(x) => x,
+            None => return,
+        };
+
+        for section in sections.iter().filter(|x| first.matches(&x.title)) {
+            if rest.is_empty() {
+                found.push(section);
+            } else {
+                find_matching_sections(&section.children, rest, found);
+            }
+        }
+    }
+
+    /// Returns which bracket a line starts with, if it is a bracketed comment
+    fn bracket_of_line(line: &str, comment_starter: &str) -> Option<Bracket> {
+        let (_, line_no_leading_spaces) = chop_off_beginning_spaces(line);
+        let (is_a_comment, _, line_no_comment_starter) =
+            remove_comment_notation_if_it_exists(line_no_leading_spaces, comment_starter);
+
+        if !is_a_comment {
+            None
+        } else if line_no_comment_starter.starts_with("<>") {
+            Some(Bracket::CloseAndOpen)
+        } else if line_no_comment_starter.starts_with('>') {
+            Some(Bracket::Open)
+        } else if line_no_comment_starter.starts_with('<') {
+            Some(Bracket::Close)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the text of a bracketed comment that comes after its bracket
+    fn text_after_bracket(line: &str, comment_starter: &str) -> String {
+        let (_, line_no_leading_spaces) = chop_off_beginning_spaces(line);
+        let (_, _, line_no_comment_starter) =
+            remove_comment_notation_if_it_exists(line_no_leading_spaces, comment_starter);
+
+        let bracket_len = match bracket_of_line(line, comment_starter) {
+            Some(Bracket::CloseAndOpen) => 2,
+            Some(_) => 1,
+            None => 0,
+        };
+
+        line_no_comment_starter[bracket_len..].to_owned()
+    }
+
+    /// Parses the bracketed structured comments of a string into a tree of sections
+    ///
+    /// # Arguments
+    ///
+    /// * `str` - A string slice to be parsed
+    /// * `filetype` - A string slice of the file extension representing what language arg `str` is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use scfmt::scfmt::parse;
+    ///
+    /// let document = parse("//> tests\n    //> basic tests\n    //<\n//<", "rs").unwrap();
+    /// let section = document.find_path("tests/basic tests").unwrap();
+    ///
+    /// assert_eq!(section.header_line, 1);
+    /// assert_eq!(section.depth, 1);
+    /// ```
+    pub fn parse(str: &str, filetype: &str) -> Result<Document, ScfmtErr> {
+        // determine if file compatible
+        let comment_starter = match EXTENSION_TO_COMMENT_STARTER_MAP.get(filetype) {
+            Some(x) => *x,
+            None => return Err(ScfmtErr::IncompatibleFileType),
+        };
+
+        parse_with_comment_starter(str, comment_starter)
+    }
+
+    /// ``scfmt:`` comments that control what scfmt may change
+    #[derive(PartialEq, Debug, Clone)]
+    enum Pragma {
+        /// ``// scfmt: off``, lines after it are left alone
+        Off,
+        /// ``// scfmt: on``, ends a ``scfmt: off`` region
+        On,
+        /// ``// scfmt: ignore-file``, the whole file is left alone
+        IgnoreFile,
+        /// ``// scfmt: labeled-closers``, text after a ``//<`` is a label that must match the title of the section it closes
+        LabeledClosers,
+        /// ``// scfmt: allow(empty-section, no-title)``, the listed lint rules aren't reported on the next line
+        Allow(Vec<String>),
+    }
+
+    fn pragma_of_line(line: &str, comment_starter: &str) -> Option<Pragma> {
+        let (_, line_no_leading_spaces) = chop_off_beginning_spaces(line);
+        let (is_a_comment, _, line_no_comment_starter) =
+            remove_comment_notation_if_it_exists(line_no_leading_spaces, comment_starter);
+
+        if !is_a_comment {
+            return None;
+        }
+
+        let pragma = line_no_comment_starter
+            .trim()
+            .strip_prefix("scfmt:")?
+            .trim();
+
+        if let Some(rules) = pragma
+            .strip_prefix("allow(")
+            .and_then(|x| x.strip_suffix(')'))
+        {
+            let rules = rules.split(',').map(|x| x.trim().to_owned()).collect();
+            return Some(Pragma::Allow(rules));
+        }
+
+        match pragma {
+            "off" => Some(Pragma::Off),
+            "on" => Some(Pragma::On),
+            "ignore-file" => Some(Pragma::IgnoreFile),
+            "labeled-closers" => Some(Pragma::LabeledClosers),
+            _ => None,
+        }
+    }
+
+    /// Returns true if scfmt should leave all of a string alone
+    ///
+    /// This is the case if it has a ``scfmt: ignore-file`` comment, or says it is ``@generated`` in the comments at its top.
+    fn file_is_ignored(str: &str, comment_starter: &str) -> bool {
+        //> ignore-file pragma
+            if str
+                .lines()
+                .any(|line| pragma_of_line(line, comment_starter) == Some(Pragma::IgnoreFile))
+            {
+                return true;
+            }
+
+        //<> @generated header
+            for line in str.lines() {
+                if line_is_only_whitepace(line) {
+                    continue;
+                }
+
+                let (_, line_no_leading_spaces) = chop_off_beginning_spaces(line);
+                let (is_a_comment, _, _) =
+                    remove_comment_notation_if_it_exists(line_no_leading_spaces, comment_starter);
+
+                // the header ends at the first line of code
+                if !is_a_comment {
+                    break;
+                }
+
+                if line.contains("@generated") {
+                    return true;
+                }
+            }
+        //<
+
+        false
+    }
+
+    /// Returns true if a string opted in to labeled closers with a ``scfmt: labeled-closers`` comment
+    fn closers_are_labeled(str: &str, comment_starter: &str) -> bool {
+        str.lines()
+            .any(|line| pragma_of_line(line, comment_starter) == Some(Pragma::LabeledClosers))
+    }
+
+    /// Returns which lines are in a ``scfmt: off`` region, including the ``scfmt: off`` and ``scfmt: on`` lines themselves
+    fn lines_turned_off(str: &str, comment_starter: &str) -> Vec<bool> {
+        let mut turned_off = false;
+
+        str.lines()
+            .map(|line| match pragma_of_line(line, comment_starter) {
+                Some(Pragma::Off) => {
+                    turned_off = true;
+                    true
+                }
+                Some(Pragma::On) => {
+                    let was_turned_off = turned_off;
+                    turned_off = false;
+                    was_turned_off
+                }
+                _ => turned_off,
+            })
+            .collect()
+    }
+
+    /// Determines the whitespace type of a string, from only the lines that aren't turned off
+    fn determine_turned_on_whitespace_type(str: &str, turned_off: &[bool]) -> (char, usize) {
+        let turned_on_lines: Vec<&str> = str
+            .lines()
+            .zip(turned_off)
+            .map(|(line, turned_off)| if *turned_off { "" } else { line })
+            .collect();
+
+        determine_whitespace_type(&turned_on_lines.join("\n"))
+    }
+
+    fn parse_with_comment_starter(str: &str, comment_starter: &str) -> Result<Document, ScfmtErr> {
+        let (document, errors) = parse_leniently(str, comment_starter);
+
+        //> report the same error formatting would stop at
+            let closed_nothing = errors
+                .iter()
+                .find(|x| matches!(x, ScfmtErr::CommentClosedNothing(_)));
+            if let Some(err) = closed_nothing {
+                return Err(err.clone());
+            }
+
+            // the most nested comment that was never closed
+            let never_closed = errors
+                .iter()
+                .rfind(|x| matches!(x, ScfmtErr::CommentNeverClosed(_)));
+            if let Some(err) = never_closed {
+                return Err(err.clone());
+            }
+
+            if let Some(err) = errors.first() {
+                return Err(err.clone());
+            }
+        //<
+
+        Ok(document)
+    }
+
+    /// Parses as much of a string's section tree as possible, returning every bracket error found along the way
+    ///
+    /// A ``//<`` that closes nothing is skipped, and a ``//<>`` that closes nothing opens a section like ``//>``. Sections that are never closed are left out, but sections nested inside them are kept. Brackets in ``scfmt: off`` regions are ignored.
+    ///
+    /// If the string opted in to labeled closers, a ``//<`` whose label doesn't match the title of the section it closes is also reported.
+    fn parse_leniently(str: &str, comment_starter: &str) -> (Document, Vec<ScfmtErr>) {
+        let mut document = Document::default();
+        let mut errors = Vec::new();
+        let turned_off = lines_turned_off(str, comment_starter);
+        let labeled = closers_are_labeled(str, comment_starter);
+        // sections that have been opened, but not yet closed
+        let mut open_sections: Vec<Section> = Vec::new();
+
+        /// Closes the most recently opened section and adds it to its parent. Returns the closed section's indentation.
+        fn close_section(
+            open_sections: &mut Vec<Section>,
+            document: &mut Document,
+            line: usize,
+            closed_by: Bracket,
+        ) -> Result<usize, ScfmtErr> {
+            let mut section = match open_sections.pop() {
+                Some(x) => x,
+                None => return Err(ScfmtErr::CommentClosedNothing(line + 1)),
+            };
+
+            section.close_line = line;
+            section.closed_by = closed_by;
+            section.body = section.header_line + 1..line;
+            let indentation = section.indentation;
+
+            match open_sections.last_mut() {
+                Some(parent) => parent.children.push(section),
+                None => document.sections.push(section),
+            }
+
+            Ok(indentation)
+        }
+
+        for (i, line) in str.lines().enumerate() {
+            let bracket = match bracket_of_line(line, comment_starter) {
+                Some(x) if !turned_off[i] => x,
+                _ => continue,
+            };
+
+            //> find where the new section would start, closing the previous one if needed
+                let own_indentation = chop_off_beginning_spaces(line).0.unwrap();
+                let indentation = match bracket {
+                    Bracket::Open => own_indentation,
+                    Bracket::CloseAndOpen => {
+                        match close_section(&mut open_sections, &mut document, i, bracket) {
+                            Ok(x) => x,
+                            Err(err) => {
+                                errors.push(err);
+                                own_indentation
+                            }
+                        }
+                    }
+                    Bracket::Close => {
+                        let label = text_after_bracket(line, comment_starter);
+                        let label = label.trim();
+                        let mismatched = match open_sections.last() {
+                            Some(section) => labeled && !label.is_empty() && label != section.title,
+                            None => false,
+                        };
+
+                        if let Err(err) = close_section(&mut open_sections, &mut document, i, bracket) {
+                            errors.push(err);
+                        } else if mismatched {
+                            errors.push(ScfmtErr::ClosingLabelMismatch(i + 1));
+                        }
+                        continue;
+                    }
+                };
+
+            //<> open a new section
+                open_sections.push(Section {
+                    header_line: i,
+                    title: text_after_bracket(line, comment_starter).trim().to_owned(),
+                    depth: open_sections.len(),
+                    indentation,
+                    opened_by: bracket,
+                    close_line: i,
+                    closed_by: Bracket::Close,
+                    body: i + 1..i + 1,
+                    children: Vec::new(),
+                });
+            //<
+        }
+
+        //> report sections that were never closed, keeping what was nested in them
+            let mut never_closed = Vec::new();
+            while let Some(section) = open_sections.pop() {
+                never_closed.push(ScfmtErr::CommentNeverClosed(section.header_line + 1));
+
+                match open_sections.last_mut() {
+                    Some(parent) => parent.children.extend(section.children),
+                    None => document.sections.extend(section.children),
+                }
+            }
+            never_closed.reverse();
+            errors.extend(never_closed);
+        //<
+
+        (document, errors)
+    }
+
+    /// Returns every unbalanced bracket in a string, instead of only the first one like ``format_str`` does
+    ///
+    /// Labeled closers that don't match their section are included, if the string opted in to them with ``scfmt: labeled-closers``. In Markdown files and files mixing languages, only the code ``format_str`` would format is checked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use scfmt::scfmt::{bracket_errors, ScfmtErr};
+    ///
+    /// let errors = bracket_errors("//<\n//>\n", "rs").unwrap();
+    ///
+    /// assert_eq!(
+    ///     errors,
+    ///     vec![ScfmtErr::CommentClosedNothing(1), ScfmtErr::CommentNeverClosed(2)]
+    /// );
+    /// ```
+    pub fn bracket_errors(str: &str, filetype: &str) -> Result<Vec<ScfmtErr>, ScfmtErr> {
+        //> collect the errors of embedded code, visiting it the same way formatting does
+            let embedded_errors = RefCell::new(Vec::new());
+            let collect_errors = |code: &str, filetype: &str, start: usize| {
+                for err in bracket_errors(code, filetype)? {
+                    embedded_errors
+                        .borrow_mut()
+                        .push(markdown::offset_err(err, start));
+                }
+                Ok(code.to_owned())
+            };
+
+            // markdown only has code in its code blocks
+            if markdown::is_markdown(filetype) {
+                markdown::transform_fences_as(
+                    str,
+                    &|fence| fence.filetype.map(str::to_owned),
+                    &collect_errors,
+                )?;
+                return Ok(embedded_errors.into_inner());
+            }
+
+            // only the embedded regions of files mixing languages are code
+            if regions::is_multi_language(str, filetype) {
+                regions::transform_regions_at(str, filetype, &collect_errors)?;
+                return Ok(embedded_errors.into_inner());
+            }
+        //<
+
+        // determine if file compatible
+        let comment_starter = match EXTENSION_TO_COMMENT_STARTER_MAP.get(filetype) {
+            Some(x) => *x,
+            None => return Err(ScfmtErr::IncompatibleFileType),
+        };
+
+        Ok(parse_leniently(str, comment_starter).1)
+    }
+
+    fn determine_whitespace_type(str: &str) -> (char, usize) {
+        //> if no whitespace is found, assume format is 4 spaces
+            let mut chr = ' ';
+            let mut num = 4;
+        //<
+
+        let mut tab_count = 0;
+        let mut space_count = 0;
+        let mut tab_spaces_count_map: HashMap<usize, usize> = HashMap::new();
+        let mut last_depth;
+        let mut last_diff = 0;
+        let mut cur_depth = 0;
+
+        for line in str.lines() {
+            // if line is not empty
+            if let Some(first_char) = line.chars().next() {
+                //> get dif between this line and last line
+                    last_depth = cur_depth;
+                    cur_depth =
+                        if let Some((local_depth, _)) = count_and_remove_begining_whitespace(line) {
+                            local_depth
+                        } else {
+                            0
+                        };
+
+                    let diff = (last_depth as isize - cur_depth as isize).unsigned_abs();
+                //<
+
+                match first_char {
+                    ' ' => space_count += 1,
+                    '\t' => tab_count += 1,
+                    _ => {}
+                }
+
+                // store diff
+                if diff != 0 {
+                    // count the current diff
+
+                    match tab_spaces_count_map.get(&diff) {
+                        Some(x) => {
+                            let current_map_value = *x;
+                            tab_spaces_count_map.insert(diff, current_map_value + 1)
+                        }
+                        None => tab_spaces_count_map.insert(diff, 1),
+                    };
+
+                    last_diff = diff;
+                } else {
+                    // if there was no change in diff, count the last_diff
+
+                    if last_diff != 0 {
+                        match tab_spaces_count_map.get(&last_diff) {
+                            Some(x) => {
+                                let current_map_value = *x;
+                                tab_spaces_count_map.insert(last_diff, current_map_value + 1)
+                            }
+                            None => tab_spaces_count_map.insert(last_diff, 1),
+                        };
+                    }
+                }
+            }
+        }
+
+        //> determine most often occuring diff
+            let mut highest_count = 0;
+            let mut diff_with_highest_count = 0;
+            for (diff_size, diff_count) in &tab_spaces_count_map {
+                if diff_count > &highest_count {
+                    highest_count = *diff_count;
+                    diff_with_highest_count = *diff_size;
+                }
+            }
+
+        //<> determine num and char
+            if diff_with_highest_count != 0 {
+                num = diff_with_highest_count;
+
+                if tab_count > space_count {
+                    chr = '\t'
+                }
+            }
+        //<
+
+        (chr, num)
+    }
+
+    fn add_whitespace(line: &str, depth: usize, whitespace_char: char) -> String {
+        let mut value = String::from("");
+
+        for _i in 0..depth {
+            value.push(whitespace_char);
+        }
+
+        value + line
+    }
+
+    fn set_whitespace(str: &str, depth: usize, whitespace_char: char) -> String {
+        let str_no_whitespace = match count_and_remove_begining_whitespace(str) {
+            Some(x) => x.1,
+            None => "".to_owned(),
+        };
+
+        //> generate whitespace
+            let mut whitespace = String::from("");
+            for _i in 0..depth {
+                whitespace.push(whitespace_char);
+            }
+        //<
+
+        whitespace + &str_no_whitespace
+    }
+
+    /// Returns a list of all files in a directory and it's subdirectories
+    pub fn get_files_in_dir(path: &str, filetype: &str) -> Result<Vec<PathBuf>, GlobError> {
+        //> get list of all files and dirs in path, using glob
+            let mut paths = Vec::new();
+
+            let mut potential_slash = "";
+            if PathBuf::from(path).is_dir() && !path.ends_with('/') {
+                potential_slash = "/";
+            }
+
+            let search_params = String::from(path) + potential_slash + "**/*" + filetype;
+
+            for entry in glob(&search_params).expect("Failed to read glob pattern") {
+                match entry {
+                    Ok(path) => {
+                        paths.push(path);
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+
+        //<> filter out directories
+            let paths = paths.into_iter().filter(|e| e.is_file());
+
+        //<> filter out non unicode files
+            let paths: Vec<PathBuf> = paths
+                .into_iter()
+                .filter(|e| fs::read_to_string(e).is_ok())
+                .collect();
+        //<
+        Ok(paths)
+    }
+
+    /// Indents the lines of a section's body, leaving lines that are turned off alone
+    fn ensure_section_body_has_correct_indentation(
+        body_lines: &mut [String],
+        turned_off: &[bool],
+        section_indentation: usize,
+        tab_spaces: usize,
+        whitespace_char: char,
+    ) {
+        //> determine how much whitespace should be added
+            let mut lowest_depth = section_indentation + tab_spaces;
+            for (line, _) in body_lines.iter().zip(turned_off).filter(|x| !x.1) {
+                if let Some((depth, _)) = count_and_remove_begining_whitespace(line) {
+                    if depth < lowest_depth {
+                        lowest_depth = depth;
+                    }
+                }
+            }
+        //<> add any needed whitespace
+            if lowest_depth < section_indentation + tab_spaces {
+                let depth_difference = section_indentation + tab_spaces - lowest_depth;
+                for (line, _) in body_lines.iter_mut().zip(turned_off).filter(|x| !x.1) {
+                    match count_and_remove_begining_whitespace(line) {
+                        // a line may hold closing comment content that was moved below it
+                        Some(_) => {
+                            *line = line
+                                .split_inclusive('\n')
+                                .map(|x| add_whitespace(x, depth_difference, whitespace_char))
+                                .collect()
+                        }
+                        None => *line = "\n".to_owned(),
+                    }
+                }
+            }
+        //<
+    }
+
+    fn chop_off_beginning_spaces(line: &str) -> (Option<usize>, &str) {
+        let mut line_no_leading_spaces = "";
+        let mut leading_spaces: Option<usize> = None;
+        for (i, char) in line.char_indices() {
+            if char as u32 > 32 {
+                line_no_leading_spaces = &line[i..];
+                leading_spaces = Some(i);
+                break;
+            }
+        }
+
+        (leading_spaces, line_no_leading_spaces)
+    }
+
+    fn remove_comment_notation_if_it_exists(
+        line: &str,
+        comment_starter: &str,
+    ) -> (bool, bool, String) {
+        let mut line_no_comment_starter = line;
+        let comment_starter_with_space = comment_starter.to_owned() + " ";
+        let mut is_a_comment = false;
+        let mut space_after_comment_starter = false;
+        if line_no_comment_starter.starts_with(&comment_starter_with_space) {
+            is_a_comment = true;
+            space_after_comment_starter = true;
+            line_no_comment_starter = &line_no_comment_starter[comment_starter.len() + 1..];
+        } else if line_no_comment_starter.starts_with(comment_starter) {
+            is_a_comment = true;
+            space_after_comment_starter = false;
+            line_no_comment_starter = &line_no_comment_starter[comment_starter.len()..];
+        }
+
+        (
+            is_a_comment,
+            space_after_comment_starter,
+            line_no_comment_starter.to_owned(),
+        )
+    }
+
+    /// Formats every line of `str`, returning one entry per source line and the document `str` was parsed into.
+    ///
+    /// Each entry ends with a '\n'. An entry may hold more than one line, when text after a `//<` was moved below it.
+    fn format_lines(str: &str, comment_starter: &str) -> Result<(Vec<String>, Document), ScfmtErr> {
+        let document = parse_with_comment_starter(str, comment_starter)?;
+        let source_lines: Vec<&str> = str.lines().collect();
+        let turned_off = lines_turned_off(str, comment_starter);
+        let (whitespace_char, tab_spaces) = determine_turned_on_whitespace_type(str, &turned_off);
+        let labeled = closers_are_labeled(str, comment_starter);
+
+        //> copy lines, setting all whitespace only lines to depth 0
+            let mut formatted_lines: Vec<String> = source_lines
+                .iter()
+                .zip(&turned_off)
+                .map(|(line, turned_off)| {
+                    if line_is_only_whitepace(line) && !turned_off {
+                        "\n".to_owned()
+                    } else {
+                        line.to_string() + "\n"
+                    }
+                })
+                .collect();
+        //<
+
+        // nested sections are formatted before the sections they are in
+        for section in document.iter_closing_order() {
+            ensure_section_body_has_correct_indentation(
+                &mut formatted_lines[section.body.clone()],
+                &turned_off[section.body.clone()],
+                section.indentation,
+                tab_spaces,
+                whitespace_char,
+            );
+
+            let line = source_lines[section.close_line];
+
+            if section.closed_by == Bracket::CloseAndOpen {
+                formatted_lines[section.close_line] =
+                    set_whitespace(line, section.indentation, whitespace_char) + "\n";
+            } else {
+                //> close comment
+                    let (_, line_no_leading_spaces) = chop_off_beginning_spaces(line);
+                    let (_, space_after_comment_starter, _) =
+                        remove_comment_notation_if_it_exists(line_no_leading_spaces, comment_starter);
+                    let possible_space = if space_after_comment_starter { " " } else { "" };
+
+                    let mut closing_line = set_whitespace(
+                        &(comment_starter.to_owned() + possible_space + "<\n"),
+                        section.indentation,
+                        whitespace_char,
+                    );
+
+                //<> move any text after //< to comment on next line, unless it's a label
+                    let comment_contents = text_after_bracket(line, comment_starter);
+
+                    if labeled {
+                        closing_line = set_whitespace(
+                            &(comment_starter.to_owned()
+                                + possible_space
+                                + "<"
+                                + comment_contents.trim_end()
+                                + "\n"),
+                            section.indentation,
+                            whitespace_char,
+                        );
+                    } else if !line_is_only_whitepace(&comment_contents) {
+                        closing_line.push_str(&set_whitespace(
+                            &(comment_starter.to_owned() + &comment_contents + "\n"),
+                            section.indentation,
+                            whitespace_char,
+                        ));
+                    }
+
+                    formatted_lines[section.close_line] = closing_line;
+                //<
+            }
+        }
+
+        Ok((formatted_lines, document))
+    }
+
+    /// Turns formatted lines back into one string, without adding an ending '\n' that `source` didn't have.
+    fn join_formatted_lines(formatted_lines: Vec<String>, source: &str) -> String {
+        //> turn all lines into one string
+            let mut formatted_file = String::new();
+            for line in formatted_lines {
+                formatted_file.push_str(&line);
+            }
+        //<
+
+        // if the last char of source str wasn't '\n', don't add the last '\n'
+        //> This prevents adding an additional empty line to our output, that wasn't in our input
+            if let Some(last_char) = source.chars().last() {
+                if last_char != '\n' {
+                    // remove the last '\n'
+                    formatted_file.pop();
+                }
+            }
+        //<
+
+        formatted_file
+    }
+
+    /// Ensures lines inside bracketed structured comments are indented
+    ///
+    /// # Arguments
+    ///
+    /// * `str` - A string slice to be formatted
+    /// * `filetype` - A string slice of the file extension representing what language arg `str` is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use scfmt::scfmt::format_str;
+    ///
+    /// let to_format = "
+    /// //>
+    /// //this comment and the line below will be indented after formatting
+    /// let a = 0;
+    /// //<";
+    /// 
+    /// let formatted = "
+    /// //>
+    ///     //this comment and the line below will be indented after formatting
+    ///     let a = 0;
+    /// //<";
+    ///
+    /// assert_eq!(formatted, format_str(&to_format, "rs").unwrap());
+    /// ```
+    pub fn format_str(str: &str, filetype: &str) -> Result<String, ScfmtErr> {
+        // markdown only has code in its code blocks
+        if markdown::is_markdown(filetype) {
+            return markdown::transform_fences(str, &format_str);
+        }
+
+        // notebooks only have code in their code cells
+        if notebook::is_notebook(filetype) {
+            return notebook::transform_cells(str, &format_str);
+        }
+
+        // only the embedded regions of files mixing languages are code
+        if regions::is_multi_language(str, filetype) {
+            return regions::transform_regions(str, filetype, &format_str);
+        }
+
+        // determine if file compatible
+        let comment_starter = match EXTENSION_TO_COMMENT_STARTER_MAP.get(filetype) {
+            Some(x) => *x,
+            None => return Err(ScfmtErr::IncompatibleFileType),
+        };
+
+        // leave ignored files alone
+        if file_is_ignored(str, comment_starter) {
+            return Ok(str.to_owned());
+        }
+
+        let (formatted_lines, _) = format_lines(str, comment_starter)?;
+
+        doc_comments::format_doc_comments(&join_formatted_lines(formatted_lines, str), filetype)
+    }
+
+    /// Like ``format_str``, but only fixes indentation of lines in `lines` that are inside a structured comment.
+    ///
+    /// The whole of `str` is still parsed, so depths inside the range are correct. Lines outside the range are returned unchanged. In Markdown files and files mixing languages, each code block or region is parsed on its own.
+    ///
+    /// # Arguments
+    ///
+    /// * `str` - A string slice to be formatted
+    /// * `filetype` - A string slice of the file extension representing what language arg `str` is.
+    /// * `lines` - Zero based range of line indexes to format. The end is exclusive.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use scfmt::scfmt::format_range;
+    ///
+    /// let to_format = "//>\nlet a = 0;\nlet b = 0;\n//<";
+    /// let formatted = format_range(to_format, "rs", 1..2).unwrap();
+    ///
+    /// assert_eq!(formatted, "//>\n    let a = 0;\nlet b = 0;\n//<");
+    /// ```
+    pub fn format_range(
+        str: &str,
+        filetype: &str,
+        lines: Range<usize>,
+    ) -> Result<String, ScfmtErr> {
+        // embedded code is formatted with the range moved onto its own lines
+        let range_in = |code: &str, filetype: &str, start: usize| {
+            let lines = lines.start.saturating_sub(start)..lines.end.saturating_sub(start);
+            format_range(code, filetype, lines)
+        };
+
+        // markdown only has code in its code blocks
+        if markdown::is_markdown(filetype) {
+            return markdown::transform_fences_as(
+                str,
+                &|fence| fence.filetype.map(str::to_owned),
+                &range_in,
+            );
+        }
+
+        // only the embedded regions of files mixing languages are code
+        if regions::is_multi_language(str, filetype) {
+            return regions::transform_regions_at(str, filetype, &range_in);
+        }
+
+        // determine if file compatible
+        let comment_starter = match EXTENSION_TO_COMMENT_STARTER_MAP.get(filetype) {
+            Some(x) => *x,
+            None => return Err(ScfmtErr::IncompatibleFileType),
+        };
+
+        // leave ignored files alone
+        if file_is_ignored(str, comment_starter) {
+            return Ok(str.to_owned());
+        }
+
+        let (formatted_lines, document) = format_lines(str, comment_starter)?;
+
+        //> keep original lines, unless they are in range and inside a section
+            let mut range_formatted = String::new();
+            for (i, (original, formatted)) in str.split_inclusive('\n').zip(formatted_lines).enumerate()
+            {
+                let in_a_section = document.iter().any(|section| section.span().contains(&i));
+
+                if lines.contains(&i) && in_a_section {
+                    // formatted lines lose their '\r', so the original line ending is kept
+                    let line_ending = &original[original.trim_end_matches(['\r', '\n']).len()..];
+                    range_formatted.push_str(formatted.trim_end_matches(['\r', '\n']));
+                    range_formatted.push_str(line_ending);
+                } else {
+                    range_formatted.push_str(original);
+                }
+            }
+        //<
+
+        Ok(range_formatted)
+    }
+
+    /// Runs ``format_str`` on contents of given file.
+    pub fn format_file(file: PathBuf) -> Result<(), ScfmtErr> {
+        transform_file(file, &format_str)
+    }
+
+    /// Runs a transform on contents of given file, writing the result back if anything changed
+    fn transform_file(
+        file: PathBuf,
+        transform: &dyn Fn(&str, &str) -> Result<String, ScfmtErr>,
+    ) -> Result<(), ScfmtErr> {
+        let extenstion = match file.extension() {
+            Some(x) => match x.to_str() {
+                Some(x) => x,
+                None => return Err(ScfmtErr::CantConvertOsString),
+            },
+            None => return Err(ScfmtErr::IncompatibleFileType),
+        };
+
+        let contents = match fs::read_to_string(&file) {
+            Ok(x) => x,
+            Err(_) => return Err(ScfmtErr::CantReadFileAsString),
+        };
+
+        let converted = transform(&contents, extenstion)?;
+
+        //> write file
+            // leave file alone if there was no change
+            if converted != contents {
+                let mut output = match File::create(file) {
+                    Ok(x) => x,
+                    Err(_) => return Err(ScfmtErr::CantCreatFile),
+                };
+
+                match write!(output, "{}", converted) {
+                    Ok(x) => x,
+                    Err(_) => return Err(ScfmtErr::CantWriteToFile),
+                };
+            }
+        //<
+
+        Ok(())
+    }
+
+    /// Runs ``format_range`` on contents of given file.
+    pub fn format_range_file(file: PathBuf, lines: Range<usize>) -> Result<(), ScfmtErr> {
+        transform_file(file, &|contents, extension| {
+            format_range(contents, extension, lines.clone())
+        })
+    }
+
+    /// Runs ``add_brackets`` on contents of given file.
+    pub fn add_brackets_file(file: PathBuf) -> Result<(), ScfmtErr> {
+        let extenstion = match file.extension() {
+            Some(x) => match x.to_str() {
+                Some(x) => x,
+                None => return Err(ScfmtErr::CantConvertOsString),
+            },
+            None => return Err(ScfmtErr::IncompatibleFileType),
+        };
+
+        let contents = match fs::read_to_string(&file) {
+            Ok(x) => x,
+            Err(_) => return Err(ScfmtErr::CantReadFileAsString),
+        };
+
+        let converted = add_brackets(&contents, extenstion)?;
+
+        //> write file
+            // leave file alone if there was no change
+            if converted != contents {
+                let mut output = match File::create(file) {
+                    Ok(x) => x,
+                    Err(_) => return Err(ScfmtErr::CantCreatFile),
+                };
+
+                match write!(output, "{}", converted) {
+                    Ok(x) => x,
+                    Err(_) => return Err(ScfmtErr::CantWriteToFile),
+                };
+            }
+        //<
+
+        Ok(())
+    }
+
+    struct CommentDetail {
+        line: usize,
+        depth: usize,
+    }
+
+    fn make_comment_closed_and_open_bracket(line: &str, comment_starter: &str) -> Option<String> {
+        let (leading_spaces, line_no_leading_spaces) = chop_off_beginning_spaces(line);
+
+        // remove comment notation if it exists
+        let (is_a_comment, _, _line_no_comment_starter) =
+            remove_comment_notation_if_it_exists(line_no_leading_spaces, comment_starter);
+
+        if !is_a_comment {
+            return None;
+        }
+
+        let first_half = &line[..leading_spaces.unwrap() + comment_starter.len()];
+        let second_half = &line[leading_spaces.unwrap() + comment_starter.len()..];
+
+        Some(String::from(first_half) + "<>" + second_half)
+    }
+
+    fn make_comment_open_bracket(line: &str, comment_starter: &str) -> Option<String> {
+        // chop off begining spaces
+        let (leading_spaces, line_no_leading_spaces) = chop_off_beginning_spaces(line);
+
+        // remove comment notation if it exists
+        let (is_a_comment, _, _line_no_comment_starter) =
+            remove_comment_notation_if_it_exists(line_no_leading_spaces, comment_starter);
+
+        if !is_a_comment {
+            return None;
+        }
+
+        let first_half = &line[..leading_spaces.unwrap() + comment_starter.len()];
+        let second_half = &line[leading_spaces.unwrap() + comment_starter.len()..];
+
+        Some(String::from(first_half) + ">" + second_half)
+    }
+
+    fn new_comment_closed_bracket(
+        depth: usize,
+        comment_starter: &str,
+        whitespace_char: char,
+    ) -> Option<String> {
+        let mut result = String::new();
+        for _i in 0..depth {
+            result.push(whitespace_char);
+        }
+
+        result.push_str(&(String::from(comment_starter) + "<"));
+        Some(result)
+    }
+
+    fn remove_empty_tail(lines_list: &mut Vec<String>) {
+        while !lines_list.is_empty() && line_is_only_whitepace(lines_list.last().unwrap()) {
+            lines_list.pop();
+        }
+    }
+
+    fn end_the_last_structured_comments(
+        lines_list: &mut Vec<String>,
+        comment_tracker: &mut Vec<CommentDetail>,
+        leading_spaces: usize,
+        comment_starter: &str,
+        whitespace_char: char,
+    ) {
+        //> remove and remember last empty lines
+            let empty_line_count = count_ending_empty_lines(lines_list);
+            remove_empty_tail(lines_list);
+
+        //<> add closing bracket comments
+            while !comment_tracker.is_empty()
+                && leading_spaces <= comment_tracker[comment_tracker.len() - 1].depth
+            {
+                let close_bracket_line = new_comment_closed_bracket(
+                    comment_tracker[comment_tracker.len() - 1].depth,
+                    comment_starter,
+                    whitespace_char,
+                )
+                .unwrap();
+                lines_list.push(close_bracket_line);
+                comment_tracker.pop();
+            }
+
+        //<> re-add previously removed whitespace
+            append_num_empty_lines(empty_line_count, lines_list);
+        //<
+    }
+
+    fn pass_a_new_comment_that_we_dont_know_if_its_structured(
+        lines_list: &mut Vec<String>,
+        comment_tracker: &mut Vec<CommentDetail>,
+        leading_spaces: Option<usize>,
+        unsure_if_last_comment_was_structured: &mut bool,
+        line: &str,
+    ) {
+        let comment = CommentDetail {
+            line: lines_list.len(),
+            depth: leading_spaces.unwrap(),
+        };
+
+        comment_tracker.push(comment);
+        *unsure_if_last_comment_was_structured = true;
+
+        lines_list.push(String::from(line));
+    }
+
+    fn count_and_remove_begining_whitespace(line: &str) -> Option<(usize, String)> {
+        // chop off begining spaces
+        let (leading_whitespace_option, line_no_leading_spaces) = chop_off_beginning_spaces(line);
+
+        leading_whitespace_option.map(|num_leading_whitespace| {
+            (num_leading_whitespace, line_no_leading_spaces.to_owned())
+        })
+    }
+
+    fn last_non_empty_line_before_index(
+        index: usize,
+        lines_list: &[String],
+    ) -> Option<(usize, &str)> {
+        for i in (0..index).rev() {
+            if !line_is_only_whitepace(&lines_list[i]) {
+                return Some((i, &lines_list[i]));
+            }
+        }
+
+        None
+    }
+
+    fn add_open_bracket_to_last_comment(
+        lines_list: &mut Vec<String>,
+        comment_tracker: &mut [CommentDetail],
+        comment_starter: &str,
+    ) {
+        let mut should_consume_closing_comment = false;
+
+        //> consume any previous now unecessary //<
+
+            let line_of_latest_comment = comment_tracker[comment_tracker.len() - 1].line;
+
+            let last_solid_line_option =
+                last_non_empty_line_before_index(line_of_latest_comment, lines_list);
+
+            // if there even could be a //< comment behind the lastest comment
+            if let Some((_last_solid_line_index, line_before_open_bracket_comment)) =
+                last_solid_line_option
+            {
+                // chop off begining spaces
+                let (leading_spaces, line_no_leading_spaces) =
+                    chop_off_beginning_spaces(line_before_open_bracket_comment);
+
+                // remove comment notation if it exists
+                let (is_a_comment, _, line_no_comment_opener) =
+                    remove_comment_notation_if_it_exists(line_no_leading_spaces, comment_starter);
+
+                let latest_comment =
+                    count_and_remove_begining_whitespace(&lines_list[line_of_latest_comment])
+                        .unwrap_or_default();
+
+                if is_a_comment
+                    && line_no_comment_opener.starts_with('<')
+                    && latest_comment.0 == leading_spaces.unwrap()
+                {
+                    should_consume_closing_comment = true;
+                }
+            }
+        //<
+        let line_with_no_bracket = lines_list[line_of_latest_comment].clone();
+
+        if should_consume_closing_comment {
+            //> pop everything to the last //<, but remember how to restore what was popped.
+                let after_spaces = count_ending_empty_lines(lines_list);
+                remove_empty_tail(lines_list);
+
+                // remove the soon to be bracketed comment
+                // we'll add it back later
+                lines_list.pop();
+
+                let before_spaces = count_ending_empty_lines(lines_list);
+                remove_empty_tail(lines_list);
+
+            //<> remove the //<
+                lines_list.pop();
+
+            //<> put things back and make add brackets to latest comment
+
+                append_num_empty_lines(before_spaces, lines_list);
+
+                // re-append the latest comment, with added brackets
+                lines_list.push(
+                    make_comment_closed_and_open_bracket(&line_with_no_bracket, comment_starter)
+                        .unwrap(),
+                );
+
+                append_num_empty_lines(after_spaces, lines_list);
+            //<
+        } else {
+            // append bracket to latest comment
+            lines_list[line_of_latest_comment] =
+                make_comment_open_bracket(&line_with_no_bracket, comment_starter).unwrap();
+        }
+    }
+
+    fn line_is_only_whitepace(str: &str) -> bool {
+        for char in str.chars() {
+            if char as u32 > 32 {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Adds brackets to bracketless structured comments
+    pub fn add_brackets(str: &str, filetype: &str) -> Result<String, ScfmtErr> {
+        // markdown only has code in its code blocks
+        if markdown::is_markdown(filetype) {
+            return markdown::transform_fences(str, &add_brackets);
+        }
+
+        // notebooks only have code in their code cells
+        if notebook::is_notebook(filetype) {
+            return notebook::transform_cells_with(str, &add_brackets, &add_brackets);
+        }
+
+        // only the embedded regions of files mixing languages are code
+        if regions::is_multi_language(str, filetype) {
+            return regions::transform_regions(str, filetype, &add_brackets);
+        }
+
+        // determine if file compatible
+        let comment_starter = match EXTENSION_TO_COMMENT_STARTER_MAP.get(filetype) {
+            Some(x) => *x,
+            None => return Err(ScfmtErr::IncompatibleFileType),
+        };
+
+        // leave ignored files alone
+        if file_is_ignored(str, comment_starter) {
+            return Ok(str.to_owned());
+        }
+
+        // remove existing brackets, so later part of this function doesn't add more on top of existing ones.
+        let str = &remove_brackets(str, filetype)?;
+
+        let turned_off = lines_turned_off(str, comment_starter);
+        let (whitespace_char, _tab_spaces) = determine_turned_on_whitespace_type(str, &turned_off);
+
+        let mut comment_tracker: Vec<CommentDetail> = Vec::new();
+
+        let mut lines_list: Vec<String> = Vec::new();
+        let mut unsure_if_last_comment_was_structured = true;
+
+        let mut processed_line_count = 0;
+        for (i, line) in str.lines().enumerate() {
+            // counts how many lines this loop has processed
+            processed_line_count += 1;
+
+            // lines that are turned off don't start or end structured comments
+            if turned_off[i] {
+                lines_list.push(String::from(line));
+                continue;
+            }
+
+            // chop off begining spaces
+            let (leading_spaces, line_no_leading_spaces) = chop_off_beginning_spaces(line);
+
+            let (is_a_comment, _, _) =
+                remove_comment_notation_if_it_exists(line_no_leading_spaces, comment_starter);
+
+            match leading_spaces {
+                Some(x) => {
+                    if is_a_comment {
+                        if !comment_tracker.is_empty() {
+                            if unsure_if_last_comment_was_structured {
+                                if x > comment_tracker[comment_tracker.len() - 1].depth {
+                                    // last was structured
+
+                                    add_open_bracket_to_last_comment(
+                                        &mut lines_list,
+                                        &mut comment_tracker,
+                                        comment_starter,
+                                    );
+
+                                    pass_a_new_comment_that_we_dont_know_if_its_structured(
+                                        &mut lines_list,
+                                        &mut comment_tracker,
+                                        leading_spaces,
+                                        &mut unsure_if_last_comment_was_structured,
+                                        line,
+                                    );
+                                } else {
+                                    // last was not structured
+
+                                    comment_tracker.pop();
+
+                                    end_the_last_structured_comments(
+                                        &mut lines_list,
+                                        &mut comment_tracker,
+                                        x,
+                                        comment_starter,
+                                        whitespace_char,
+                                    );
+
+                                    pass_a_new_comment_that_we_dont_know_if_its_structured(
+                                        &mut lines_list,
+                                        &mut comment_tracker,
+                                        leading_spaces,
+                                        &mut unsure_if_last_comment_was_structured,
+                                        line,
+                                    );
+                                }
+                            } else if x > comment_tracker[comment_tracker.len() - 1].depth {
+                                pass_a_new_comment_that_we_dont_know_if_its_structured(
+                                    &mut lines_list,
+                                    &mut comment_tracker,
+                                    leading_spaces,
+                                    &mut unsure_if_last_comment_was_structured,
+                                    line,
+                                );
+                            } else {
+                                end_the_last_structured_comments(
+                                    &mut lines_list,
+                                    &mut comment_tracker,
+                                    x,
+                                    comment_starter,
+                                    whitespace_char,
+                                );
+
+                                pass_a_new_comment_that_we_dont_know_if_its_structured(
+                                    &mut lines_list,
+                                    &mut comment_tracker,
+                                    leading_spaces,
+                                    &mut unsure_if_last_comment_was_structured,
+                                    line,
+                                );
+                            }
+                        } else {
+                            pass_a_new_comment_that_we_dont_know_if_its_structured(
+                                &mut lines_list,
+                                &mut comment_tracker,
+                                leading_spaces,
+                                &mut unsure_if_last_comment_was_structured,
+                                line,
+                            );
+                        }
+                    } else if !comment_tracker.is_empty() {
+                        if unsure_if_last_comment_was_structured {
+                            if x > comment_tracker[comment_tracker.len() - 1].depth {
+                                // last was structured
+
+                                add_open_bracket_to_last_comment(
+                                    &mut lines_list,
+                                    &mut comment_tracker,
+                                    comment_starter,
+                                );
+                            } else {
+                                // last was not structured
+
+                                comment_tracker.pop();
+
+                                end_the_last_structured_comments(
+                                    &mut lines_list,
+                                    &mut comment_tracker,
+                                    x,
+                                    comment_starter,
+                                    whitespace_char,
+                                );
+                            }
+                            unsure_if_last_comment_was_structured = false;
+
+                            lines_list.push(String::from(line));
+                        } else if x > comment_tracker[comment_tracker.len() - 1].depth {
+                            lines_list.push(String::from(line));
+                        } else {
+                            end_the_last_structured_comments(
+                                &mut lines_list,
+                                &mut comment_tracker,
+                                x,
+                                comment_starter,
+                                whitespace_char,
+                            );
+
+                            // forward the current line
+                            lines_list.push(String::from(line));
+                        }
+                    } else {
+                        lines_list.push(String::from(line));
+                    }
+                }
+                None => {
+                    lines_list.push("".to_owned());
                 }
             }
-        //<
-
-        (chr, num)
-    }
-
-    fn add_whitespace(line: &str, depth: usize, whitespace_char: char) -> String {
-        let mut value = String::from("");
-
-        for _i in 0..depth {
-            value.push(whitespace_char);
         }
 
-        value + line
-    }
-
-    fn set_whitespace(str: &str, depth: usize, whitespace_char: char) -> String {
-        let str_no_whitespace = match count_and_remove_begining_whitespace(str) {
-            Some(x) => x.1,
-            None => "".to_owned(),
-        };
-
-        //> generate whitespace
-            let mut whitespace = String::from("");
-            for _i in 0..depth {
-                whitespace.push(whitespace_char);
+        //> last comment was not structured, if it was the last non empty line in the String
+            if unsure_if_last_comment_was_structured && !comment_tracker.is_empty() {
+                comment_tracker.pop();
             }
         //<
 
-        whitespace + &str_no_whitespace
-    }
-
-    /// Returns a list of all files in a directory and it's subdirectories
-    pub fn get_files_in_dir(path: &str, filetype: &str) -> Result<Vec<PathBuf>, GlobError> {
-        //> get list of all files and dirs in path, using glob
-            let mut paths = Vec::new();
-
-            let mut potential_slash = "";
-            if PathBuf::from(path).is_dir() && !path.ends_with('/') {
-                potential_slash = "/";
-            }
-
-            let search_params = String::from(path) + potential_slash + "**/*" + filetype;
+        end_the_last_structured_comments(
+            &mut lines_list,
+            &mut comment_tracker,
+            0,
+            comment_starter,
+            whitespace_char,
+        );
 
-            for entry in glob(&search_params).expect("Failed to read glob pattern") {
-                match entry {
-                    Ok(path) => {
-                        paths.push(path);
-                    }
-                    Err(e) => return Err(e),
-                }
+        //> turn all lines into one string
+            let mut final_string = String::new();
+            for line in lines_list {
+                final_string.push_str(&line);
+                final_string.push('\n');
             }
+        //<
 
-        //<> filter out directories
-            let paths = paths.into_iter().filter(|e| e.is_file());
+        // remove last '\n'
+        final_string.pop();
 
-        //<> filter out non unicode files
-            let paths: Vec<PathBuf> = paths
-                .into_iter()
-                .filter(|e| fs::read_to_string(e).is_ok())
-                .collect();
-        //<
-        Ok(paths)
-    }
+        //> append any missed empty lines, as the .lines() function may have skipped them.
+            let line_diff = count_lines(str) - processed_line_count;
 
-    fn ensure_previous_lines_have_correct_indentation(
-        formatted_lines: &mut [String],
-        comment_tracker: &mut [CommentDetail],
-        tab_spaces: usize,
-        whitespace_char: char,
-    ) {
-        //> determine how much whitespace should be added
-            let mut lowest_depth = comment_tracker[comment_tracker.len() - 1].depth + tab_spaces;
-            let line_of_last_unclosed_comment = comment_tracker[comment_tracker.len() - 1].line;
-            for i in line_of_last_unclosed_comment + 1..formatted_lines.len() {
-                let whitespaces_option = count_and_remove_begining_whitespace(&formatted_lines[i]);
-                match whitespaces_option {
-                    Some(spaces_tuple) => {
-                        if spaces_tuple.0 < lowest_depth {
-                            lowest_depth = spaces_tuple.0;
-                        }
-                    }
-                    None => continue,
-                }
-            }
-        //<> add any needed whitespace
-            if lowest_depth < comment_tracker[comment_tracker.len() - 1].depth + tab_spaces {
-                let depth_difference =
-                    comment_tracker[comment_tracker.len() - 1].depth + tab_spaces - lowest_depth;
-                if depth_difference > 0 {
-                    for i in line_of_last_unclosed_comment + 1..formatted_lines.len() {
-                        match count_and_remove_begining_whitespace(&formatted_lines[i]) {
-                            Some(_) => {
-                                formatted_lines[i] = add_whitespace(
-                                    &formatted_lines[i],
-                                    depth_difference,
-                                    whitespace_char,
-                                )
-                            }
-                            None => formatted_lines[i] = "\n".to_owned(),
-                        }
-                    }
+            if line_diff > 0 {
+                for _ in 0..line_diff {
+                    final_string.push('\n');
                 }
             }
         //<
+
+        Ok(final_string)
     }
 
-    fn chop_off_beginning_spaces(line: &str) -> (Option<usize>, &str) {
-        let mut line_no_leading_spaces = "";
-        let mut leading_spaces: Option<usize> = None;
-        for (i, char) in line.chars().enumerate() {
-            if char as u32 > 32 {
-                line_no_leading_spaces = &line[i..];
-                leading_spaces = Some(i);
-                break;
-            }
+    /// Adds a '_' character in front of any comment brackets. Nullifying any existing bracketed structured comments, without removing any characters.
+    ///
+    /// This is intended to be run on existing codebases that have not previously been using structured commenting. As brackets may exist in comments that were not intended to be structured comments.
+    pub fn null_existing_brackets(str: &str, filetype: &str) -> Result<String, ScfmtErr> {
+        // markdown only has code in its code blocks
+        if markdown::is_markdown(filetype) {
+            return markdown::transform_fences(str, &null_existing_brackets);
         }
 
-        (leading_spaces, line_no_leading_spaces)
-    }
-
-    fn remove_comment_notation_if_it_exists(
-        line: &str,
-        comment_starter: &str,
-    ) -> (bool, bool, String) {
-        let mut line_no_comment_starter = line;
-        let comment_starter_with_space = comment_starter.to_owned() + " ";
-        let mut is_a_comment = false;
-        let mut space_after_comment_starter = false;
-        if line_no_comment_starter.starts_with(&comment_starter_with_space) {
-            is_a_comment = true;
-            space_after_comment_starter = true;
-            line_no_comment_starter = &line_no_comment_starter[comment_starter.len() + 1..];
-        } else if line_no_comment_starter.starts_with(comment_starter) {
-            is_a_comment = true;
-            space_after_comment_starter = false;
-            line_no_comment_starter = &line_no_comment_starter[comment_starter.len()..];
+        // notebooks only have code in their code cells
+        if notebook::is_notebook(filetype) {
+            return notebook::transform_cells_with(
+                str,
+                &null_existing_brackets,
+                &null_existing_brackets,
+            );
         }
 
-        (
-            is_a_comment,
-            space_after_comment_starter,
-            line_no_comment_starter.to_owned(),
-        )
-    }
+        // only the embedded regions of files mixing languages are code
+        if regions::is_multi_language(str, filetype) {
+            return regions::transform_regions(str, filetype, &null_existing_brackets);
+        }
 
-    /// Ensures lines inside bracketed structured comments are indented
-    ///
-    /// # Arguments
-    ///
-    /// * `str` - A string slice to be formatted
-    /// * `filetype` - A string slice of the file extension representing what language arg `str` is.
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// use scfmt::scfmt::format_str;
-    ///
-    /// let to_format = "
-    /// //>
-    /// //this comment and the line below will be indented after formatting
-    /// let a = 0;
-    /// //<";
-    /// 
-    /// let formatted = "
-    /// //>
-    ///     //this comment and the line below will be indented after formatting
-    ///     let a = 0;
-    /// //<";
-    ///
-    /// assert_eq!(formatted, format_str(&to_format, "rs").unwrap());
-    /// ```
-    pub fn format_str(str: &str, filetype: &str) -> Result<String, ScfmtErr> {
         // determine if file compatible
         let comment_starter = match EXTENSION_TO_COMMENT_STARTER_MAP.get(filetype) {
             Some(x) => *x,
             None => return Err(ScfmtErr::IncompatibleFileType),
         };
 
-        let mut formatted_file = String::from("");
-        let mut formatted_lines: Vec<String> = Vec::new();
-        let (whitespace_char, tab_spaces) = determine_whitespace_type(str);
-        let mut comment_tracker: Vec<CommentDetail> = Vec::new();
+        // leave ignored files alone
+        if file_is_ignored(str, comment_starter) {
+            return Ok(str.to_owned());
+        }
 
+        let turned_off = lines_turned_off(str, comment_starter);
+        let (whitespace_char, _tab_spaces) = determine_turned_on_whitespace_type(str, &turned_off);
+        let mut lines_list = Vec::new();
+        let mut processed_line_count = 0;
         for (i, line) in str.lines().enumerate() {
-            // chop off begining spaces
-            let (leading_spaces, line_no_leading_spaces) = chop_off_beginning_spaces(line);
-
-            // remove comment notation if it exists
-            let (is_a_comment, space_after_comment_starter, line_no_comment_starter) =
-                remove_comment_notation_if_it_exists(line_no_leading_spaces, comment_starter);
-
-            //> apply whitespace depth
-                if is_a_comment & line_no_comment_starter.starts_with('>') {
-                    formatted_lines.push(line.to_string() + "\n");
-
-                    //> add comment to comment tracker
-                        let comment = CommentDetail {
-                            line: i,
-                            depth: leading_spaces.unwrap(),
-                        };
-                        comment_tracker.push(comment);
-                    //<
-                } else if is_a_comment & line_no_comment_starter.starts_with("<>") {
-                    if comment_tracker.is_empty() {
-                        return Err(ScfmtErr::CommentClosedNothing(i + 1));
-                    }
-
-                    ensure_previous_lines_have_correct_indentation(
-                        &mut formatted_lines,
-                        &mut comment_tracker,
-                        tab_spaces,
-                        whitespace_char,
-                    );
-
-                    formatted_lines.push(
-                        set_whitespace(
-                            line,
-                            comment_tracker[comment_tracker.len() - 1].depth,
-                            whitespace_char,
-                        ) + "\n",
-                    );
-
-                    //> remove and add comment to comment tracker
-                        let comment = CommentDetail {
-                            line: i,
-                            depth: comment_tracker[comment_tracker.len() - 1].depth,
-                        };
-                        comment_tracker.pop();
-                        comment_tracker.push(comment);
-                    //<
-                } else if is_a_comment & line_no_comment_starter.starts_with('<') {
-                    if comment_tracker.is_empty() {
-                        return Err(ScfmtErr::CommentClosedNothing(i + 1));
-                    }
-
-                    ensure_previous_lines_have_correct_indentation(
-                        &mut formatted_lines,
-                        &mut comment_tracker,
-                        tab_spaces,
-                        whitespace_char,
-                    );
-
-                    //> close comment
-                        let possible_space = if space_after_comment_starter { " " } else { "" };
+            // counts how many lines this loop has processed
+            processed_line_count += 1;
 
-                        formatted_lines.push(set_whitespace(
-                            &(comment_starter.to_owned() + possible_space + "<\n"),
-                            comment_tracker[comment_tracker.len() - 1].depth,
-                            whitespace_char,
-                        ));
+            if turned_off[i] {
+                lines_list.push(line.to_owned());
+            } else if bracket_of_line(line, comment_starter).is_some() {
+                // chop off begining spaces
+                let (leading_spaces, line_no_leading_spaces) = chop_off_beginning_spaces(line);
 
-                    //<> move any text after //< to comment on next line
-                        // remove bracket from line
-                        let comment_contents = &line_no_comment_starter[1..];
+                // remove comment notation
+                let (_, space_after_comment_starter, line_no_comment_starter) =
+                    remove_comment_notation_if_it_exists(line_no_leading_spaces, comment_starter);
 
-                        if !line_is_only_whitepace(comment_contents) {
-                            formatted_lines.push(set_whitespace(
-                                &(comment_starter.to_owned() + comment_contents + "\n"),
-                                comment_tracker[comment_tracker.len() - 1].depth,
-                                whitespace_char,
-                            ));
-                        }
-                    //<
+                let potential_space = if space_after_comment_starter { " " } else { "" };
 
-                    // remove comment from comment tracker
-                    comment_tracker.pop();
-                } else if leading_spaces != None {
-                    formatted_lines.push(line.to_string() + "\n");
-                } else {
-                    // all whitespace only lines are set to depth 0
-                    formatted_lines.push("\n".to_string());
-                }
-            //<
+                lines_list.push(add_whitespace(
+                    &(comment_starter.to_owned()
+                        + potential_space
+                        + "_"
+                        + &line_no_comment_starter),
+                    leading_spaces.unwrap(),
+                    whitespace_char,
+                ));
+            } else if line_is_only_whitepace(line) {
+                lines_list.push("".to_owned());
+            } else {
+                lines_list.push(line.to_owned());
+            }
         }
 
         //> turn all lines into one string
-            for line in formatted_lines {
-                formatted_file.push_str(&line);
+            let mut final_string = String::new();
+            for line in lines_list {
+                final_string.push_str(&line);
+                final_string.push('\n');
             }
         //<
 
-        // if the last char of source str wasn't '\n', don't add the last '\n'
-        //> This prevents adding an additional empty line to our output, that wasn't in our input
-            if let Some(last_char) = str.chars().last() {
-                if last_char != '\n' {
-                    // remove the last '\n'
-                    formatted_file.pop();
+        // remove last '\n'
+        final_string.pop();
+
+        //> append any missed empty lines, as the .lines() function may have skipped them.
+            let line_diff = count_lines(str) - processed_line_count;
+
+            if line_diff > 0 {
+                for _ in 0..line_diff {
+                    final_string.push('\n');
                 }
             }
+        //<
+
+        Ok(final_string)
+    }
+
+    fn count_lines(str: &str) -> usize {
+        if str.is_empty() {
+            return 0;
+        }
 
-        //<> ensure formatting successful
-            if !comment_tracker.is_empty() {
-                let err_line = comment_tracker[comment_tracker.len() - 1].line + 1;
-                return Err(ScfmtErr::CommentNeverClosed(err_line));
+        let mut count = 1;
+        for c in str.chars() {
+            if c == '\n' {
+                count += 1;
             }
-        //<
-        Ok(formatted_file)
+        }
+        count
     }
 
-    /// Runs ``format_str`` on contents of given file.
-    pub fn format_file(file: PathBuf) -> Result<(), ScfmtErr> {
+    /// Runs ``remove_brackets`` on contents of given file
+    pub fn remove_brackets_file(file: PathBuf) -> Result<(), ScfmtErr> {
         let extenstion = match file.extension() {
             Some(x) => match x.to_str() {
                 Some(x) => x,
@@ -720,7 +3736,7 @@ pub mod scfmt {
             Err(_) => return Err(ScfmtErr::CantReadFileAsString),
         };
 
-        let converted = format_str(&contents, extenstion)?;
+        let converted = remove_brackets(&contents, extenstion)?;
 
         //> write file
             // leave file alone if there was no change
@@ -740,8 +3756,8 @@ pub mod scfmt {
         Ok(())
     }
 
-    /// Runs ``add_brackets`` on contents of given file.
-    pub fn add_brackets_file(file: PathBuf) -> Result<(), ScfmtErr> {
+    /// Runs ``null_existing_brackets`` on contents of given file
+    pub fn null_existing_brackets_file(file: PathBuf) -> Result<(), ScfmtErr> {
         let extenstion = match file.extension() {
             Some(x) => match x.to_str() {
                 Some(x) => x,
@@ -755,7 +3771,7 @@ pub mod scfmt {
             Err(_) => return Err(ScfmtErr::CantReadFileAsString),
         };
 
-        let converted = add_brackets(&contents, extenstion)?;
+        let converted = null_existing_brackets(&contents, extenstion)?;
 
         //> write file
             // leave file alone if there was no change
@@ -775,710 +3791,1304 @@ pub mod scfmt {
         Ok(())
     }
 
-    struct CommentDetail {
-        line: usize,
-        depth: usize,
+    fn count_ending_empty_lines(lines_list: &[String]) -> usize {
+        let mut count = 0;
+        for i in (0..lines_list.len()).rev() {
+            if !line_is_only_whitepace(&lines_list[i]) {
+                break;
+            }
+            count += 1;
+        }
+
+        count
     }
 
-    fn make_comment_closed_and_open_bracket(line: &str, comment_starter: &str) -> Option<String> {
-        let (leading_spaces, line_no_leading_spaces) = chop_off_beginning_spaces(line);
+    fn append_num_empty_lines(num: usize, lines_list: &mut Vec<String>) {
+        for _ in 0..num {
+            lines_list.push("".to_owned());
+        }
+    }
 
-        // remove comment notation if it exists
-        let (is_a_comment, _, _line_no_comment_starter) =
-            remove_comment_notation_if_it_exists(line_no_leading_spaces, comment_starter);
+    /// Converts bracketed structured comments into bracketless structured comments
+    ///
+    /// Becuase bracketless structured comments rely soley on indentation to show what lines they are talking about, this function formats the input str before removing bracket comments. To ensure structured comment information is not lost.
+    pub fn remove_brackets(str: &str, filetype: &str) -> Result<String, ScfmtErr> {
+        // markdown only has code in its code blocks
+        if markdown::is_markdown(filetype) {
+            return markdown::transform_fences(str, &remove_brackets);
+        }
 
-        if !is_a_comment {
-            return None;
+        // notebooks only have code in their code cells
+        // cells that can't be formatted, like Python ones, have their brackets removed as they are
+        if notebook::is_notebook(filetype) {
+            return notebook::transform_cells_with(
+                str,
+                &remove_brackets,
+                &remove_unformatted_brackets,
+            );
         }
 
-        let first_half = &line[..leading_spaces.unwrap() + comment_starter.len()];
-        let second_half = &line[leading_spaces.unwrap() + comment_starter.len()..];
+        // only the embedded regions of files mixing languages are code
+        if regions::is_multi_language(str, filetype) {
+            return regions::transform_regions(str, filetype, &remove_brackets);
+        }
 
-        Some(String::from(first_half) + "<>" + second_half)
+        //format str before removing brackets, to ensure their information is not lost.
+        remove_unformatted_brackets(&format_str(str, filetype)?, filetype)
     }
 
-    fn make_comment_open_bracket(line: &str, comment_starter: &str) -> Option<String> {
-        // chop off begining spaces
-        let (leading_spaces, line_no_leading_spaces) = chop_off_beginning_spaces(line);
-
-        // remove comment notation if it exists
-        let (is_a_comment, _, _line_no_comment_starter) =
-            remove_comment_notation_if_it_exists(line_no_leading_spaces, comment_starter);
+    /// Converts bracketed structured comments into bracketless structured comments, without formatting first
+    fn remove_unformatted_brackets(str: &str, filetype: &str) -> Result<String, ScfmtErr> {
+        // determine if file compatible
+        let comment_starter = match EXTENSION_TO_COMMENT_STARTER_MAP.get(filetype) {
+            Some(x) => *x,
+            None => return Err(ScfmtErr::IncompatibleFileType),
+        };
 
-        if !is_a_comment {
-            return None;
+        // leave ignored files alone
+        if file_is_ignored(str, comment_starter) {
+            return Ok(str.to_owned());
         }
 
-        let first_half = &line[..leading_spaces.unwrap() + comment_starter.len()];
-        let second_half = &line[leading_spaces.unwrap() + comment_starter.len()..];
-
-        Some(String::from(first_half) + ">" + second_half)
-    }
+        let document = parse_with_comment_starter(str, comment_starter)?;
+        let turned_off = lines_turned_off(str, comment_starter);
 
-    fn new_comment_closed_bracket(
-        depth: usize,
-        comment_starter: &str,
-        whitespace_char: char,
-    ) -> Option<String> {
-        let mut result = String::new();
-        for _i in 0..depth {
-            result.push(whitespace_char);
-        }
+        let (whitespace_char, _tab_spaces) = determine_turned_on_whitespace_type(str, &turned_off);
 
-        result.push_str(&(String::from(comment_starter) + "<"));
-        Some(result)
-    }
+        let mut formatted_str = String::new();
+        let source_lines: Vec<&str> = str.lines().collect();
+        let processed_line_count = source_lines.len();
+
+        //> copy lines, with whitespace only lines made empty
+            let mut lines_list: Vec<Option<String>> = source_lines
+                .iter()
+                .zip(&turned_off)
+                .map(|(line, turned_off)| {
+                    if line_is_only_whitepace(line) && !turned_off {
+                        Some("\n".to_owned())
+                    } else {
+                        Some(line.to_string() + "\n")
+                    }
+                })
+                .collect();
 
-    fn remove_empty_tail(lines_list: &mut Vec<String>) {
-        while !lines_list.is_empty() && line_is_only_whitepace(lines_list.last().unwrap()) {
-            lines_list.pop();
-        }
-    }
+        //<> remove brackets of every section
+            for section in document.iter() {
+                let header = source_lines[section.header_line];
+                let (leading_whitespace, _) = chop_off_beginning_spaces(header);
 
-    fn end_the_last_structured_comments(
-        lines_list: &mut Vec<String>,
-        comment_tracker: &mut Vec<CommentDetail>,
-        leading_spaces: usize,
-        comment_starter: &str,
-        whitespace_char: char,
-    ) {
-        //> remove and remember last empty lines
-            let empty_line_count = count_ending_empty_lines(lines_list);
-            remove_empty_tail(lines_list);
+                lines_list[section.header_line] = Some(
+                    add_whitespace(
+                        &(comment_starter.to_owned() + &text_after_bracket(header, comment_starter)),
+                        leading_whitespace.unwrap(),
+                        whitespace_char,
+                    ) + "\n",
+                );
 
-        //<> add closing bracket comments
-            while !comment_tracker.is_empty()
-                && leading_spaces <= comment_tracker[comment_tracker.len() - 1].depth
-            {
-                let close_bracket_line = new_comment_closed_bracket(
-                    comment_tracker[comment_tracker.len() - 1].depth,
-                    comment_starter,
-                    whitespace_char,
-                )
-                .unwrap();
-                lines_list.push(close_bracket_line);
-                comment_tracker.pop();
+                // remove closing lines by not adding them to output
+                if section.closed_by == Bracket::Close {
+                    lines_list[section.close_line] = None;
+                }
             }
+        //<
 
-        //<> re-add previously removed whitespace
-            append_num_empty_lines(empty_line_count, lines_list);
+        //> turn all lines into one string
+            for line in lines_list.into_iter().flatten() {
+                formatted_str.push_str(&line);
+            }
         //<
-    }
 
-    fn pass_a_new_comment_that_we_dont_know_if_its_structured(
-        lines_list: &mut Vec<String>,
-        comment_tracker: &mut Vec<CommentDetail>,
-        leading_spaces: Option<usize>,
-        unsure_if_last_comment_was_structured: &mut bool,
-        line: &str,
-    ) {
-        let comment = CommentDetail {
-            line: lines_list.len(),
-            depth: leading_spaces.unwrap(),
-        };
+        // remove last '\n'
+        formatted_str.pop();
 
-        comment_tracker.push(comment);
-        *unsure_if_last_comment_was_structured = true;
+        //> append any missed empty lines, as the .lines() function may have skipped them.
+            let line_diff = count_lines(str) - processed_line_count;
 
-        lines_list.push(String::from(line));
+            if line_diff > 0 {
+                for _ in 0..line_diff {
+                    formatted_str.push('\n');
+                }
+            }
+        //<
+
+        Ok(formatted_str)
     }
 
-    fn count_and_remove_begining_whitespace(line: &str) -> Option<(usize, String)> {
-        // chop off begining spaces
-        let (leading_whitespace_option, line_no_leading_spaces) = chop_off_beginning_spaces(line);
+    /// Formats ``outline`` can print a table of contents in
+    #[derive(PartialEq, Debug, Clone, Copy)]
+    pub enum OutlineFormat {
+        /// A nested Markdown list
+        Markdown,
+        /// A JSON array of sections, with their titles, line ranges and nested sections
+        Json,
+        /// Plain text, with nested sections indented
+        Text,
+    }
 
-        leading_whitespace_option.map(|num_leading_whitespace| {
-            (num_leading_whitespace, line_no_leading_spaces.to_owned())
-        })
+    /// A section in an outline. Lines are zero based, and the end is exclusive.
+    struct OutlineItem {
+        title: String,
+        lines: Range<usize>,
+        children: Vec<OutlineItem>,
     }
 
-    fn last_non_empty_line_before_index(
-        index: usize,
-        lines_list: &[String],
-    ) -> Option<(usize, &str)> {
-        for i in (0..index).rev() {
-            if !line_is_only_whitepace(&lines_list[i]) {
-                return Some((i, &lines_list[i]));
-            }
+    fn outline_items(
+        sections: &[Section],
+        line_range: &dyn Fn(&Section) -> Range<usize>,
+    ) -> Vec<OutlineItem> {
+        sections
+            .iter()
+            .map(|section| OutlineItem {
+                title: section.title.clone(),
+                lines: line_range(section),
+                children: outline_items(&section.children, line_range),
+            })
+            .collect()
+    }
+
+    /// Returns the outline of a string's structured comments
+    ///
+    /// If `str` has no bracketed structured comments, the sections that ``add_brackets`` would infer are used instead.
+    fn outline_of(str: &str, filetype: &str) -> Result<Vec<OutlineItem>, ScfmtErr> {
+        let document = parse(str, filetype)?;
+
+        if !document.sections.is_empty() {
+            return Ok(outline_items(&document.sections, &|section| {
+                section.lines()
+            }));
         }
 
-        None
-    }
+        //> infer sections of bracketless structured comments
+            let bracketed = add_brackets(str, filetype)?;
+            let comment_starter = EXTENSION_TO_COMMENT_STARTER_MAP.get(filetype).unwrap();
+            let document = parse(&bracketed, filetype)?;
+
+        //<> map lines of the bracketed string back to lines of `str`
+            // every //< was added by add_brackets, so lines after one are one further from their original line
+            let mut added_lines_before = Vec::new();
+            let mut added_lines = 0;
+            for line in bracketed.lines() {
+                added_lines_before.push(added_lines);
+                if bracket_of_line(line, comment_starter) == Some(Bracket::Close) {
+                    added_lines += 1;
+                }
+            }
 
-    fn add_open_bracket_to_last_comment(
-        lines_list: &mut Vec<String>,
-        comment_tracker: &mut [CommentDetail],
-        comment_starter: &str,
-    ) {
-        let mut should_consume_closing_comment = false;
+            let original_line = |line: usize| line - added_lines_before[line];
+        //<
 
-        //> consume any previous now unecessary //<
+        Ok(outline_items(&document.sections, &|section| {
+            original_line(section.header_line)..original_line(section.close_line)
+        }))
+    }
 
-            let line_of_latest_comment = comment_tracker[comment_tracker.len() - 1].line;
+    fn outline_title(title: &str) -> &str {
+        if title.is_empty() {
+            "(untitled)"
+        } else {
+            title
+        }
+    }
 
-            let last_solid_line_option =
-                last_non_empty_line_before_index(line_of_latest_comment, lines_list);
+    fn push_outline_lines(
+        items: &[OutlineItem],
+        depth: usize,
+        format: OutlineFormat,
+        output: &mut String,
+    ) {
+        for item in items {
+            let line = match format {
+                OutlineFormat::Markdown => format!(
+                    "{}- {} (lines {}-{})\n",
+                    "  ".repeat(depth),
+                    outline_title(&item.title),
+                    item.lines.start + 1,
+                    item.lines.end
+                ),
+                _ => format!(
+                    "{}{} ({}-{})\n",
+                    "    ".repeat(depth),
+                    outline_title(&item.title),
+                    item.lines.start + 1,
+                    item.lines.end
+                ),
+            };
+
+            output.push_str(&line);
+            push_outline_lines(&item.children, depth + 1, format, output);
+        }
+    }
 
-            // if there even could be a //< comment behind the lastest comment
-            if let Some((_last_solid_line_index, line_before_open_bracket_comment)) =
-                last_solid_line_option
-            {
-                // chop off begining spaces
-                let (leading_spaces, line_no_leading_spaces) =
-                    chop_off_beginning_spaces(line_before_open_bracket_comment);
+    fn outline_json(items: &[OutlineItem]) -> serde_json::Value {
+        items
+            .iter()
+            .map(|item| {
+                serde_json::json!({
+                    "title": item.title,
+                    "start_line": item.lines.start + 1,
+                    "end_line": item.lines.end,
+                    "children": outline_json(&item.children),
+                })
+            })
+            .collect()
+    }
 
-                // remove comment notation if it exists
-                let (is_a_comment, _, line_no_comment_opener) =
-                    remove_comment_notation_if_it_exists(line_no_leading_spaces, comment_starter);
+    /// Returns a table of contents of the structured comments in a string
+    ///
+    /// Bracketless structured comments are supported, through the same inference ``add_brackets`` uses. Line numbers in the outline start from 1.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use scfmt::scfmt::{outline, OutlineFormat};
+    ///
+    /// let to_outline = "//> tests\n    //> basic tests\n    //<\n//<";
+    ///
+    /// assert_eq!(
+    ///     outline(to_outline, "rs", OutlineFormat::Markdown).unwrap(),
+    ///     "- tests (lines 1-4)\n  - basic tests (lines 2-3)\n"
+    /// );
+    /// ```
+    pub fn outline(str: &str, filetype: &str, format: OutlineFormat) -> Result<String, ScfmtErr> {
+        let items = outline_of(str, filetype)?;
 
-                let latest_comment =
-                    match count_and_remove_begining_whitespace(&lines_list[line_of_latest_comment]) {
-                        Some(x) => x,
-                        None => (0, String::from("")),
-                    };
+        if format == OutlineFormat::Json {
+            return Ok(serde_json::to_string_pretty(&outline_json(&items)).unwrap() + "\n");
+        }
 
-                if is_a_comment
-                    && line_no_comment_opener.starts_with('<')
-                    && latest_comment.0 == leading_spaces.unwrap()
-                {
-                    should_consume_closing_comment = true;
-                }
-            }
-        //<
-        let line_with_no_bracket = lines_list[line_of_latest_comment].clone();
+        let mut output = String::new();
+        push_outline_lines(&items, 0, format, &mut output);
+        Ok(output)
+    }
 
-        if should_consume_closing_comment {
-            //> pop everything to the last //<, but remember how to restore what was popped.
-                let after_spaces = count_ending_empty_lines(lines_list);
-                remove_empty_tail(lines_list);
+    /// A range of lines an editor can fold. Lines are zero based, and both ends are inclusive.
+    #[derive(PartialEq, Debug, Clone)]
+    pub struct FoldingRange {
+        pub start_line: usize,
+        pub end_line: usize,
+    }
 
-                // remove the soon to be bracketed comment
-                // we'll add it back later
-                lines_list.pop();
+    /// A section, as a symbol editors can show in outlines and breadcrumbs
+    #[derive(PartialEq, Debug, Clone)]
+    pub struct DocumentSymbol {
+        /// Title of the section
+        pub name: String,
+        /// Zero based range of lines that belong to the section
+        pub lines: Range<usize>,
+        /// Zero based index of the section's header line
+        pub header_line: usize,
+        /// Symbols of sections nested directly inside this one
+        pub children: Vec<DocumentSymbol>,
+    }
 
-                let before_spaces = count_ending_empty_lines(lines_list);
-                remove_empty_tail(lines_list);
+    /// Returns the document of a string for editor features, skipping over any unbalanced brackets
+    fn editor_document(str: &str, filetype: &str) -> Result<Document, ScfmtErr> {
+        // determine if file compatible
+        let comment_starter = match EXTENSION_TO_COMMENT_STARTER_MAP.get(filetype) {
+            Some(x) => *x,
+            None => return Err(ScfmtErr::IncompatibleFileType),
+        };
 
-            //<> remove the //<
-                lines_list.pop();
+        Ok(parse_leniently(str, comment_starter).0)
+    }
 
-            //<> put things back and make add brackets to latest comment
+    /// Returns a folding range for every section, from its opening line to its closing line
+    ///
+    /// A ``//<>`` ends one folding range and starts the next. Unbalanced brackets are skipped, so files being edited still get folding ranges.
+    pub fn folding_ranges(str: &str, filetype: &str) -> Result<Vec<FoldingRange>, ScfmtErr> {
+        let document = editor_document(str, filetype)?;
+
+        Ok(document
+            .iter()
+            .map(|section| FoldingRange {
+                start_line: section.header_line,
+                end_line: section.lines().end - 1,
+            })
+            .collect())
+    }
 
-                append_num_empty_lines(before_spaces, lines_list);
+    fn document_symbols_of(sections: &[Section]) -> Vec<DocumentSymbol> {
+        sections
+            .iter()
+            .map(|section| DocumentSymbol {
+                name: section.title.clone(),
+                lines: section.lines(),
+                header_line: section.header_line,
+                children: document_symbols_of(&section.children),
+            })
+            .collect()
+    }
 
-                // re-append the latest comment, with added brackets
-                lines_list.push(
-                    make_comment_closed_and_open_bracket(&line_with_no_bracket, comment_starter)
-                        .unwrap(),
-                );
+    /// Returns a tree of symbols, one for every section, named after the section's title
+    ///
+    /// Unbalanced brackets are skipped, so files being edited still get symbols.
+    pub fn document_symbols(str: &str, filetype: &str) -> Result<Vec<DocumentSymbol>, ScfmtErr> {
+        let document = editor_document(str, filetype)?;
+        Ok(document_symbols_of(&document.sections))
+    }
 
-                append_num_empty_lines(after_spaces, lines_list);
-            //<
-        } else {
-            // append bracket to latest comment
-            lines_list[line_of_latest_comment] =
-                make_comment_open_bracket(&line_with_no_bracket, comment_starter).unwrap();
-        }
+    /// Returns the titles of the sections enclosing a line, from the outermost section to the innermost
+    ///
+    /// `line` is a zero based line index. A section's opening comment is inside it, and a ``//<>`` is inside the section it opens.
+    /// Unbalanced brackets are skipped, so files being edited still get a breadcrumb.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use scfmt::scfmt::breadcrumb;
+    ///
+    /// let str = "//> tests\n    //> basic tests\n        let a = 0;\n    //<\n//<";
+    ///
+    /// assert_eq!(breadcrumb(str, "rs", 2).unwrap(), vec!["tests", "basic tests"]);
+    /// ```
+    pub fn breadcrumb(str: &str, filetype: &str, line: usize) -> Result<Vec<String>, ScfmtErr> {
+        let document = editor_document(str, filetype)?;
+        Ok(breadcrumb_of(&document, line))
     }
 
-    fn line_is_only_whitepace(str: &str) -> bool {
-        for char in str.chars() {
-            if char as u32 > 32 {
-                return false;
-            }
-        }
-        true
+    fn breadcrumb_of(document: &Document, line: usize) -> Vec<String> {
+        document
+            .iter()
+            .filter(|section| section.lines().contains(&line))
+            .map(|section| section.title.clone())
+            .collect()
     }
 
-    /// Adds brackets to bracketless structured comments
-    pub fn add_brackets(str: &str, filetype: &str) -> Result<String, ScfmtErr> {
+    /// A line found by ``grep``
+    #[derive(PartialEq, Debug, Clone)]
+    pub struct GrepHit {
+        /// Zero based index of the line
+        pub line: usize,
+        /// Titles of the sections enclosing the line, from the outermost section to the innermost
+        pub breadcrumb: Vec<String>,
+    }
+
+    /// Searches the titles of sections for a pattern, returning the opening line of every section whose title matches
+    ///
+    /// If `in_section` is given, lines of code inside sections with titles it matches are searched instead. Lines holding brackets aren't searched.
+    /// Unbalanced brackets are skipped, so files being edited can still be searched.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use regex::Regex;
+    /// use scfmt::scfmt::grep;
+    ///
+    /// let str = "//> parse header\n    let a = b.unwrap();\n//<\nlet c = d.unwrap();";
+    /// let pattern = Regex::new(r"unwrap\(\)").unwrap();
+    /// let hits = grep(str, "rs", &pattern, Some(&Regex::new("^parse").unwrap())).unwrap();
+    ///
+    /// assert_eq!(hits.len(), 1);
+    /// assert_eq!(hits[0].line, 1);
+    /// assert_eq!(hits[0].breadcrumb, vec!["parse header"]);
+    /// ```
+    pub fn grep(
+        str: &str,
+        filetype: &str,
+        pattern: &Regex,
+        in_section: Option<&Regex>,
+    ) -> Result<Vec<GrepHit>, ScfmtErr> {
         // determine if file compatible
         let comment_starter = match EXTENSION_TO_COMMENT_STARTER_MAP.get(filetype) {
             Some(x) => *x,
             None => return Err(ScfmtErr::IncompatibleFileType),
         };
 
-        // remove existing brackets, so later part of this function doesn't add more on top of existing ones.
-        let str = &remove_brackets(str, filetype)?;
-
-        let (whitespace_char, _tab_spaces) = determine_whitespace_type(str);
-
-        let mut comment_tracker: Vec<CommentDetail> = Vec::new();
+        // leave ignored files alone
+        if file_is_ignored(str, comment_starter) {
+            return Ok(Vec::new());
+        }
 
-        let mut lines_list: Vec<String> = Vec::new();
-        let mut unsure_if_last_comment_was_structured = true;
+        let document = parse_leniently(str, comment_starter).0;
+        let hits: Vec<usize> = match in_section {
+            None => document
+                .iter()
+                .filter(|section| pattern.is_match(&section.title))
+                .map(|section| section.header_line)
+                .collect(),
+            Some(title) => {
+                let source_lines: Vec<&str> = str.lines().collect();
+                let mut searched = vec![false; source_lines.len()];
+                for section in document.iter().filter(|x| title.is_match(&x.title)) {
+                    for i in section.lines() {
+                        searched[i] = true;
+                    }
+                }
 
-        let mut processed_line_count = 0;
-        for line in str.lines() {
-            // counts how many lines this loop has processed
-            processed_line_count += 1;
+                (0..source_lines.len())
+                    .filter(|&i| searched[i])
+                    .filter(|&i| bracket_of_line(source_lines[i], comment_starter).is_none())
+                    .filter(|&i| pattern.is_match(source_lines[i]))
+                    .collect()
+            }
+        };
 
-            // chop off begining spaces
-            let (leading_spaces, line_no_leading_spaces) = chop_off_beginning_spaces(line);
+        Ok(hits
+            .into_iter()
+            .map(|line| GrepHit {
+                line,
+                breadcrumb: breadcrumb_of(&document, line),
+            })
+            .collect())
+    }
 
-            let (is_a_comment, _, _) =
-                remove_comment_notation_if_it_exists(line_no_leading_spaces, comment_starter);
+    //> LSP JSON
+        /// Returns the LSP range of the given lines. LSP positions count UTF-16 code units.
+        fn lsp_range(source_lines: &[&str], lines: Range<usize>) -> serde_json::Value {
+            let last_line = lines.end.max(lines.start + 1) - 1;
+            let last_line_length = match source_lines.get(last_line) {
+                Some(x) => x.encode_utf16().count(),
+                None => 0,
+            };
+
+            serde_json::json!({
+                "start": { "line": lines.start, "character": 0 },
+                "end": { "line": last_line, "character": last_line_length },
+            })
+        }
 
-            match leading_spaces {
-                Some(x) => {
-                    if is_a_comment {
-                        if !comment_tracker.is_empty() {
-                            if unsure_if_last_comment_was_structured {
-                                if x > comment_tracker[comment_tracker.len() - 1].depth {
-                                    // last was structured
+        fn folding_ranges_lsp_json(folding_ranges: &[FoldingRange]) -> serde_json::Value {
+            folding_ranges
+                .iter()
+                .map(|x| {
+                    serde_json::json!({
+                        "startLine": x.start_line,
+                        "endLine": x.end_line,
+                        "kind": "region",
+                    })
+                })
+                .collect()
+        }
 
-                                    add_open_bracket_to_last_comment(
-                                        &mut lines_list,
-                                        &mut comment_tracker,
-                                        comment_starter,
-                                    );
+        fn document_symbols_lsp_json(
+            symbols: &[DocumentSymbol],
+            source_lines: &[&str],
+        ) -> serde_json::Value {
+            symbols
+                .iter()
+                .map(|symbol| {
+                    let selection_lines = symbol.header_line..symbol.header_line + 1;
+                    serde_json::json!({
+                        // editors don't allow symbols without names
+                        "name": outline_title(&symbol.name),
+                        // SymbolKind.Namespace
+                        "kind": 3,
+                        "range": lsp_range(source_lines, symbol.lines.clone()),
+                        "selectionRange": lsp_range(source_lines, selection_lines),
+                        "children": document_symbols_lsp_json(&symbol.children, source_lines),
+                    })
+                })
+                .collect()
+        }
+    //<
 
-                                    pass_a_new_comment_that_we_dont_know_if_its_structured(
-                                        &mut lines_list,
-                                        &mut comment_tracker,
-                                        leading_spaces,
-                                        &mut unsure_if_last_comment_was_structured,
-                                        line,
-                                    );
-                                } else {
-                                    // last was not structured
+    /// Returns the folding ranges and document symbols of a string, as the JSON a language server would send
+    ///
+    /// The result is an object with a ``foldingRanges`` array of ``FoldingRange``s, and a ``documentSymbols`` array of ``DocumentSymbol``s.
+    pub fn symbols_lsp_json(str: &str, filetype: &str) -> Result<String, ScfmtErr> {
+        let source_lines: Vec<&str> = str.lines().collect();
 
-                                    comment_tracker.pop();
+        let json = serde_json::json!({
+            "foldingRanges": folding_ranges_lsp_json(&folding_ranges(str, filetype)?),
+            "documentSymbols": document_symbols_lsp_json(&document_symbols(str, filetype)?, &source_lines),
+        });
 
-                                    end_the_last_structured_comments(
-                                        &mut lines_list,
-                                        &mut comment_tracker,
-                                        x,
-                                        comment_starter,
-                                        whitespace_char,
-                                    );
+        Ok(serde_json::to_string_pretty(&json).unwrap() + "\n")
+    }
 
-                                    pass_a_new_comment_that_we_dont_know_if_its_structured(
-                                        &mut lines_list,
-                                        &mut comment_tracker,
-                                        leading_spaces,
-                                        &mut unsure_if_last_comment_was_structured,
-                                        line,
-                                    );
-                                }
-                            } else if x > comment_tracker[comment_tracker.len() - 1].depth {
-                                pass_a_new_comment_that_we_dont_know_if_its_structured(
-                                    &mut lines_list,
-                                    &mut comment_tracker,
-                                    leading_spaces,
-                                    &mut unsure_if_last_comment_was_structured,
-                                    line,
-                                );
-                            } else {
-                                end_the_last_structured_comments(
-                                    &mut lines_list,
-                                    &mut comment_tracker,
-                                    x,
-                                    comment_starter,
-                                    whitespace_char,
-                                );
+    /// Kinds of fold markers editors use to mark regions of code
+    #[derive(PartialEq, Debug, Clone, Copy)]
+    pub enum Markers {
+        /// ``// #region Foo`` and ``// #endregion``, as used by VS Code. Also matches C#'s ``#region`` preprocessor directive and ``#pragma region``.
+        Region,
+        /// ``// <editor-fold desc="Foo">`` and ``// </editor-fold>``, as used by IntelliJ
+        EditorFold,
+        /// ``// Foo {{{`` and ``// }}}``, as used by vim's marker folding
+        Vim,
+    }
 
-                                pass_a_new_comment_that_we_dont_know_if_its_structured(
-                                    &mut lines_list,
-                                    &mut comment_tracker,
-                                    leading_spaces,
-                                    &mut unsure_if_last_comment_was_structured,
-                                    line,
-                                );
-                            }
-                        } else {
-                            pass_a_new_comment_that_we_dont_know_if_its_structured(
-                                &mut lines_list,
-                                &mut comment_tracker,
-                                leading_spaces,
-                                &mut unsure_if_last_comment_was_structured,
-                                line,
-                            );
-                        }
-                    } else if !comment_tracker.is_empty() {
-                        if unsure_if_last_comment_was_structured {
-                            if x > comment_tracker[comment_tracker.len() - 1].depth {
-                                // last was structured
+    /// A line that starts or ends a marked region
+    enum MarkerLine {
+        Start(String),
+        End,
+    }
 
-                                add_open_bracket_to_last_comment(
-                                    &mut lines_list,
-                                    &mut comment_tracker,
-                                    comment_starter,
-                                );
-                            } else {
-                                // last was not structured
+    /// Returns what marker a line holds, if any
+    fn marker_of_line(line: &str, comment_starter: &str, markers: Markers) -> Option<MarkerLine> {
+        let (_, line_no_leading_spaces) = chop_off_beginning_spaces(line);
+        let (is_a_comment, _, line_no_comment_starter) =
+            remove_comment_notation_if_it_exists(line_no_leading_spaces, comment_starter);
+        let comment = line_no_comment_starter.trim();
 
-                                comment_tracker.pop();
+        match markers {
+            Markers::Region => {
+                //> #region may be a comment, or a preprocessor directive like in C#
+                    let directive = if is_a_comment && comment_starter != "#" {
+                        comment
+                    } else {
+                        line_no_leading_spaces.trim_end()
+                    };
+                    let directive = match directive.strip_prefix("#pragma ") {
+                        Some(x) => x,
+                        None => directive.strip_prefix('#')?,
+                    };
+                    let directive = directive.trim_start();
+                //<
 
-                                end_the_last_structured_comments(
-                                    &mut lines_list,
-                                    &mut comment_tracker,
-                                    x,
-                                    comment_starter,
-                                    whitespace_char,
-                                );
-                            }
-                            unsure_if_last_comment_was_structured = false;
+                if let Some(title) = directive.strip_prefix("endregion") {
+                    if title.is_empty() || title.starts_with(char::is_whitespace) {
+                        return Some(MarkerLine::End);
+                    }
+                } else if let Some(title) = directive.strip_prefix("region") {
+                    if title.is_empty() || title.starts_with(char::is_whitespace) {
+                        return Some(MarkerLine::Start(title.trim().to_owned()));
+                    }
+                }
 
-                            lines_list.push(String::from(line));
-                        } else if x > comment_tracker[comment_tracker.len() - 1].depth {
-                            lines_list.push(String::from(line));
-                        } else {
-                            end_the_last_structured_comments(
-                                &mut lines_list,
-                                &mut comment_tracker,
-                                x,
-                                comment_starter,
-                                whitespace_char,
-                            );
+                None
+            }
+            Markers::EditorFold if is_a_comment => {
+                if comment.starts_with("</editor-fold") {
+                    Some(MarkerLine::End)
+                } else if comment.starts_with("<editor-fold") {
+                    //> title is the desc attribute
+                        let title = match comment.split_once("desc=\"") {
+                            Some((_, desc)) => match desc.split_once('"') {
+                                Some((desc, _)) => desc,
+                                None => desc,
+                            },
+                            None => "",
+                        };
+                    //<
 
-                            // forward the current line
-                            lines_list.push(String::from(line));
-                        }
+                    Some(MarkerLine::Start(
+                        title.replace("&quot;", "\"").trim().to_owned(),
+                    ))
+                } else {
+                    None
+                }
+            }
+            Markers::Vim if is_a_comment => {
+                // markers may be followed by a fold level
+                let comment = comment.trim_end_matches(|x: char| x.is_ascii_digit());
+
+                if let Some(before) = comment.strip_suffix("}}}") {
+                    // a }}} closing a {{{ earlier on the line, like in {{{a}}}, is text rather than a marker
+                    if before.matches("{{{").count() > before.matches("}}}").count() {
+                        None
                     } else {
-                        lines_list.push(String::from(line));
+                        Some(MarkerLine::End)
                     }
-                }
-                None => {
-                    lines_list.push("".to_owned());
+                } else {
+                    comment
+                        .strip_suffix("{{{")
+                        .map(|title| MarkerLine::Start(title.trim().to_owned()))
                 }
             }
+            _ => None,
         }
+    }
 
-        //> last comment was not structured, if it was the last non empty line in the String
-            if unsure_if_last_comment_was_structured && !comment_tracker.is_empty() {
-                comment_tracker.pop();
+    /// Returns a line holding a marker, with the given leading whitespace
+    fn marker_line(
+        marker: MarkerLine,
+        whitespace: &str,
+        comment_starter: &str,
+        filetype: &str,
+        markers: Markers,
+    ) -> String {
+        let with_title = |before: &str, title: &str, after: &str| {
+            if title.is_empty() {
+                before.trim_end().to_owned() + after
+            } else {
+                before.to_owned() + title + after
             }
-        //<
+        };
 
-        end_the_last_structured_comments(
-            &mut lines_list,
-            &mut comment_tracker,
-            0,
-            comment_starter,
-            whitespace_char,
-        );
+        let marker = match (markers, marker) {
+            (Markers::Region, marker) => {
+                // C# uses a preprocessor directive, and languages commenting with '#' already start with one
+                let starter = if filetype == "cs" || filetype == "csx" {
+                    "#".to_owned()
+                } else if comment_starter == "#" {
+                    comment_starter.to_owned()
+                } else {
+                    comment_starter.to_owned() + " #"
+                };
 
-        //> turn all lines into one string
-            let mut final_string = String::new();
-            for line in lines_list {
-                final_string.push_str(&line);
-                final_string.push('\n');
+                match marker {
+                    MarkerLine::Start(title) => with_title(&(starter + "region "), &title, ""),
+                    MarkerLine::End => starter + "endregion",
+                }
+            }
+            (Markers::EditorFold, MarkerLine::Start(title)) => {
+                comment_starter.to_owned()
+                    + " <editor-fold desc=\""
+                    + &title.replace('"', "&quot;")
+                    + "\">"
+            }
+            (Markers::EditorFold, MarkerLine::End) => {
+                comment_starter.to_owned() + " </editor-fold>"
+            }
+            (Markers::Vim, MarkerLine::Start(title)) => {
+                with_title(&(comment_starter.to_owned() + " "), &title, " {{{")
+            }
+            (Markers::Vim, MarkerLine::End) => comment_starter.to_owned() + " }}}",
+        };
+
+        whitespace.to_owned() + &marker
+    }
+
+    /// Converts fold markers into bracketed structured comments, then formats the result
+    ///
+    /// Nested markers become nested sections. Markers that don't start or end where they should are reported like unbalanced brackets.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use scfmt::scfmt::{markers_to_brackets, Markers};
+    ///
+    /// let to_convert = "// #region setup\nlet a = 0;\n// #endregion";
+    ///
+    /// assert_eq!(
+    ///     markers_to_brackets(to_convert, "ts", Markers::Region).unwrap(),
+    ///     "//> setup\n    let a = 0;\n//<"
+    /// );
+    /// ```
+    pub fn markers_to_brackets(
+        str: &str,
+        filetype: &str,
+        markers: Markers,
+    ) -> Result<String, ScfmtErr> {
+        // determine if file compatible
+        let comment_starter = match EXTENSION_TO_COMMENT_STARTER_MAP.get(filetype) {
+            Some(x) => *x,
+            None => return Err(ScfmtErr::IncompatibleFileType),
+        };
+
+        //> replace markers with brackets
+            let mut lines_list = Vec::new();
+            for line in str.lines() {
+                let whitespace = &line[..line.len() - line.trim_start().len()];
+
+                let converted = match marker_of_line(line, comment_starter, markers) {
+                    Some(MarkerLine::Start(title)) if title.is_empty() => {
+                        whitespace.to_owned() + comment_starter + ">"
+                    }
+                    Some(MarkerLine::Start(title)) => {
+                        whitespace.to_owned() + comment_starter + "> " + &title
+                    }
+                    Some(MarkerLine::End) => whitespace.to_owned() + comment_starter + "<",
+                    None => line.to_owned(),
+                };
+
+                lines_list.push(converted + "\n");
             }
         //<
 
-        // remove last '\n'
-        final_string.pop();
+        format_str(&join_formatted_lines(lines_list, str), filetype)
+    }
 
-        //> append any missed empty lines, as the .lines() function may have skipped them.
-            let line_diff = count_lines(str) - processed_line_count;
+    /// Converts bracketed structured comments into fold markers
+    ///
+    /// The string is formatted first. A ``//<>`` becomes a line ending one region, and a line starting the next.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use scfmt::scfmt::{brackets_to_markers, Markers};
+    ///
+    /// let to_convert = "//> setup\n    let a = 0;\n//<";
+    ///
+    /// assert_eq!(
+    ///     brackets_to_markers(to_convert, "rs", Markers::Vim).unwrap(),
+    ///     "// setup {{{\n    let a = 0;\n// }}}"
+    /// );
+    /// ```
+    pub fn brackets_to_markers(
+        str: &str,
+        filetype: &str,
+        markers: Markers,
+    ) -> Result<String, ScfmtErr> {
+        // determine if file compatible
+        let comment_starter = match EXTENSION_TO_COMMENT_STARTER_MAP.get(filetype) {
+            Some(x) => *x,
+            None => return Err(ScfmtErr::IncompatibleFileType),
+        };
 
-            if line_diff > 0 {
-                for _ in 0..line_diff {
-                    final_string.push('\n');
+        let str = &format_str(str, filetype)?;
+        let document = parse_with_comment_starter(str, comment_starter)?;
+
+        let mut lines_list: Vec<String> = str.lines().map(|line| line.to_owned() + "\n").collect();
+
+        //> replace brackets with markers
+            for section in document.iter() {
+                let header = &lines_list[section.header_line];
+                let whitespace = header[..header.len() - header.trim_start().len()].to_owned();
+
+                let mut converted = String::new();
+                if section.opened_by == Bracket::CloseAndOpen {
+                    converted += &(marker_line(
+                        MarkerLine::End,
+                        &whitespace,
+                        comment_starter,
+                        filetype,
+                        markers,
+                    ) + "\n");
+                }
+                converted += &marker_line(
+                    MarkerLine::Start(section.title.clone()),
+                    &whitespace,
+                    comment_starter,
+                    filetype,
+                    markers,
+                );
+                lines_list[section.header_line] = converted + "\n";
+
+                if section.closed_by == Bracket::Close {
+                    lines_list[section.close_line] = marker_line(
+                        MarkerLine::End,
+                        &whitespace,
+                        comment_starter,
+                        filetype,
+                        markers,
+                    ) + "\n";
                 }
             }
         //<
 
-        Ok(final_string)
+        Ok(join_formatted_lines(lines_list, str))
+    }
+
+    /// Runs ``markers_to_brackets`` on contents of given file
+    pub fn markers_to_brackets_file(file: PathBuf, markers: Markers) -> Result<(), ScfmtErr> {
+        transform_file(file, &|contents, extension| {
+            markers_to_brackets(contents, extension, markers)
+        })
+    }
+
+    /// Runs ``brackets_to_markers`` on contents of given file
+    pub fn brackets_to_markers_file(file: PathBuf, markers: Markers) -> Result<(), ScfmtErr> {
+        transform_file(file, &|contents, extension| {
+            brackets_to_markers(contents, extension, markers)
+        })
     }
 
-    /// Adds a '_' character in front of any comment brackets. Nullifying any existing bracketed structured comments, without removing any characters.
+    /// Labels every ``//<`` with the title of the section it closes, replacing any label it already had
     ///
-    /// This is intended to be run on existing codebases that have not previously been using structured commenting. As brackets may exist in comments that were not intended to be structured comments.
-    pub fn null_existing_brackets(str: &str, filetype: &str) -> Result<String, ScfmtErr> {
+    /// A ``scfmt: labeled-closers`` comment is added to the top of the string if it doesn't have one, so formatting keeps the labels and checks that they match.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use scfmt::scfmt::label_closers;
+    ///
+    /// let to_label = "//> setup\n    let a = 0;\n//< old title";
+    /// let labeled = label_closers(to_label, "rs").unwrap();
+    ///
+    /// assert_eq!(
+    ///     labeled,
+    ///     "// scfmt: labeled-closers\n//> setup\n    let a = 0;\n//< setup"
+    /// );
+    /// ```
+    pub fn label_closers(str: &str, filetype: &str) -> Result<String, ScfmtErr> {
         // determine if file compatible
         let comment_starter = match EXTENSION_TO_COMMENT_STARTER_MAP.get(filetype) {
             Some(x) => *x,
             None => return Err(ScfmtErr::IncompatibleFileType),
         };
 
-        let (whitespace_char, _tab_spaces) = determine_whitespace_type(str);
-        let mut lines_list = Vec::new();
-        let mut processed_line_count = 0;
-        for line in str.lines() {
-            // counts how many lines this loop has processed
-            processed_line_count += 1;
-
-            // chop off begining spaces
-            let (_leading_spaces, line_no_leading_spaces) = chop_off_beginning_spaces(line);
+        // leave ignored files alone
+        if file_is_ignored(str, comment_starter) {
+            return Ok(str.to_owned());
+        }
 
-            // remove comment notation if it exists
-            let (is_a_comment, space_after_comment_starter, line_no_comment_starter) =
-                remove_comment_notation_if_it_exists(line_no_leading_spaces, comment_starter);
+        //> parse, where labels that don't match are about to be replaced anyway
+            let (document, errors) = parse_leniently(str, comment_starter);
+            let bracket_error = errors
+                .into_iter()
+                .find(|x| !matches!(x, ScfmtErr::ClosingLabelMismatch(_)));
+            if let Some(err) = bracket_error {
+                return Err(err);
+            }
+        //<
 
-            if is_a_comment && line_no_comment_starter.starts_with('<')
-                || line_no_comment_starter.starts_with('>')
-            {
-                let depth_option = count_and_remove_begining_whitespace(line);
-                let depth = match depth_option {
-                    Some(x) => x.0,
-                    None => 0,
-                };
+        let mut lines_list: Vec<String> = str.lines().map(|line| line.to_owned() + "\n").collect();
 
-                let potential_space = if space_after_comment_starter { " " } else { "" };
+        //> replace labels
+            for section in document.iter() {
+                if section.closed_by != Bracket::Close {
+                    continue;
+                }
 
-                lines_list.push(add_whitespace(
-                    &(comment_starter.to_owned()
-                        + potential_space
-                        + "_"
-                        + &line_no_comment_starter),
-                    depth,
-                    whitespace_char,
-                ));
-            } else if line_is_only_whitepace(line) {
-                lines_list.push("".to_owned());
-            } else {
-                lines_list.push(line.to_owned());
-            }
-        }
+                let line = &lines_list[section.close_line];
+                let (leading_spaces, line_no_leading_spaces) = chop_off_beginning_spaces(line);
+                let (_, space_after_comment_starter, _) =
+                    remove_comment_notation_if_it_exists(line_no_leading_spaces, comment_starter);
+                let possible_space = if space_after_comment_starter { " " } else { "" };
+                let label = if section.title.is_empty() {
+                    String::new()
+                } else {
+                    " ".to_owned() + &section.title
+                };
 
-        //> turn all lines into one string
-            let mut final_string = String::new();
-            for line in lines_list {
-                final_string.push_str(&line);
-                final_string.push('\n');
+                let leading_whitespace = &line[..leading_spaces.unwrap()];
+                lines_list[section.close_line] = leading_whitespace.to_owned()
+                    + comment_starter
+                    + possible_space
+                    + "<"
+                    + &label
+                    + "\n";
             }
         //<
 
-        // remove last '\n'
-        final_string.pop();
-
-        //> append any missed empty lines, as the .lines() function may have skipped them.
-            let line_diff = count_lines(str) - processed_line_count;
+        //> opt in to labeled closers, below any shebang line
+            if !closers_are_labeled(str, comment_starter) {
+                let pragma_line = match lines_list.first() {
+                    Some(x) if x.starts_with("#!") => 1,
+                    _ => 0,
+                };
 
-            if line_diff > 0 {
-                for _ in 0..line_diff {
-                    final_string.push('\n');
-                }
+                lines_list.insert(
+                    pragma_line,
+                    comment_starter.to_owned() + " scfmt: labeled-closers\n",
+                );
             }
         //<
 
-        Ok(final_string)
+        Ok(join_formatted_lines(lines_list, str))
     }
 
-    fn count_lines(str: &str) -> usize {
-        if str.is_empty() {
-            return 0;
-        }
+    /// Runs ``label_closers`` on contents of given file
+    pub fn label_closers_file(file: PathBuf) -> Result<(), ScfmtErr> {
+        transform_file(file, &label_closers)
+    }
 
-        let mut count = 1;
-        for c in str.chars() {
-            if c == '\n' {
-                count += 1;
+    /// A change ``repair`` made to balance brackets. Lines are zero based indexes into the string that was repaired.
+    #[derive(PartialEq, Debug, Clone)]
+    pub enum Repair {
+        /// Added a ``//<`` below `after_line`, closing the section opened on `header_line`
+        AddedCloser {
+            header_line: usize,
+            after_line: usize,
+        },
+        /// Removed a ``//<`` that closed nothing
+        RemovedCloser(usize),
+        /// Nullified a ``//<`` that closed nothing, keeping the text after it
+        NullifiedCloser(usize),
+        /// Turned a ``//<>`` that closed nothing into a ``//>``
+        OpenedOrphan(usize),
+    }
+
+    impl Repair {
+        /// Zero based index of the line the repair was made at
+        pub fn line(&self) -> usize {
+            match self {
+                Repair::AddedCloser { after_line, .. } => *after_line,
+                Repair::RemovedCloser(line)
+                | Repair::NullifiedCloser(line)
+                | Repair::OpenedOrphan(line) => *line,
             }
         }
-        count
     }
 
-    /// Runs ``remove_brackets`` on contents of given file
-    pub fn remove_brackets_file(file: PathBuf) -> Result<(), ScfmtErr> {
-        let extenstion = match file.extension() {
-            Some(x) => match x.to_str() {
-                Some(x) => x,
-                None => return Err(ScfmtErr::CantConvertOsString),
-            },
+    /// Balances the brackets of a string, then formats it. Returns the repaired string, and every change that was made.
+    ///
+    /// Missing ``//<`` are added where the indentation of the following lines says the section ends, like ``add_brackets`` does.
+    /// A ``//<`` that closes nothing is removed, or nullified if there is text after it. A ``//<>`` that closes nothing is turned into a ``//>``.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use scfmt::scfmt::{repair, Repair};
+    ///
+    /// let to_repair = "//<\n//> setup\n    let a = 0;\nlet b = 0;\n";
+    /// let (repaired, repairs) = repair(to_repair, "rs").unwrap();
+    ///
+    /// assert_eq!(repaired, "//> setup\n    let a = 0;\n//<\nlet b = 0;\n");
+    /// assert_eq!(
+    ///     repairs,
+    ///     vec![
+    ///         Repair::RemovedCloser(0),
+    ///         Repair::AddedCloser { header_line: 1, after_line: 2 },
+    ///     ]
+    /// );
+    /// ```
+    pub fn repair(str: &str, filetype: &str) -> Result<(String, Vec<Repair>), ScfmtErr> {
+        // determine if file compatible
+        let comment_starter = match EXTENSION_TO_COMMENT_STARTER_MAP.get(filetype) {
+            Some(x) => *x,
             None => return Err(ScfmtErr::IncompatibleFileType),
         };
 
-        let contents = match fs::read_to_string(&file) {
-            Ok(x) => x,
-            Err(_) => return Err(ScfmtErr::CantReadFileAsString),
-        };
+        // leave ignored files alone
+        if file_is_ignored(str, comment_starter) {
+            return Ok((str.to_owned(), Vec::new()));
+        }
 
-        let converted = remove_brackets(&contents, extenstion)?;
+        // only touch brackets when they are unbalanced, so sections that are fine are never moved
+        let (_, errors) = parse_leniently(str, comment_starter);
+        if errors
+            .iter()
+            .all(|x| matches!(x, ScfmtErr::ClosingLabelMismatch(_)))
+        {
+            return Ok((format_str(str, filetype)?, Vec::new()));
+        }
 
-        //> write file
-            // leave file alone if there was no change
-            if converted != contents {
-                let mut output = match File::create(file) {
-                    Ok(x) => x,
-                    Err(_) => return Err(ScfmtErr::CantCreatFile),
-                };
+        let source_lines: Vec<&str> = str.lines().collect();
+        let turned_off = lines_turned_off(str, comment_starter);
+        let (whitespace_char, _tab_spaces) = determine_turned_on_whitespace_type(str, &turned_off);
+        let labeled = closers_are_labeled(str, comment_starter);
+
+        let mut repairs = Vec::new();
+        // what each source line becomes, or None if it is removed
+        let mut lines_list: Vec<Option<String>> =
+            source_lines.iter().map(|x| Some(x.to_string())).collect();
+        // header line of each section given a new closer, the source line to add the closer below, and the closer
+        let mut added_closers: Vec<(usize, usize, String)> = Vec::new();
+
+        struct OpenSection {
+            header_line: usize,
+            indentation: usize,
+            title: String,
+            /// First line after the header and any nested sections that were closed
+            body_start: usize,
+            /// Whether a line inside the section was indented deeper than its header
+            indented_body: bool,
+        }
 
-                match write!(output, "{}", converted) {
-                    Ok(x) => x,
-                    Err(_) => return Err(ScfmtErr::CantWriteToFile),
-                };
+        // closes a section with a new //<, placed after the last non empty line before `end`
+        let mut add_closer = |section: OpenSection, end: usize| {
+            let after_line = (section.header_line..end)
+                .rev()
+                .find(|&i| !line_is_only_whitepace(source_lines[i]))
+                .unwrap_or(section.header_line);
+
+            let mut closer =
+                new_comment_closed_bracket(section.indentation, comment_starter, whitespace_char)
+                    .unwrap();
+            if labeled && !section.title.is_empty() {
+                closer = closer + " " + &section.title;
             }
-        //<
-
-        Ok(())
-    }
 
-    /// Runs ``null_existing_brackets`` on contents of given file
-    pub fn null_existing_brackets_file(file: PathBuf) -> Result<(), ScfmtErr> {
-        let extenstion = match file.extension() {
-            Some(x) => match x.to_str() {
-                Some(x) => x,
-                None => return Err(ScfmtErr::CantConvertOsString),
-            },
-            None => return Err(ScfmtErr::IncompatibleFileType),
+            added_closers.push((section.header_line, after_line, closer));
         };
 
-        let contents = match fs::read_to_string(&file) {
-            Ok(x) => x,
-            Err(_) => return Err(ScfmtErr::CantReadFileAsString),
-        };
+        let mut open_sections: Vec<OpenSection> = Vec::new();
+        for (i, line) in source_lines.iter().enumerate() {
+            if turned_off[i] || line_is_only_whitepace(line) {
+                continue;
+            }
+            let own_indentation = chop_off_beginning_spaces(line).0.unwrap();
+            let bracket = bracket_of_line(line, comment_starter);
+
+            //> close sections this line is indented at or below, like add_brackets does
+                if bracket.is_none() || bracket == Some(Bracket::Open) {
+                    // a line as indented as a section only ends it when its body was indented deeper
+                    while let Some(section) = open_sections.last() {
+                        let ends_section = own_indentation < section.indentation
+                            || own_indentation == section.indentation && section.indented_body;
+                        if !ends_section {
+                            break;
+                        }
 
-        let converted = null_existing_brackets(&contents, extenstion)?;
+                        add_closer(open_sections.pop().unwrap(), i);
+                        if let Some(parent) = open_sections.last_mut() {
+                            parent.body_start = i;
+                        }
+                    }
 
-        //> write file
-            // leave file alone if there was no change
-            if converted != contents {
-                let mut output = match File::create(file) {
-                    Ok(x) => x,
-                    Err(_) => return Err(ScfmtErr::CantCreatFile),
-                };
+                    for section in open_sections.iter_mut() {
+                        if own_indentation > section.indentation {
+                            section.indented_body = true;
+                        }
+                    }
+                }
+            //<
 
-                match write!(output, "{}", converted) {
-                    Ok(x) => x,
-                    Err(_) => return Err(ScfmtErr::CantWriteToFile),
-                };
-            }
-        //<
+            let bracket = match bracket {
+                Some(x) => x,
+                None => continue,
+            };
+            let mut indentation = own_indentation;
+
+            if bracket != Bracket::Open {
+                //> close sections indented deeper than this closer, when a section it could close is further out
+                    while let Some(section) = open_sections.last() {
+                        let could_close_outer = open_sections
+                            .iter()
+                            .any(|x| x.indentation <= own_indentation);
+
+                        if section.indentation <= own_indentation || !could_close_outer {
+                            break;
+                        }
 
-        Ok(())
-    }
+                        add_closer(open_sections.pop().unwrap(), i);
+                    }
 
-    fn line_is_a_comment(str: &str, comment_starter: &str) -> bool {
-        match count_and_remove_begining_whitespace(str) {
-            Some(x) => {
-                let comment_starter_with_space = comment_starter.to_owned() + " ";
-                let str = x.1;
+                //<> close a section, or repair a closer that closes nothing
+                    match open_sections.pop() {
+                        Some(section) => {
+                            indentation = section.indentation;
+                            if let Some(parent) = open_sections.last_mut() {
+                                parent.body_start = i + 1;
+                            }
+                        }
+                        None if bracket == Bracket::CloseAndOpen => {
+                            lines_list[i] = Some(line.replacen("<>", ">", 1));
+                            repairs.push(Repair::OpenedOrphan(i));
+                        }
+                        None if line_is_only_whitepace(&text_after_bracket(line, comment_starter)) => {
+                            lines_list[i] = None;
+                            repairs.push(Repair::RemovedCloser(i));
+                        }
+                        None => {
+                            lines_list[i] = Some(line.replacen('<', "_<", 1));
+                            repairs.push(Repair::NullifiedCloser(i));
+                        }
+                    }
+                //<
+            }
 
-                if str.starts_with(&comment_starter_with_space) {
-                    true
-                } else {
-                    str.starts_with(comment_starter)
-                }
+            if bracket != Bracket::Close {
+                open_sections.push(OpenSection {
+                    header_line: i,
+                    indentation,
+                    title: text_after_bracket(line, comment_starter).trim().to_owned(),
+                    body_start: i + 1,
+                    indented_body: false,
+                });
             }
-            None => false,
         }
-    }
 
-    fn remove_comment_starter(str: &str, comment_starter: &str) -> String {
-        match count_and_remove_begining_whitespace(str) {
-            Some(x) => {
-                let str = x.1;
+        //> close sections that were never closed, where their indentation ends
+            while let Some(section) = open_sections.pop() {
+                let end = (section.body_start..source_lines.len())
+                    .find(|&i| {
+                        let line = source_lines[i];
+                        !turned_off[i]
+                            && !line_is_only_whitepace(line)
+                            && chop_off_beginning_spaces(line).0.unwrap() <= section.indentation
+                    })
+                    .unwrap_or(source_lines.len());
+
+                add_closer(section, end);
+            }
+        //<
 
-                let mut line_no_comment_starter = "";
-                let comment_starter_with_space = comment_starter.to_owned() + " ";
-                if str.starts_with(&comment_starter_with_space) {
-                    line_no_comment_starter = &str[comment_starter.len() + 1..];
-                } else if let Some(stripped) = str.strip_prefix(comment_starter) {
-                    line_no_comment_starter = stripped;
+        //> build the repaired string
+            let mut repaired_lines = Vec::new();
+            for (i, line) in lines_list.into_iter().enumerate() {
+                if let Some(line) = line {
+                    repaired_lines.push(line + "\n");
                 }
 
-                line_no_comment_starter.to_string()
+                for (_, _, closer) in added_closers.iter().filter(|x| x.1 == i) {
+                    repaired_lines.push(closer.to_owned() + "\n");
+                }
             }
-            None => str.to_owned(),
-        }
-    }
+            let repaired = join_formatted_lines(repaired_lines, str);
+        //<
 
-    fn count_ending_empty_lines(lines_list: &[String]) -> usize {
-        let mut count = 0;
-        for i in (0..lines_list.len()).rev() {
-            if !line_is_only_whitepace(&lines_list[i]) {
-                break;
-            }
-            count += 1;
+        for (header_line, after_line, _) in added_closers {
+            repairs.push(Repair::AddedCloser {
+                header_line,
+                after_line,
+            });
         }
+        repairs.sort_by_key(|x| x.line());
 
-        count
+        Ok((format_str(&repaired, filetype)?, repairs))
     }
 
-    fn append_num_empty_lines(num: usize, lines_list: &mut Vec<String>) {
-        for _ in 0..num {
-            lines_list.push("".to_owned());
-        }
+    /// Runs ``repair`` on contents of given file, returning the changes that were made
+    pub fn repair_file(file: PathBuf) -> Result<Vec<Repair>, ScfmtErr> {
+        let repairs = RefCell::new(Vec::new());
+        transform_file(file, &|contents, extension| {
+            let (repaired, file_repairs) = repair(contents, extension)?;
+            repairs.replace(file_repairs);
+            Ok(repaired)
+        })?;
+
+        Ok(repairs.into_inner())
     }
 
-    /// Converts bracketed structured comments into bracketless structured comments
-    ///
-    /// Becuase bracketless structured comments rely soley on indentation to show what lines they are talking about, this function formats the input str before removing bracket comments. To ensure structured comment information is not lost.
-    pub fn remove_brackets(str: &str, filetype: &str) -> Result<String, ScfmtErr> {
-        // determine if file compatible
-        let comment_starter = match EXTENSION_TO_COMMENT_STARTER_MAP.get(filetype) {
-            Some(x) => *x,
-            None => return Err(ScfmtErr::IncompatibleFileType),
-        };
+    /// The lines of a section taken out of a string by ``extract``
+    #[derive(PartialEq, Debug, Clone)]
+    pub struct Extracted {
+        /// Byte range of the lines in the string they were taken from, including the '\n' ending the last line
+        pub range: Range<usize>,
+        /// The lines, with the indentation they share removed. Every line ends with a '\n'.
+        pub text: String,
+    }
 
-        let mut lines_list: Vec<String> = Vec::new();
+    /// Returns byte ranges of every line in a string, including the line's ending '\n'
+    fn line_byte_ranges(str: &str) -> Vec<Range<usize>> {
+        let mut ranges = Vec::new();
+        let mut start = 0;
+        for line in str.split_inclusive('\n') {
+            ranges.push(start..start + line.len());
+            start += line.len();
+        }
 
-        //format str before removing brackets, to ensure their information is not lost.
-        let str = &format_str(str, filetype)?;
+        ranges
+    }
 
-        let (whitespace_char, _tab_spaces) = determine_whitespace_type(str);
+    /// Removes the indentation that every non empty line shares
+    fn dedent(lines: &[&str]) -> String {
+        let shared_indentation = lines
+            .iter()
+            .filter_map(|line| chop_off_beginning_spaces(line).0)
+            .min()
+            .unwrap_or(0);
+
+        let mut dedented = String::new();
+        for line in lines {
+            if !line_is_only_whitepace(line) {
+                dedented.push_str(&line[shared_indentation..]);
+            }
+            dedented.push('\n');
+        }
 
-        let mut formatted_str = String::new();
-        let mut processed_line_count = 0;
+        dedented
+    }
 
-        for line in str.lines() {
-            // counts how many lines this loop has processed
-            processed_line_count += 1;
+    /// Takes the body of every section at a path of titles out of a string, such as ``"tests/basic tests"``
+    ///
+    /// Titles in `path` may be glob patterns, like ``Document::find_matching`` takes. If `with_brackets` is true, the section's opening and closing comments are included.
+    /// A closing ``//<>`` is never included, as it opens the next section.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use scfmt::scfmt::extract;
+    ///
+    /// let to_extract = "//> tests\n    //> basic\n        let a = 0;\n    //<\n//<\n";
+    /// let extracted = extract(to_extract, "rs", "tests/b*", false).unwrap();
+    ///
+    /// assert_eq!(extracted[0].text, "let a = 0;\n");
+    /// assert_eq!(&to_extract[extracted[0].range.clone()], "        let a = 0;\n");
+    /// ```
+    pub fn extract(
+        str: &str,
+        filetype: &str,
+        path: &str,
+        with_brackets: bool,
+    ) -> Result<Vec<Extracted>, ScfmtErr> {
+        let document = parse(str, filetype)?;
+        let source_lines: Vec<&str> = str.lines().collect();
+        let line_ranges = line_byte_ranges(str);
+
+        let mut extracted = Vec::new();
+        for section in document.find_matching(path) {
+            let lines = if with_brackets {
+                section.lines()
+            } else {
+                section.body.clone()
+            };
+
+            let range = match (line_ranges.get(lines.start), line_ranges.get(lines.end - 1)) {
+                (Some(first), Some(last)) if !lines.is_empty() => first.start..last.end,
+                _ => line_ranges[section.header_line].end..line_ranges[section.header_line].end,
+            };
+
+            extracted.push(Extracted {
+                range,
+                text: dedent(&source_lines[lines]),
+            });
+        }
 
-            let line_no_leading_whitespace;
-            let leading_whitespace;
+        Ok(extracted)
+    }
 
-            if let Some(x) = count_and_remove_begining_whitespace(line) {
-                leading_whitespace = x.0;
-                line_no_leading_whitespace = &x.1;
+    /// A line of the collapsed view of a string made by ``view``
+    #[derive(PartialEq, Debug, Clone)]
+    pub enum ViewLine {
+        /// A line of the string, shown as it is
+        Line(String),
+        /// Lines of a section that were hidden, below its header
+        Collapsed {
+            /// Leading whitespace of the first hidden line that isn't empty
+            indentation: String,
+            /// How many lines were hidden
+            lines: usize,
+        },
+    }
 
-                if line_is_a_comment(line_no_leading_whitespace, comment_starter) {
-                    let line_no_comment_starter =
-                        remove_comment_starter(line_no_leading_whitespace, comment_starter);
+    /// Returns the lines of a string with every section nested `depth` or more sections deep collapsed, hiding everything below its header
+    ///
+    /// Sections that aren't nested in another section have a depth of 0, so a `depth` of 1 only shows what is inside those sections.
+    /// Sections at a path in `expand`, and the sections leading to them, are never collapsed. Paths may contain glob patterns, like ``Document::find_matching`` takes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use scfmt::scfmt::{view, ViewLine};
+    ///
+    /// let to_view = "//> setup\n    let a = 0;\n    let b = 0;\n//<";
+    /// let lines = view(to_view, "rs", 0, &[]).unwrap();
+    ///
+    /// assert_eq!(
+    ///     lines,
+    ///     vec![
+    ///         ViewLine::Line("//> setup".to_owned()),
+    ///         ViewLine::Collapsed { indentation: "    ".to_owned(), lines: 3 },
+    ///     ]
+    /// );
+    /// ```
+    pub fn view(
+        str: &str,
+        filetype: &str,
+        depth: usize,
+        expand: &[&str],
+    ) -> Result<Vec<ViewLine>, ScfmtErr> {
+        let document = parse(str, filetype)?;
+        let source_lines: Vec<&str> = str.lines().collect();
+
+        //> find sections to collapse
+            let expanded: Vec<&Section> = expand
+                .iter()
+                .flat_map(|path| document.find_matching(path))
+                .collect();
 
-                    if let Some(line_no_brackets) = line_no_comment_starter.strip_prefix("<>") {
-                        lines_list.push(
-                            add_whitespace(
-                                &(comment_starter.to_owned() + line_no_brackets),
-                                leading_whitespace,
-                                whitespace_char,
-                            ) + "\n",
-                        );
-                    } else if let Some(line_no_brackets) = line_no_comment_starter.strip_prefix('>')
-                    {
-                        lines_list.push(
-                            add_whitespace(
-                                &(comment_starter.to_owned() + line_no_brackets),
-                                leading_whitespace,
-                                whitespace_char,
-                            ) + "\n",
-                        );
-                    } else if line_no_comment_starter.starts_with('<') {
-                        // remove line by not adding it to output
-                        continue;
-                    } else {
-                        lines_list.push(line.to_owned() + "\n");
-                    }
-                } else {
-                    lines_list.push(line.to_owned() + "\n");
-                }
-            } else {
-                lines_list.push("\n".to_owned());
-            }
-        }
+            let collapsed: Vec<&Section> = document
+                .iter()
+                .filter(|section| {
+                    let leads_to_expanded = expanded
+                        .iter()
+                        .any(|x| section.lines().contains(&x.header_line));
 
-        //> turn all lines into one string
-            for line in lines_list {
-                formatted_str.push_str(&line);
-            }
+                    section.depth >= depth && !leads_to_expanded && section.lines().len() > 1
+                })
+                .collect();
         //<
 
-        // remove last '\n'
-        formatted_str.pop();
-
-        //> append any missed empty lines, as the .lines() function may have skipped them.
-            let line_diff = count_lines(str) - processed_line_count;
-
-            if line_diff > 0 {
-                for _ in 0..line_diff {
-                    formatted_str.push('\n');
+        let mut view_lines = Vec::new();
+        let mut i = 0;
+        while i < source_lines.len() {
+            view_lines.push(ViewLine::Line(source_lines[i].to_owned()));
+
+            match collapsed.iter().find(|x| x.header_line == i) {
+                Some(section) => {
+                    let hidden = section.header_line + 1..section.lines().end;
+                    let indentation = source_lines[hidden.clone()]
+                        .iter()
+                        .find_map(|line| {
+                            let (leading_spaces, _) = chop_off_beginning_spaces(line);
+                            leading_spaces.map(|x| line[..x].to_owned())
+                        })
+                        .unwrap_or_default();
+
+                    view_lines.push(ViewLine::Collapsed {
+                        indentation,
+                        lines: hidden.len(),
+                    });
+                    i = hidden.end;
                 }
+                None => i += 1,
             }
-        //<
+        }
 
-        Ok(formatted_str)
+        Ok(view_lines)
     }
 }
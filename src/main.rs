@@ -1,7 +1,13 @@
 use ::scfmt::scfmt::ScfmtErr;
 use colored::Colorize;
 use scfmt::scfmt;
-use std::{env, path::PathBuf};
+use std::{
+    cell::{Cell, RefCell},
+    env, fs, io,
+    ops::Range,
+    path::{Path, PathBuf},
+    process,
+};
 #[macro_use]
 extern crate version;
 
@@ -11,17 +17,84 @@ static HELP_STR: &str =
 USAGE:
     [OPTIONS] [DIRECTORY]
 
+    Markdown files are formatted one fenced code block at a time, using the language of each block
+    Vue, Svelte, HTML and PHP files are formatted one <script>, <style> or <?php ?> region at a time
+    Jupyter notebooks are formatted one code cell at a time, using the language of the notebook's kernel
+
 OPTIONS:
     *None*                      Passing no option simply formats bracketed structured comments
     ab, add_brackets            Gives brackets to any bracketless strucutered comments
     rb, remove_brackets         Removes brackets from any bracketed structured comments
     n,  null                    Invalidates any existing bracketed comments, while preserving their content
-    v,  version                 Print current version info";
+    --lines <START>:<END>       Only formats lines START through END of a file. Lines are numbered from 1
+    --label-closers             Labels every //< with the title of the comment it closes
+    v,  version                 Print current version info
+
+COMMANDS:
+    outline [--format <FORMAT>] <FILE>
+                                Prints a table of contents of a file's structured comments.
+                                FORMAT is one of markdown (default), json or text
+    extract [--with-brackets] <FILE> <PATH>
+                                Prints the body of every section at a path of titles like \"tests/basic tests\",
+                                without its indentation. Titles may be glob patterns
+    lint [--config <FILE>] [DIRECTORY]
+                                Reports problems with structured comments, such as empty sections.
+                                Rules are configured in the [lint] table of FILE, or ./scfmt.toml if it exists
+    lsp                         Runs a language server over stdin and stdout
+    convert --from <MARKERS> --to <MARKERS> [DIRECTORY]
+                                Converts editor fold markers to brackets, or brackets to fold markers.
+                                MARKERS is one of brackets, region, editor-fold or vim
+    repair [DIRECTORY]          Balances unclosed and unopened comments, then formats. Prints every change made
+    view --depth <N> [--expand <PATH>]... <FILE>
+                                Prints a file with every section nested N or more sections deep collapsed.
+                                Sections at each PATH of titles, like \"tests/basic tests\", are left open
+    where <FILE>:<LINE>         Prints the titles of the sections enclosing a line. Lines are numbered from 1
+    grep [--in-section <TITLE>] <PATTERN> <PATHS>...
+                                Prints every section whose title matches the regex PATTERN, with its breadcrumb.
+                                With --in-section, prints lines of code matching PATTERN inside sections
+                                with titles matching the regex TITLE instead
+    wrap <FILE> --lines <START>:<END> [--title <TITLE>]
+                                Puts lines START through END into a new section, indenting them
+    unwrap <FILE> --section <PATH>
+                                Removes the brackets of the section at a path of titles, and dedents its body
+    split <FILE> --at <LINE> [--title <TITLE>]
+                                Splits the section LINE is in into two, starting the second one at LINE
+    merge <FILE> --section <PATH> [--keep-first-title]
+                                Merges the section at a path of titles with the section after it.
+                                Their titles are combined, unless --keep-first-title is given
+    move <FILE> --section <PATH> --before <PATH>
+                                Moves the section at a path of titles to just before the section at another
+    sort <FILE> --section <PATH>
+                                Sorts the sections directly inside the section at a path of titles by title
+    rename --from <TITLE> --to <TITLE> [--ignore-case] [--regex] [--within <PATH>] [--dry-run] <PATHS>...
+                                Renames section titles in files and directories. With --regex, parts of titles
+                                matching FROM are replaced, and TO may use capture groups like $1.
+                                --ignore-case ignores case, with or without --regex.
+                                --within only renames sections at or inside a path of titles.
+                                --dry-run prints every title that would change without changing it
+    tags [--format <FORMAT>] [-o <FILE>] <PATHS>...
+                                Prints a tag file with an entry for every section title, or writes it to FILE.
+                                FORMAT is one of ctags (default) or etags
+    to-markdown [-o <OUTPUT>] <FILE>
+                                Prints a Markdown document with a heading for every section title
+                                and fenced code blocks for the code, or writes it to OUTPUT
+    from-markdown [-o <OUTPUT>] <FILE>
+                                Prints the source file a to-markdown document was made from, exactly
+    doc [-o <DIRECTORY>] <PATHS>...
+                                Writes an HTML page for every file, with sections as collapsible blocks,
+                                and an index.html linking to every section. DIRECTORY defaults to site
+    symbols [--format lsp-json] <FILE>
+                                Prints the folding ranges and document symbols of a file's sections,
+                                as the JSON a language server would send";
 
 fn print_err(err: &str) {
     println!("{}: {}", "error".red().bold(), err);
 }
 
+fn print_warning(warning: &str) {
+    println!("{}: {}", "warning".yellow().bold(), warning);
+}
+
 fn print_if_err(err_result: Result<(), ScfmtErr>, file: PathBuf) {
     if let Err(err) = err_result {
         let file_string = match file.as_os_str().to_str() {
@@ -39,6 +112,70 @@ fn print_if_err(err_result: Result<(), ScfmtErr>, file: PathBuf) {
             ScfmtErr::CommentNeverClosed(line) => print_err(
                 &("comment never closed\n".to_owned() + &file_string + ":" + &format!("{}", line)),
             ),
+            ScfmtErr::ClosingLabelMismatch(line) => print_err(
+                &("closing comment label doesn't match the comment it closes\n".to_owned()
+                    + &file_string
+                    + ":"
+                    + &format!("{}", line)),
+            ),
+            ScfmtErr::SectionNotFound(path) => {
+                print_err(&(format!("no section found at \"{}\"\n", path) + &file_string))
+            }
+            ScfmtErr::LinesCrossSection(line) => print_err(
+                &("lines would cut the section opened here in two\n".to_owned()
+                    + &file_string
+                    + ":"
+                    + &format!("{}", line)),
+            ),
+            ScfmtErr::InvalidLineRange => {
+                print_err(&("lines are outside of the file\n".to_owned() + &file_string))
+            }
+            ScfmtErr::LineNotInSection(line) => print_err(
+                &("line isn't inside a section\n".to_owned()
+                    + &file_string
+                    + ":"
+                    + &format!("{}", line)),
+            ),
+            ScfmtErr::NoNextSection(line) => print_err(
+                &("section has no section after it to merge with\n".to_owned()
+                    + &file_string
+                    + ":"
+                    + &format!("{}", line)),
+            ),
+            ScfmtErr::SectionMovedIntoItself(line) => print_err(
+                &("section can't be moved inside of itself\n".to_owned()
+                    + &file_string
+                    + ":"
+                    + &format!("{}", line)),
+            ),
+            ScfmtErr::InvalidMarkdown(line) => print_err(
+                &("markdown wasn't made by to-markdown\n".to_owned()
+                    + &file_string
+                    + ":"
+                    + &format!("{}", line)),
+            ),
+            ScfmtErr::InvalidNotebook => print_err(
+                &("notebook isn't valid JSON with a list of cells\n".to_owned() + &file_string),
+            ),
+            ScfmtErr::UnsupportedKernel(language) if language.is_empty() => print_err(
+                &("notebook doesn't say what language its kernel is\n".to_owned() + &file_string),
+            ),
+            ScfmtErr::UnsupportedKernel(language) => print_err(
+                &(format!(
+                    "notebook kernel language \"{}\" isn't supported\n",
+                    language
+                ) + &file_string),
+            ),
+            ScfmtErr::IndentationSensitiveKernel(language) => print_err(
+                &(format!(
+                    "notebook kernel language \"{}\" can't be formatted, as its indentation is part of its syntax\n",
+                    language
+                ) + &file_string),
+            ),
+            ScfmtErr::InNotebookCell(cell, err) => print_if_err(
+                Err(*err),
+                PathBuf::from(format!("{} cell {}", file_string, cell)),
+            ),
             ScfmtErr::CantConvertOsString => {
                 print_err(&("Cannot convert OS String to displayable\n".to_owned() + &file_string))
             }
@@ -56,7 +193,41 @@ fn print_if_err(err_result: Result<(), ScfmtErr>, file: PathBuf) {
     }
 }
 
-fn attempt_transform_path(f: fn(PathBuf) -> Result<(), ScfmtErr>, dir: &str) {
+/// Like ``print_if_err``, for commands given a single file, which also report that the file's type isn't supported instead of skipping it
+fn print_if_single_file_err(err_result: Result<(), ScfmtErr>, file: PathBuf) {
+    match err_result {
+        Err(ScfmtErr::IncompatibleFileType) => {
+            print_err(&format!("unsupported file type\n{}", file.display()))
+        }
+        _ => print_if_err(err_result, file),
+    }
+}
+
+/// Wraps a transform of files, noting Markdown code blocks it skips for being in unknown languages
+fn noting_skipped_fences(
+    f: &'static dyn Fn(PathBuf) -> Result<(), ScfmtErr>,
+) -> impl Fn(PathBuf) -> Result<(), ScfmtErr> {
+    move |file: PathBuf| {
+        if let Ok((contents, extension)) = read_source(&file) {
+            if scfmt::markdown::is_markdown(&extension) {
+                for fence in scfmt::markdown::fences(&contents) {
+                    if fence.filetype.is_none() && !fence.language.is_empty() {
+                        print_warning(&format!(
+                            "skipped code block in unknown language \"{}\"\n{}:{}",
+                            fence.language,
+                            file.display(),
+                            fence.line + 1
+                        ));
+                    }
+                }
+            }
+        }
+
+        f(file)
+    }
+}
+
+fn attempt_transform_path(f: &dyn Fn(PathBuf) -> Result<(), ScfmtErr>, dir: &str) {
     let path = PathBuf::from(dir);
 
     if path.is_dir() {
@@ -75,9 +246,720 @@ fn attempt_transform_path(f: fn(PathBuf) -> Result<(), ScfmtErr>, dir: &str) {
     }
 }
 
+/// Parses a one based, inclusive ``START:END`` line range into a zero based ``Range``
+fn parse_line_range(range: &str) -> Option<Range<usize>> {
+    let (start, end) = range.split_once(':')?;
+    let start: usize = start.parse().ok()?;
+    let end: usize = end.parse().ok()?;
+
+    if start == 0 || end < start {
+        return None;
+    }
+
+    Some(start - 1..end)
+}
+
+/// A command, run on the arguments given after its name
+type Command = fn(Vec<String>) -> Result<(), String>;
+
+/// Reads a file, returning its contents and extension
+fn read_source(file: &Path) -> Result<(String, String), ScfmtErr> {
+    let extension = match file.extension() {
+        Some(x) => match x.to_str() {
+            Some(x) => x.to_owned(),
+            None => return Err(ScfmtErr::CantConvertOsString),
+        },
+        None => return Err(ScfmtErr::IncompatibleFileType),
+    };
+
+    match fs::read_to_string(file) {
+        Ok(contents) => Ok((contents, extension)),
+        Err(_) => Err(ScfmtErr::CantReadFileAsString),
+    }
+}
+
+/// Removes ``--name <VALUE>`` from args, returning the value if it was given
+fn take_option(args: &mut Vec<String>, name: &str) -> Result<Option<String>, String> {
+    match args.iter().position(|x| x == name) {
+        Some(i) => {
+            if i + 1 >= args.len() {
+                return Err(format!("Missing value for {}", name));
+            }
+
+            let value = args.remove(i + 1);
+            args.remove(i);
+            Ok(Some(value))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Removes ``--name`` from args, returning true if it was given
+fn take_flag(args: &mut Vec<String>, name: &str) -> bool {
+    match args.iter().position(|x| x == name) {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Returns the only argument left, which should be the file a command was run on
+fn single_file_arg(args: &[String]) -> Result<PathBuf, String> {
+    match args {
+        [file] => Ok(PathBuf::from(file)),
+        [] => Err(
+            "Passed too few arguments. Run \"scfmt help\" for a list of valid options".to_owned(),
+        ),
+        _ => Err("Passed too many arguments.".to_owned()),
+    }
+}
+
+fn outline_command(mut args: Vec<String>) -> Result<(), String> {
+    let format = match take_option(&mut args, "--format")?.as_deref() {
+        None | Some("markdown") | Some("md") => scfmt::OutlineFormat::Markdown,
+        Some("json") => scfmt::OutlineFormat::Json,
+        Some("text") => scfmt::OutlineFormat::Text,
+        Some(x) => return Err(format!("Unknown outline format \"{}\"", x)),
+    };
+    let file = single_file_arg(&args)?;
+
+    match read_source(&file)
+        .and_then(|(contents, extension)| scfmt::outline(&contents, &extension, format))
+    {
+        Ok(outline) => print!("{}", outline),
+        Err(err) => print_if_single_file_err(Err(err), file),
+    }
+
+    Ok(())
+}
+
+/// Returns the fold markers with the given name, or None for brackets
+fn markers_named(name: &str) -> Result<Option<scfmt::Markers>, String> {
+    match name {
+        "brackets" => Ok(None),
+        "region" => Ok(Some(scfmt::Markers::Region)),
+        "editor-fold" => Ok(Some(scfmt::Markers::EditorFold)),
+        "vim" => Ok(Some(scfmt::Markers::Vim)),
+        _ => Err(format!("Unknown markers \"{}\"", name)),
+    }
+}
+
+fn convert_command(mut args: Vec<String>) -> Result<(), String> {
+    let from = take_option(&mut args, "--from")?.ok_or("Missing --from <MARKERS>")?;
+    let to = take_option(&mut args, "--to")?.ok_or("Missing --to <MARKERS>")?;
+    let dir = single_file_arg(&args)?;
+    let dir = dir.to_str().unwrap_or_default();
+
+    match (markers_named(&from)?, markers_named(&to)?) {
+        (Some(markers), None) => {
+            attempt_transform_path(&|file| scfmt::markers_to_brackets_file(file, markers), dir)
+        }
+        (None, Some(markers)) => {
+            attempt_transform_path(&|file| scfmt::brackets_to_markers_file(file, markers), dir)
+        }
+        _ => return Err("Either --from or --to must be brackets".to_owned()),
+    }
+
+    Ok(())
+}
+
+fn symbols_command(mut args: Vec<String>) -> Result<(), String> {
+    match take_option(&mut args, "--format")?.as_deref() {
+        None | Some("lsp-json") => {}
+        Some(x) => return Err(format!("Unknown symbols format \"{}\"", x)),
+    };
+    let file = single_file_arg(&args)?;
+
+    match read_source(&file)
+        .and_then(|(contents, extension)| scfmt::symbols_lsp_json(&contents, &extension))
+    {
+        Ok(symbols) => print!("{}", symbols),
+        Err(err) => print_if_single_file_err(Err(err), file),
+    }
+
+    Ok(())
+}
+
+/// Describes a change ``repair`` made, for a person to review
+fn repair_message(repair: &scfmt::Repair) -> String {
+    match repair {
+        scfmt::Repair::AddedCloser { header_line, .. } => format!(
+            "added a closing comment below this line, for the comment opened on line {}",
+            header_line + 1
+        ),
+        scfmt::Repair::RemovedCloser(_) => {
+            "removed a closing comment that closed nothing".to_owned()
+        }
+        scfmt::Repair::NullifiedCloser(_) => {
+            "nullified a closing comment that closed nothing".to_owned()
+        }
+        scfmt::Repair::OpenedOrphan(_) => {
+            "turned a closing and opening comment that closed nothing into an opening comment"
+                .to_owned()
+        }
+    }
+}
+
+fn repair_command(args: Vec<String>) -> Result<(), String> {
+    let dir = single_file_arg(&args)?;
+    let dir = dir.to_str().unwrap_or_default();
+
+    attempt_transform_path(
+        &|file| {
+            for repair in scfmt::repair_file(file.clone())? {
+                println!(
+                    "{}:{}: {}",
+                    file.display(),
+                    repair.line() + 1,
+                    repair_message(&repair)
+                );
+            }
+            Ok(())
+        },
+        dir,
+    );
+
+    Ok(())
+}
+
+fn extract_command(mut args: Vec<String>) -> Result<(), String> {
+    let with_brackets = take_flag(&mut args, "--with-brackets");
+    let (file, path) = match args.as_slice() {
+        [file, path] => (PathBuf::from(file), path),
+        [] | [_] => {
+            return Err(
+                "Passed too few arguments. Run \"scfmt help\" for a list of valid options"
+                    .to_owned(),
+            )
+        }
+        _ => return Err("Passed too many arguments.".to_owned()),
+    };
+
+    match read_source(&file).and_then(|(contents, extension)| {
+        scfmt::extract(&contents, &extension, path, with_brackets)
+    }) {
+        Ok(extracted) => {
+            if extracted.is_empty() {
+                return Err(format!("No section found at \"{}\"", path));
+            }
+
+            for x in extracted {
+                print!("{}", x.text);
+            }
+        }
+        Err(err) => print_if_single_file_err(Err(err), file),
+    }
+
+    Ok(())
+}
+
+fn view_command(mut args: Vec<String>) -> Result<(), String> {
+    let depth = take_option(&mut args, "--depth")?.ok_or("Missing --depth <N>")?;
+    let depth: usize = match depth.parse() {
+        Ok(x) => x,
+        Err(_) => return Err(format!("Invalid depth \"{}\"", depth)),
+    };
+
+    let mut expand = Vec::new();
+    while let Some(path) = take_option(&mut args, "--expand")? {
+        expand.push(path);
+    }
+    let expand: Vec<&str> = expand.iter().map(|x| x.as_str()).collect();
+
+    let file = single_file_arg(&args)?;
+
+    match read_source(&file)
+        .and_then(|(contents, extension)| scfmt::view(&contents, &extension, depth, &expand))
+    {
+        Ok(lines) => {
+            for line in lines {
+                match line {
+                    scfmt::ViewLine::Line(line) => println!("{}", line),
+                    scfmt::ViewLine::Collapsed { indentation, lines } => {
+                        println!("{}{}", indentation, format!("… {} lines", lines).cyan())
+                    }
+                }
+            }
+        }
+        Err(err) => print_if_single_file_err(Err(err), file),
+    }
+
+    Ok(())
+}
+
+/// Joins the titles of a breadcrumb, from outermost to innermost
+fn breadcrumb_string(titles: &[String]) -> String {
+    let titles: Vec<&str> = titles
+        .iter()
+        .map(|x| {
+            if x.is_empty() {
+                "(untitled)"
+            } else {
+                x.as_str()
+            }
+        })
+        .collect();
+    titles.join(" > ")
+}
+
+fn where_command(args: Vec<String>) -> Result<(), String> {
+    let position = single_file_arg(&args)?;
+    let position = position.to_str().unwrap_or_default();
+
+    let (file, line) = match position.rsplit_once(':') {
+        Some((file, line)) => match line.parse::<usize>() {
+            Ok(line) if line > 0 => (PathBuf::from(file), line - 1),
+            _ => return Err(format!("Invalid line \"{}\"", line)),
+        },
+        None => return Err("Expected <FILE>:<LINE>".to_owned()),
+    };
+
+    match read_source(&file)
+        .and_then(|(contents, extension)| scfmt::breadcrumb(&contents, &extension, line))
+    {
+        Ok(titles) => {
+            if !titles.is_empty() {
+                println!("{}", breadcrumb_string(&titles));
+            }
+        }
+        Err(err) => print_if_single_file_err(Err(err), file),
+    }
+
+    Ok(())
+}
+
+fn grep_command(mut args: Vec<String>) -> Result<(), String> {
+    let in_section = match take_option(&mut args, "--in-section")? {
+        Some(x) => match regex::Regex::new(&x) {
+            Ok(x) => Some(x),
+            Err(err) => return Err(format!("Invalid regex: {}", err)),
+        },
+        None => None,
+    };
+
+    if args.len() < 2 {
+        return Err(
+            "Passed too few arguments. Run \"scfmt help\" for a list of valid options".to_owned(),
+        );
+    }
+    let pattern = match regex::Regex::new(&args.remove(0)) {
+        Ok(x) => x,
+        Err(err) => return Err(format!("Invalid regex: {}", err)),
+    };
+
+    let search = |file: PathBuf| {
+        let (contents, extension) = read_source(&file)?;
+        for hit in scfmt::grep(&contents, &extension, &pattern, in_section.as_ref())? {
+            println!(
+                "{}:{}: {}",
+                file.display(),
+                hit.line + 1,
+                breadcrumb_string(&hit.breadcrumb)
+            );
+        }
+        Ok(())
+    };
+
+    for dir in &args {
+        attempt_transform_path(&search, dir);
+    }
+    Ok(())
+}
+
+fn tags_command(mut args: Vec<String>) -> Result<(), String> {
+    let etags = match take_option(&mut args, "--format")?.as_deref() {
+        None | Some("ctags") => false,
+        Some("etags") => true,
+        Some(x) => return Err(format!("Unknown tags format \"{}\"", x)),
+    };
+    let output = take_option(&mut args, "-o")?;
+
+    if args.is_empty() {
+        return Err(
+            "Passed too few arguments. Run \"scfmt help\" for a list of valid options".to_owned(),
+        );
+    }
+
+    let files = RefCell::new(Vec::new());
+    for dir in &args {
+        attempt_transform_path(
+            &|file| {
+                let (contents, extension) = read_source(&file)?;
+                let tags = scfmt::tags::tags(&contents, &extension)?;
+                if !tags.is_empty() {
+                    files.borrow_mut().push((file.display().to_string(), tags));
+                }
+                Ok(())
+            },
+            dir,
+        );
+    }
+
+    let tag_file = if etags {
+        scfmt::tags::etags(&files.into_inner())
+    } else {
+        scfmt::tags::ctags(&files.into_inner())
+    };
+
+    print_or_write(tag_file, output)
+}
+
+/// Prints a converted file, or writes it to the ``-o`` file
+fn print_or_write(converted: String, output: Option<String>) -> Result<(), String> {
+    match output {
+        Some(output) => match fs::write(&output, converted) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(format!("Cannot write to file {}", output)),
+        },
+        None => {
+            print!("{}", converted);
+            Ok(())
+        }
+    }
+}
+
+fn to_markdown_command(mut args: Vec<String>) -> Result<(), String> {
+    let output = take_option(&mut args, "-o")?;
+    let file = single_file_arg(&args)?;
+
+    match read_source(&file)
+        .and_then(|(contents, extension)| scfmt::markdown::to_markdown(&contents, &extension))
+    {
+        Ok(markdown) => print_or_write(markdown, output),
+        Err(err) => {
+            print_if_single_file_err(Err(err), file);
+            Ok(())
+        }
+    }
+}
+
+fn from_markdown_command(mut args: Vec<String>) -> Result<(), String> {
+    let output = take_option(&mut args, "-o")?;
+    let file = single_file_arg(&args)?;
+
+    let source = match fs::read_to_string(&file) {
+        Ok(markdown) => scfmt::markdown::from_markdown(&markdown),
+        Err(_) => Err(ScfmtErr::CantReadFileAsString),
+    };
+    match source {
+        Ok(source) => print_or_write(source, output),
+        Err(err) => {
+            print_if_single_file_err(Err(err), file);
+            Ok(())
+        }
+    }
+}
+
+fn doc_command(mut args: Vec<String>) -> Result<(), String> {
+    let site = PathBuf::from(take_option(&mut args, "-o")?.unwrap_or("site".to_owned()));
+
+    if args.is_empty() {
+        return Err(
+            "Passed too few arguments. Run \"scfmt help\" for a list of valid options".to_owned(),
+        );
+    }
+
+    let files = RefCell::new(Vec::new());
+    for dir in &args {
+        attempt_transform_path(
+            &|file| {
+                let (contents, extension) = read_source(&file)?;
+
+                // pages mirror the layout of the files, inside the site directory
+                let components: Vec<&str> = file
+                    .components()
+                    .filter_map(|x| match x {
+                        std::path::Component::Normal(x) => x.to_str(),
+                        _ => None,
+                    })
+                    .collect();
+                let href = components.join("/") + ".html";
+                let index_href = "../".repeat(components.len().saturating_sub(1)) + "index.html";
+
+                let name = file.display().to_string();
+                let (page, document) = scfmt::doc::page(&contents, &extension, &name, &index_href)?;
+
+                let page_file = site.join(&href);
+                if let Some(parent) = page_file.parent() {
+                    if fs::create_dir_all(parent).is_err() {
+                        return Err(ScfmtErr::CantCreatFile);
+                    }
+                }
+                if fs::write(page_file, page).is_err() {
+                    return Err(ScfmtErr::CantWriteToFile);
+                }
+
+                files.borrow_mut().push((name, href, document));
+                Ok(())
+            },
+            dir,
+        );
+    }
+
+    if fs::create_dir_all(&site).is_err() {
+        return Err(format!("Cannot create directory {}", site.display()));
+    }
+    let index_file = site.join("index.html");
+    match fs::write(&index_file, scfmt::doc::index(&files.into_inner())) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(format!("Cannot write to file {}", index_file.display())),
+    }
+}
+
+fn wrap_command(mut args: Vec<String>) -> Result<(), String> {
+    let lines = take_option(&mut args, "--lines")?.ok_or("Missing --lines <START>:<END>")?;
+    let lines = parse_line_range(&lines)
+        .ok_or("Invalid line range given. Expected --lines <START>:<END>")?;
+    let title = take_option(&mut args, "--title")?.unwrap_or_default();
+    let file = single_file_arg(&args)?;
+
+    print_if_single_file_err(
+        scfmt::refactor::wrap_lines_file(file.clone(), lines, &title),
+        file,
+    );
+    Ok(())
+}
+
+fn unwrap_command(mut args: Vec<String>) -> Result<(), String> {
+    let path = take_option(&mut args, "--section")?.ok_or("Missing --section <PATH>")?;
+    let file = single_file_arg(&args)?;
+
+    print_if_single_file_err(
+        scfmt::refactor::unwrap_section_file(file.clone(), &path),
+        file,
+    );
+    Ok(())
+}
+
+fn split_command(mut args: Vec<String>) -> Result<(), String> {
+    let at = take_option(&mut args, "--at")?.ok_or("Missing --at <LINE>")?;
+    let at = match at.parse::<usize>() {
+        Ok(x) if x > 0 => x - 1,
+        _ => return Err("Invalid line given. Expected --at <LINE>".to_owned()),
+    };
+    let title = take_option(&mut args, "--title")?.unwrap_or_default();
+    let file = single_file_arg(&args)?;
+
+    print_if_single_file_err(
+        scfmt::refactor::split_section_file(file.clone(), at, &title),
+        file,
+    );
+    Ok(())
+}
+
+fn merge_command(mut args: Vec<String>) -> Result<(), String> {
+    let path = take_option(&mut args, "--section")?.ok_or("Missing --section <PATH>")?;
+    let combine_titles = !take_flag(&mut args, "--keep-first-title");
+    let file = single_file_arg(&args)?;
+
+    print_if_single_file_err(
+        scfmt::refactor::merge_sections_file(file.clone(), &path, combine_titles),
+        file,
+    );
+    Ok(())
+}
+
+fn move_command(mut args: Vec<String>) -> Result<(), String> {
+    let path = take_option(&mut args, "--section")?.ok_or("Missing --section <PATH>")?;
+    let before = take_option(&mut args, "--before")?.ok_or("Missing --before <PATH>")?;
+    let file = single_file_arg(&args)?;
+
+    print_if_single_file_err(
+        scfmt::refactor::move_section_file(file.clone(), &path, &before),
+        file,
+    );
+    Ok(())
+}
+
+fn sort_command(mut args: Vec<String>) -> Result<(), String> {
+    let path = take_option(&mut args, "--section")?.ok_or("Missing --section <PATH>")?;
+    let file = single_file_arg(&args)?;
+
+    print_if_single_file_err(
+        scfmt::refactor::sort_sections_file(file.clone(), &path),
+        file,
+    );
+    Ok(())
+}
+
+fn rename_command(mut args: Vec<String>) -> Result<(), String> {
+    //> read options
+        let from = take_option(&mut args, "--from")?.ok_or("Missing --from <TITLE>")?;
+        let to = take_option(&mut args, "--to")?.ok_or("Missing --to <TITLE>")?;
+        let within = take_option(&mut args, "--within")?;
+        let dry_run = take_flag(&mut args, "--dry-run");
+
+        let regex = take_flag(&mut args, "--regex");
+        let ignore_case = take_flag(&mut args, "--ignore-case");
+
+        let matcher = if regex {
+            match regex::RegexBuilder::new(&from)
+                .case_insensitive(ignore_case)
+                .build()
+            {
+                Ok(x) => scfmt::refactor::TitleMatch::Regex(x),
+                Err(err) => return Err(format!("Invalid regex: {}", err)),
+            }
+        } else if ignore_case {
+            scfmt::refactor::TitleMatch::CaseInsensitive(from)
+        } else {
+            scfmt::refactor::TitleMatch::Exact(from)
+        };
+
+        if args.is_empty() {
+            return Err(
+                "Passed too few arguments. Run \"scfmt help\" for a list of valid options".to_owned(),
+            );
+        }
+    //<
+
+    let rename = |file: PathBuf| {
+        let sites = if dry_run {
+            let (contents, extension) = read_source(&file)?;
+            let within = within.as_deref();
+            scfmt::refactor::rename_titles(&contents, &extension, &matcher, &to, within)?.1
+        } else {
+            scfmt::refactor::rename_titles_file(file.clone(), &matcher, &to, within.as_deref())?
+        };
+
+        for site in sites {
+            println!(
+                "{}:{}: {} -> {}",
+                file.display(),
+                site.line + 1,
+                site.from,
+                site.to
+            );
+        }
+        Ok(())
+    };
+
+    for dir in &args {
+        attempt_transform_path(&rename, dir);
+    }
+    Ok(())
+}
+
+fn lint_command(mut args: Vec<String>) -> Result<(), String> {
+    //> read config
+        let config_file = take_option(&mut args, "--config")?;
+        let config = match config_file {
+            Some(file) => match fs::read_to_string(&file) {
+                Ok(contents) => scfmt::lint::LintConfig::from_toml(&contents)?,
+                Err(_) => return Err(format!("Cannot read config file {}", file)),
+            },
+            None => match fs::read_to_string("scfmt.toml") {
+                Ok(contents) => scfmt::lint::LintConfig::from_toml(&contents)?,
+                Err(_) => scfmt::lint::LintConfig::default(),
+            },
+        };
+    //<
+
+    let dir = single_file_arg(&args)?;
+    let dir = dir.to_str().unwrap_or_default();
+
+    let found_error = Cell::new(false);
+    attempt_transform_path(
+        &|file| {
+            let (contents, extension) = read_source(&file)?;
+            let lints = match scfmt::lint::lint(&contents, &extension, &config) {
+                Ok(x) => x,
+                Err(err) => {
+                    // unbalanced brackets are errors too
+                    if matches!(
+                        err,
+                        ScfmtErr::CommentClosedNothing(_)
+                            | ScfmtErr::CommentNeverClosed(_)
+                            | ScfmtErr::ClosingLabelMismatch(_)
+                    ) {
+                        found_error.set(true);
+                    }
+                    return Err(err);
+                }
+            };
+
+            for lint in lints {
+                let lint_str = format!(
+                    "{} [{}]\n{}:{}",
+                    lint.message,
+                    lint.rule.name(),
+                    file.display(),
+                    lint.line + 1
+                );
+
+                match lint.severity {
+                    scfmt::lint::Severity::Warning => print_warning(&lint_str),
+                    scfmt::lint::Severity::Error => {
+                        found_error.set(true);
+                        print_err(&lint_str);
+                    }
+                }
+            }
+            Ok(())
+        },
+        dir,
+    );
+
+    // let CI fail on errors
+    if found_error.get() {
+        process::exit(1);
+    }
+
+    Ok(())
+}
+
+fn lsp_command(args: Vec<String>) -> Result<(), String> {
+    if !args.is_empty() {
+        return Err("Passed too many arguments.".to_owned());
+    }
+
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    match scfmt::lsp::run(&mut stdin.lock(), &mut stdout.lock()) {
+        Ok(()) => Ok(()),
+        Err(err) => Err(format!("Language server stopped: {}", err)),
+    }
+}
+
 fn main() {
     let args: Vec<String> = env::args().collect();
 
+    //> commands
+        let command: Option<Command> = match args.get(1).map(|x| x.as_str()) {
+            Some("outline") => Some(outline_command),
+            Some("lsp") => Some(lsp_command),
+            Some("symbols") => Some(symbols_command),
+            Some("convert") => Some(convert_command),
+            Some("repair") => Some(repair_command),
+            Some("lint") => Some(lint_command),
+            Some("extract") => Some(extract_command),
+            Some("view") => Some(view_command),
+            Some("where") => Some(where_command),
+            Some("grep") => Some(grep_command),
+            Some("tags") => Some(tags_command),
+            Some("doc") => Some(doc_command),
+            Some("to-markdown") => Some(to_markdown_command),
+            Some("from-markdown") => Some(from_markdown_command),
+            Some("wrap") => Some(wrap_command),
+            Some("unwrap") => Some(unwrap_command),
+            Some("split") => Some(split_command),
+            Some("merge") => Some(merge_command),
+            Some("move") => Some(move_command),
+            Some("sort") => Some(sort_command),
+            Some("rename") => Some(rename_command),
+            _ => None,
+        };
+
+        if let Some(command) = command {
+            if let Err(err) = command(args[2..].to_vec()) {
+                print_err(&err);
+            }
+            return;
+        }
+    //<
+
     if args.len() == 1 {
         print_err("Passed too few arguments. Run \"scfmt help\" for a list of valid options");
     } else if args.len() == 2 {
@@ -88,18 +970,42 @@ fn main() {
         } else if &args[1] == "version" || &args[1] == "v" {
             println!("scfmt {}", version!());
         } else {
-            attempt_transform_path(scfmt::format_file, dir);
+            attempt_transform_path(&noting_skipped_fences(&scfmt::format_file), dir);
         }
     } else if args.len() == 3 {
         let flag = &args[1];
         let dir = &args[2];
 
         if flag == "add_brackets" || flag == "ab" {
-            attempt_transform_path(scfmt::add_brackets_file, dir);
+            attempt_transform_path(&noting_skipped_fences(&scfmt::add_brackets_file), dir);
         } else if flag == "remove_brackets" || flag == "rb" {
-            attempt_transform_path(scfmt::remove_brackets_file, dir);
+            attempt_transform_path(&noting_skipped_fences(&scfmt::remove_brackets_file), dir);
         } else if flag == "null" || flag == "n" {
-            attempt_transform_path(scfmt::null_existing_brackets_file, dir);
+            attempt_transform_path(&scfmt::null_existing_brackets_file, dir);
+        } else if flag == "--label-closers" {
+            attempt_transform_path(&scfmt::label_closers_file, dir);
+        } else {
+            print_err("Invalid option given. Run \"scfmt help\" for a list of valid options");
+        }
+    } else if args.len() == 4 {
+        let flag = &args[1];
+        let value = &args[2];
+        let file = PathBuf::from(&args[3]);
+
+        if flag == "--lines" {
+            match parse_line_range(value) {
+                Some(lines) => {
+                    if file.is_file() {
+                        print_if_single_file_err(
+                            scfmt::format_range_file(file.clone(), lines),
+                            file,
+                        );
+                    } else {
+                        print_err("Invalid path given. --lines can only be used on a file");
+                    }
+                }
+                None => print_err("Invalid line range given. Expected --lines <START>:<END>"),
+            }
         } else {
             print_err("Invalid option given. Run \"scfmt help\" for a list of valid options");
         }
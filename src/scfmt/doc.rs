@@ -0,0 +1,214 @@
+//! Static HTML pages showing the sections of source files, for skimming a project's layout in a browser
+
+use super::{
+    file_is_ignored, parse_leniently, Document, ScfmtErr, Section, EXTENSION_TO_COMMENT_STARTER_MAP,
+};
+use std::ops::Range;
+
+/// Styles and script shared by every page. Pages work offline, so nothing is loaded from anywhere else.
+static HEAD: &str = r#"<style>
+    body { font-family: sans-serif; margin: 2em; color: #222; }
+    nav { margin-bottom: 1em; }
+    pre { margin: 0; font-family: monospace; }
+    a { color: #2255aa; text-decoration: none; }
+    a:hover { text-decoration: underline; }
+    .ln { display: inline-block; width: 4em; color: #999; user-select: none; }
+    details { border-left: 2px solid #ccd; padding-left: 0.5em; margin: 0.2em 0; }
+    summary { cursor: pointer; font-family: monospace; font-weight: bold; }
+    summary .ln { font-weight: normal; }
+    .untitled { color: #999; font-style: italic; }
+    ul { list-style: none; padding-left: 1.5em; }
+</style>
+<script>
+    // open every section enclosing the line the URL points to
+    function openTarget() {
+        var target = document.getElementById(location.hash.slice(1));
+        for (var x = target; x; x = x.parentElement) {
+            if (x.tagName === "DETAILS") {
+                x.open = true;
+            }
+        }
+    }
+    window.addEventListener("hashchange", openTarget);
+    window.addEventListener("DOMContentLoaded", openTarget);
+</script>"#;
+
+/// Escapes text so it can be put in HTML, including in attribute values
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for char in text.chars() {
+        match char {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(char),
+        }
+    }
+    escaped
+}
+
+fn html(title: &str, body: &str) -> String {
+    format!(
+        concat!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n",
+            "<title>{}</title>\n{}\n</head>\n<body>\n{}</body>\n</html>\n",
+        ),
+        escape(title),
+        HEAD,
+        body
+    )
+}
+
+/// Returns a section's title for showing in a page, marking sections without one
+fn title_html(section: &Section) -> String {
+    if section.title.is_empty() {
+        "<span class=\"untitled\">(untitled)</span>".to_owned()
+    } else {
+        escape(&section.title)
+    }
+}
+
+/// Renders lines of code, with the sections among them as collapsible blocks
+fn render_lines(source_lines: &[&str], lines: Range<usize>, sections: &[Section]) -> String {
+    let mut rendered = String::new();
+
+    let render_code = |rendered: &mut String, code: Range<usize>| {
+        if code.is_empty() {
+            return;
+        }
+
+        rendered.push_str("<pre>");
+        for i in code {
+            rendered.push_str(&format!(
+                "<span id=\"L{0}\"><a class=\"ln\" href=\"#L{0}\">{0}</a>{1}</span>\n",
+                i + 1,
+                escape(source_lines[i])
+            ));
+        }
+        rendered.push_str("</pre>\n");
+    };
+
+    let mut i = lines.start;
+    for section in sections {
+        render_code(&mut rendered, i..section.header_line);
+
+        // the bracketed comments themselves are shown as the block's summary and its end
+        rendered.push_str(&format!(
+            "<details id=\"L{0}\">\n<summary><a class=\"ln\" href=\"#L{0}\">{0}</a>{1}</summary>\n",
+            section.header_line + 1,
+            title_html(section)
+        ));
+        rendered.push_str(&render_lines(
+            source_lines,
+            section.body.clone(),
+            &section.children,
+        ));
+        rendered.push_str("</details>\n");
+
+        i = section.lines().end;
+    }
+    render_code(&mut rendered, i..lines.end);
+
+    rendered
+}
+
+/// Renders a source file as an HTML page, returning the page along with the sections in it
+///
+/// Sections are shown as collapsible ``<details>`` blocks titled with their titles, and code is shown as escaped text. Unbalanced brackets are skipped, so files being edited can still be rendered.
+///
+/// # Arguments
+///
+/// * `name` - Name of the file, shown at the top of the page
+/// * `index_href` - Link to the page listing every file, relative to this page
+///
+/// # Examples
+///
+/// ```
+/// use scfmt::scfmt::doc::page;
+///
+/// let (html, document) = page("//> setup\n    let a = 1 < 2;\n//<", "rs", "lib.rs", "index.html").unwrap();
+///
+/// assert!(html.contains("<summary><a class=\"ln\" href=\"#L1\">1</a>setup</summary>"));
+/// assert!(html.contains("    let a = 1 &lt; 2;"));
+/// assert_eq!(document.sections[0].title, "setup");
+/// ```
+pub fn page(
+    str: &str,
+    filetype: &str,
+    name: &str,
+    index_href: &str,
+) -> Result<(String, Document), ScfmtErr> {
+    // determine if file compatible
+    let comment_starter = match EXTENSION_TO_COMMENT_STARTER_MAP.get(filetype) {
+        Some(x) => *x,
+        None => return Err(ScfmtErr::IncompatibleFileType),
+    };
+
+    // ignored files are shown without sections
+    let document = if file_is_ignored(str, comment_starter) {
+        Document::default()
+    } else {
+        parse_leniently(str, comment_starter).0
+    };
+
+    let source_lines: Vec<&str> = str.lines().collect();
+    let body = format!(
+        "<nav><a href=\"{}\">Index</a></nav>\n<h1>{}</h1>\n{}",
+        escape(index_href),
+        escape(name),
+        render_lines(&source_lines, 0..source_lines.len(), &document.sections)
+    );
+
+    Ok((html(name, &body), document))
+}
+
+fn index_entries(sections: &[Section], href: &str) -> String {
+    if sections.is_empty() {
+        return String::new();
+    }
+
+    let mut entries = "<ul>\n".to_owned();
+    for section in sections {
+        entries.push_str(&format!(
+            "<li><a href=\"{}#L{}\">{}</a>\n{}</li>\n",
+            escape(href),
+            section.header_line + 1,
+            title_html(section),
+            index_entries(&section.children, href)
+        ));
+    }
+    entries.push_str("</ul>\n");
+
+    entries
+}
+
+/// Renders an HTML page linking to every file and every section in them
+///
+/// Each file is given as its name, a link to its page, and the sections returned by ``page`` for it.
+///
+/// # Examples
+///
+/// ```
+/// use scfmt::scfmt::doc::{index, page};
+///
+/// let (_, document) = page("//> setup\n//<", "rs", "lib.rs", "index.html").unwrap();
+/// let html = index(&[("lib.rs".to_owned(), "lib.rs.html".to_owned(), document)]);
+///
+/// assert!(html.contains("<a href=\"lib.rs.html#L1\">setup</a>"));
+/// ```
+pub fn index(files: &[(String, String, Document)]) -> String {
+    let mut body = "<h1>Index</h1>\n<ul>\n".to_owned();
+    for (name, href, document) in files {
+        body.push_str(&format!(
+            "<li><a href=\"{}\">{}</a>\n{}</li>\n",
+            escape(href),
+            escape(name),
+            index_entries(&document.sections, href)
+        ));
+    }
+    body.push_str("</ul>\n");
+
+    html("Index", &body)
+}
@@ -0,0 +1,374 @@
+//! Structured comments inside documentation comments, like the examples in ``///`` and ``/** */`` comments of Rust files
+//!
+//! A doc comment is read as the Markdown it holds, with its ``///``, ``//!`` or `` * `` prefixes stripped. The code blocks in it are then formatted like the code blocks of a Markdown file, and the prefixes are put back.
+//!
+//! Only code blocks are formatted, and a code block with brackets that don't balance is left as it is. Structured comments written in the prose of a doc comment are documentation, and are left alone.
+
+use super::markdown::{offset_err, transform_fences_as, Fence};
+use super::{
+    format_str, join_formatted_lines, lines_turned_off, ScfmtErr, EXTENSION_TO_COMMENT_STARTER_MAP,
+};
+use std::ops::Range;
+
+/// Code block attributes that rustdoc reads as Rust code
+static RUSTDOC_ATTRIBUTES: [&str; 5] = [
+    "ignore",
+    "no_run",
+    "should_panic",
+    "compile_fail",
+    "test_harness",
+];
+
+/// A line of a doc comment, split into the part marking it as documentation and the Markdown after it
+struct DocLine {
+    /// Indentation and marker, like ``    ///`` or `` *``
+    prefix: String,
+    /// Space between the prefix and the text
+    separator: String,
+    text: String,
+}
+
+/// A run of lines holding one doc comment
+struct DocBlock {
+    /// Zero based indexes of the lines holding Markdown
+    lines: Range<usize>,
+    doc_lines: Vec<DocLine>,
+}
+
+fn split_line(line: &str, prefix_len: usize) -> DocLine {
+    let rest = &line[prefix_len..];
+    let separator_len = if rest.starts_with(' ') { 1 } else { 0 };
+
+    DocLine {
+        prefix: line[..prefix_len].to_owned(),
+        separator: rest[..separator_len].to_owned(),
+        text: rest[separator_len..].to_owned(),
+    }
+}
+
+/// Returns the marker a line doc comment starts with, if it is one
+fn line_doc_marker(line: &str) -> Option<&'static str> {
+    let trimmed = line.trim_start();
+    if trimmed.starts_with("///") && !trimmed.starts_with("////") {
+        Some("///")
+    } else if trimmed.starts_with("//!") {
+        Some("//!")
+    } else {
+        None
+    }
+}
+
+/// Returns whether a line opens a block doc comment that continues on the lines after it
+fn opens_block_doc(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    (trimmed.starts_with("/**") && !trimmed.starts_with("/**/") || trimmed.starts_with("/*!"))
+        && !trimmed[3..].contains("*/")
+}
+
+/// Returns every doc comment in lines of code
+fn doc_blocks(lines: &[&str]) -> Vec<DocBlock> {
+    let mut blocks = Vec::new();
+
+    let mut i = 0;
+    while i < lines.len() {
+        //> line doc comments, made of consecutive lines with the same marker
+            if let Some(marker) = line_doc_marker(lines[i]) {
+                let start = i;
+                let mut doc_lines = Vec::new();
+                while i < lines.len() && line_doc_marker(lines[i]) == Some(marker) {
+                    let indentation = lines[i].len() - lines[i].trim_start().len();
+                    doc_lines.push(split_line(lines[i], indentation + marker.len()));
+                    i += 1;
+                }
+
+                blocks.push(DocBlock {
+                    lines: start..i,
+                    doc_lines,
+                });
+                continue;
+            }
+        //<
+
+        //> block doc comments, holding the lines between the opening and closing line
+            if opens_block_doc(lines[i]) {
+                let start = i + 1;
+                let mut end = start;
+                while end < lines.len() && !lines[end].contains("*/") {
+                    end += 1;
+                }
+
+                // brackets in lines without a leading '*' are already formatted as code
+                if lines[start..end]
+                    .iter()
+                    .any(|line| !line.trim().is_empty() && !line.trim_start().starts_with('*'))
+                {
+                    i = end + 1;
+                    continue;
+                }
+
+                let doc_lines = lines[start..end]
+                    .iter()
+                    .map(|line| {
+                        let indentation = line.len() - line.trim_start().len();
+                        split_line(line, (indentation + 1).min(line.len()))
+                    })
+                    .collect();
+
+                blocks.push(DocBlock {
+                    lines: start..end,
+                    doc_lines,
+                });
+                i = end + 1;
+                continue;
+            }
+        //<
+
+        i += 1;
+    }
+
+    blocks
+}
+
+/// Returns the filetype of the code in a doc comment's code block
+///
+/// Code blocks without a language, or with only rustdoc attributes, hold Rust code like rustdoc assumes.
+fn filetype_of(fence: &Fence) -> Option<String> {
+    if fence.language.is_empty()
+        || RUSTDOC_ATTRIBUTES.contains(&fence.language.as_str())
+        || fence.language.starts_with("edition")
+    {
+        return Some("rs".to_owned());
+    }
+
+    fence.filetype.map(str::to_owned)
+}
+
+/// Returns whether each line of code starts inside a string that was opened on a line before it
+///
+/// Strings are ``"`` strings with ``\`` escapes, and raw strings like ``r#"..."#``. Quotes in character literals like ``'"'`` and in line comments don't open strings.
+fn lines_in_strings(code: &str, comment_starter: &str) -> Vec<bool> {
+    let starts_with = |chars: &[char], text: &str| {
+        text.chars()
+            .eq(chars.iter().take(text.chars().count()).copied())
+    };
+    let is_identifier = |x: char| x.is_alphanumeric() || x == '_';
+
+    // what closes the string the current line is in
+    let mut closing: Option<String> = None;
+
+    code.lines()
+        .map(|line| {
+            let starts_in_string = closing.is_some();
+            let chars: Vec<char> = line.chars().collect();
+
+            let mut i = 0;
+            while i < chars.len() {
+                let rest = &chars[i..];
+                match &closing {
+                    Some(x) if x == "\"" && rest[0] == '\\' => i += 1,
+                    Some(x) if starts_with(rest, x) => {
+                        i += x.len() - 1;
+                        closing = None;
+                    }
+                    Some(_) => {}
+                    None if starts_with(rest, comment_starter) => break,
+                    None if rest[0] == '"' => closing = Some("\"".to_owned()),
+                    //> raw strings, which end at a quote followed by as many #s as they started with
+                        None if rest[0] == 'r'
+                            && (i == 0
+                                || !is_identifier(chars[i - 1])
+                                || chars[i - 1] == 'b' && (i == 1 || !is_identifier(chars[i - 2]))) =>
+                        {
+                            let hashes = rest[1..].iter().take_while(|x| **x == '#').count();
+                            if rest.get(hashes + 1) == Some(&'"') {
+                                closing = Some("\"".to_owned() + &"#".repeat(hashes));
+                                i += hashes + 1;
+                            }
+                        }
+                    //<
+                    //> character literals, told apart from lifetimes by their closing quote
+                        None if rest[0] == '\'' => {
+                            if rest.get(1) == Some(&'\\') {
+                                i += rest[2..]
+                                    .iter()
+                                    .position(|x| *x == '\'')
+                                    .map_or(0, |x| x + 2);
+                            } else if rest.get(2) == Some(&'\'') {
+                                i += 2;
+                            }
+                        }
+                    //<
+                    None => {}
+                }
+                i += 1;
+            }
+
+            starts_in_string
+        })
+        .collect()
+}
+
+/// Formats the code in a doc comment's code block, leaving lines inside multi-line strings as they are
+///
+/// Examples often pass code holding structured comments to a function as a string, and those lines are text rather than code. They're turned off while formatting, by surrounding them with ``scfmt: off`` and ``scfmt: on`` comments that are removed after.
+fn format_code_block(code: &str, filetype: &str) -> Result<String, ScfmtErr> {
+    let comment_starter = match EXTENSION_TO_COMMENT_STARTER_MAP.get(filetype) {
+        Some(x) => *x,
+        None => return format_str(code, filetype),
+    };
+
+    let turned_off = lines_turned_off(code, comment_starter);
+    let in_string: Vec<bool> = lines_in_strings(code, comment_starter)
+        .into_iter()
+        .zip(turned_off)
+        .map(|(in_string, turned_off)| in_string && !turned_off)
+        .collect();
+    if !in_string.contains(&true) {
+        return format_str(code, filetype);
+    }
+
+    //> turn off lines in strings
+        let mut lines: Vec<String> = Vec::new();
+        // zero based indexes of the added comments in `lines`
+        let mut added: Vec<usize> = Vec::new();
+        for (i, line) in code.lines().enumerate() {
+            if in_string[i] && (i == 0 || !in_string[i - 1]) {
+                added.push(lines.len());
+                lines.push(format!("{} scfmt: off", comment_starter));
+            }
+            lines.push(line.to_owned());
+            if in_string[i] && !in_string.get(i + 1).copied().unwrap_or(false) {
+                added.push(lines.len());
+                lines.push(format!("{} scfmt: on", comment_starter));
+            }
+        }
+    //<
+
+    let formatted = format_str(&(lines.join("\n") + "\n"), filetype)?;
+
+    Ok(join_formatted_lines(
+        formatted
+            .lines()
+            .enumerate()
+            .filter(|(i, _)| !added.contains(i))
+            .map(|(_, line)| line.to_owned() + "\n")
+            .collect(),
+        code,
+    ))
+}
+
+/// Puts the prefixes back on the transformed Markdown of a doc comment
+///
+/// Lines the transform didn't change are kept exactly as they were, including any trailing whitespace.
+fn restore_prefixes(block: &DocBlock, source_lines: &[&str], transformed: &str) -> Vec<String> {
+    let separator = block
+        .doc_lines
+        .iter()
+        .find(|x| !x.text.is_empty())
+        .map(|x| x.separator.clone())
+        .unwrap_or_else(|| " ".to_owned());
+    let join = |prefix: &str, separator: &str, text: &str| {
+        if text.is_empty() {
+            prefix.to_owned()
+        } else {
+            format!("{}{}{}", prefix, separator, text)
+        }
+    };
+
+    let transformed_lines: Vec<&str> = transformed.lines().collect();
+
+    // a transform that added or removed lines can't be lined up with the original prefixes
+    if transformed_lines.len() != block.doc_lines.len() {
+        let first = &block.doc_lines[0];
+        return transformed_lines
+            .iter()
+            .map(|text| join(&first.prefix, &separator, text))
+            .collect();
+    }
+
+    transformed_lines
+        .iter()
+        .zip(&block.doc_lines)
+        .zip(&source_lines[block.lines.clone()])
+        .map(|((text, doc_line), source_line)| {
+            if *text == doc_line.text {
+                source_line.to_string()
+            } else if doc_line.separator.is_empty() {
+                join(&doc_line.prefix, &separator, text)
+            } else {
+                join(&doc_line.prefix, &doc_line.separator, text)
+            }
+        })
+        .collect()
+}
+
+/// Formats the structured comments in the code blocks of every doc comment, leaving the rest of the code alone
+///
+/// Only Rust files are changed, and doc comments in a ``scfmt: off`` region aren't. Code blocks that can't be formatted, like an example of unbalanced brackets, are left as they are.
+///
+/// # Examples
+///
+/// ```
+/// use scfmt::scfmt::doc_comments::format_doc_comments;
+///
+/// let to_format = "/// ```\n/// //> a\n/// let a = 0;\n/// //<\n/// ```\nfn a() {}";
+/// let formatted = format_doc_comments(to_format, "rs").unwrap();
+///
+/// assert_eq!(
+///     formatted,
+///     "/// ```\n/// //> a\n///     let a = 0;\n/// //<\n/// ```\nfn a() {}"
+/// );
+/// ```
+pub fn format_doc_comments(str: &str, filetype: &str) -> Result<String, ScfmtErr> {
+    // determine if file compatible
+    let comment_starter = match EXTENSION_TO_COMMENT_STARTER_MAP.get(filetype) {
+        Some(x) => *x,
+        None => return Err(ScfmtErr::IncompatibleFileType),
+    };
+
+    // only Rust has doc comments like these
+    if filetype != "rs" {
+        return Ok(str.to_owned());
+    }
+
+    let source_lines: Vec<&str> = str.lines().collect();
+    let turned_off = lines_turned_off(str, comment_starter);
+
+    let mut formatted: Vec<String> = Vec::new();
+    let mut i = 0;
+    for block in doc_blocks(&source_lines) {
+        if block.doc_lines.is_empty() || turned_off[block.lines.clone()].contains(&true) {
+            continue;
+        }
+
+        let mut markdown = String::new();
+        for doc_line in &block.doc_lines {
+            markdown.push_str(&doc_line.text);
+            markdown.push('\n');
+        }
+
+        let transformed = transform_fences_as(
+            &markdown,
+            &|fence| filetype_of(fence),
+            // an example that doesn't parse is left for the reader, rather than failing the file
+            &|code, filetype, _| {
+                Ok(format_code_block(code, filetype).unwrap_or_else(|_| code.to_owned()))
+            },
+        )
+        .map_err(|x| offset_err(x, block.lines.start))?;
+
+        formatted.extend(
+            source_lines[i..block.lines.start]
+                .iter()
+                .map(|x| x.to_string()),
+        );
+        formatted.extend(restore_prefixes(&block, &source_lines, &transformed));
+        i = block.lines.end;
+    }
+    formatted.extend(source_lines[i..].iter().map(|x| x.to_string()));
+
+    Ok(join_formatted_lines(
+        formatted.into_iter().map(|x| x + "\n").collect(),
+        str,
+    ))
+}
@@ -0,0 +1,342 @@
+//! Checks structured comments for problems that don't stop them from being formatted, such as empty sections
+
+use super::{
+    chop_off_beginning_spaces, file_is_ignored, parse_with_comment_starter, pragma_of_line,
+    text_after_bracket, Bracket, Pragma, ScfmtErr, Section, EXTENSION_TO_COMMENT_STARTER_MAP,
+};
+use std::collections::HashMap;
+
+/// How serious a lint is
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// The problems ``lint`` can report
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub enum Rule {
+    /// A section with nothing in it
+    EmptySection,
+    /// A section without title text
+    NoTitle,
+    /// A section nested deeper than ``max-depth``
+    TooDeep,
+    /// A section with more than ``max-lines`` lines between its brackets
+    TooLong,
+    /// A section with the same title as a section before it, that is nested in the same section
+    DuplicateTitle,
+    /// A section with only one line in it
+    SingleLineSection,
+    /// A closing bracket indented differently from the bracket that opened its section
+    CloserIndentation,
+    /// A title that isn't separated from its bracket by a space, like ``//>title``
+    MissingSpace,
+}
+
+/// Every rule, in the order they are documented
+pub static RULES: [Rule; 8] = [
+    Rule::EmptySection,
+    Rule::NoTitle,
+    Rule::TooDeep,
+    Rule::TooLong,
+    Rule::DuplicateTitle,
+    Rule::SingleLineSection,
+    Rule::CloserIndentation,
+    Rule::MissingSpace,
+];
+
+impl Rule {
+    /// Name of the rule, as used in config files and ``scfmt: allow()`` comments
+    pub fn name(&self) -> &'static str {
+        match self {
+            Rule::EmptySection => "empty-section",
+            Rule::NoTitle => "no-title",
+            Rule::TooDeep => "too-deep",
+            Rule::TooLong => "too-long",
+            Rule::DuplicateTitle => "duplicate-title",
+            Rule::SingleLineSection => "single-line-section",
+            Rule::CloserIndentation => "closer-indentation",
+            Rule::MissingSpace => "missing-space",
+        }
+    }
+
+    /// Returns the rule with the given name
+    pub fn named(name: &str) -> Option<Rule> {
+        RULES.iter().find(|x| x.name() == name).copied()
+    }
+}
+
+/// A problem found by ``lint``
+#[derive(PartialEq, Debug, Clone)]
+pub struct Lint {
+    pub rule: Rule,
+    pub severity: Severity,
+    /// Zero based index of the line the problem is on
+    pub line: usize,
+    pub message: String,
+}
+
+/// Which rules ``lint`` checks, and how serious they are
+#[derive(PartialEq, Debug, Clone)]
+pub struct LintConfig {
+    /// Severity of every enabled rule. Rules that aren't in the map are turned off.
+    pub rules: HashMap<Rule, Severity>,
+    /// Deepest a section may be nested for ``too-deep``. Sections that aren't in another section have a depth of 1.
+    pub max_depth: usize,
+    /// Most lines a section may have between its brackets for ``too-long``
+    pub max_lines: usize,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        let rules = [
+            (Rule::EmptySection, Severity::Warning),
+            (Rule::NoTitle, Severity::Warning),
+            (Rule::TooDeep, Severity::Warning),
+            (Rule::DuplicateTitle, Severity::Warning),
+            (Rule::CloserIndentation, Severity::Warning),
+        ];
+
+        LintConfig {
+            rules: rules.into_iter().collect(),
+            max_depth: 4,
+            max_lines: 200,
+        }
+    }
+}
+
+impl LintConfig {
+    /// Reads the ``[lint]`` table of a ``scfmt.toml`` file. Anything it doesn't set keeps its default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use scfmt::scfmt::lint::{LintConfig, Rule, Severity};
+    ///
+    /// let config = LintConfig::from_toml("[lint]\nempty-section = \"error\"\nno-title = \"off\"\nmax-depth = 2").unwrap();
+    ///
+    /// assert_eq!(config.rules.get(&Rule::EmptySection), Some(&Severity::Error));
+    /// assert_eq!(config.rules.get(&Rule::NoTitle), None);
+    /// assert_eq!(config.max_depth, 2);
+    /// ```
+    pub fn from_toml(str: &str) -> Result<LintConfig, String> {
+        let mut config = LintConfig::default();
+
+        let table: toml::Table = match str.parse() {
+            Ok(x) => x,
+            Err(err) => return Err(format!("Invalid config: {}", err)),
+        };
+        let lint = match table.get("lint") {
+            Some(toml::Value::Table(x)) => x,
+            Some(_) => return Err("Invalid config: lint must be a table".to_owned()),
+            None => return Ok(config),
+        };
+
+        for (key, value) in lint {
+            match key.as_str() {
+                "max-depth" | "max-lines" => {
+                    let limit = match value.as_integer() {
+                        Some(x) if x >= 0 => x as usize,
+                        _ => return Err(format!("Invalid config: {} must be a number", key)),
+                    };
+
+                    if key == "max-depth" {
+                        config.max_depth = limit;
+                    } else {
+                        config.max_lines = limit;
+                    }
+                }
+                _ => {
+                    let rule = match Rule::named(key) {
+                        Some(x) => x,
+                        None => {
+                            return Err(format!("Invalid config: unknown lint rule \"{}\"", key))
+                        }
+                    };
+
+                    match value.as_str() {
+                        Some("off") => {
+                            config.rules.remove(&rule);
+                        }
+                        Some("warning") => {
+                            config.rules.insert(rule, Severity::Warning);
+                        }
+                        Some("error") => {
+                            config.rules.insert(rule, Severity::Error);
+                        }
+                        _ => {
+                            return Err(format!(
+                                "Invalid config: {} must be \"off\", \"warning\" or \"error\"",
+                                key
+                            ))
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+/// Checks the structured comments of a string, returning every problem found in the order they appear
+///
+/// Unbalanced brackets are returned as an error, like ``format_str`` does. A ``scfmt: allow(rule, ...)`` comment stops the listed rules from being reported on the line after it.
+///
+/// # Examples
+///
+/// ```
+/// use scfmt::scfmt::lint::{lint, LintConfig, Rule};
+///
+/// let lints = lint("//> setup\n//<", "rs", &LintConfig::default()).unwrap();
+///
+/// assert_eq!(lints.len(), 1);
+/// assert_eq!(lints[0].rule, Rule::EmptySection);
+/// assert_eq!(lints[0].line, 0);
+/// ```
+pub fn lint(str: &str, filetype: &str, config: &LintConfig) -> Result<Vec<Lint>, ScfmtErr> {
+    // determine if file compatible
+    let comment_starter = match EXTENSION_TO_COMMENT_STARTER_MAP.get(filetype) {
+        Some(x) => *x,
+        None => return Err(ScfmtErr::IncompatibleFileType),
+    };
+
+    // leave ignored files alone
+    if file_is_ignored(str, comment_starter) {
+        return Ok(Vec::new());
+    }
+
+    let document = parse_with_comment_starter(str, comment_starter)?;
+    let source_lines: Vec<&str> = str.lines().collect();
+
+    let mut lints = Vec::new();
+    let mut report = |rule: Rule, line: usize, message: String| {
+        if let Some(severity) = config.rules.get(&rule) {
+            lints.push(Lint {
+                rule,
+                severity: *severity,
+                line,
+                message,
+            });
+        }
+    };
+
+    //> check each section
+        for section in document.iter() {
+            let body_lines = source_lines[section.body.clone()]
+                .iter()
+                .filter(|x| !x.trim().is_empty())
+                .count();
+
+            if body_lines == 0 {
+                report(
+                    Rule::EmptySection,
+                    section.header_line,
+                    "empty section".to_owned(),
+                );
+            } else if body_lines == 1 {
+                report(
+                    Rule::SingleLineSection,
+                    section.header_line,
+                    "section has only one line".to_owned(),
+                );
+            }
+
+            if section.title.is_empty() {
+                report(
+                    Rule::NoTitle,
+                    section.header_line,
+                    "section has no title".to_owned(),
+                );
+            } else if !text_after_bracket(source_lines[section.header_line], comment_starter)
+                .starts_with(char::is_whitespace)
+            {
+                report(
+                    Rule::MissingSpace,
+                    section.header_line,
+                    "missing space between bracket and title".to_owned(),
+                );
+            }
+
+            if section.depth + 1 > config.max_depth {
+                report(
+                    Rule::TooDeep,
+                    section.header_line,
+                    format!(
+                        "section is nested {} deep, more than the maximum of {}",
+                        section.depth + 1,
+                        config.max_depth
+                    ),
+                );
+            }
+
+            if section.body.len() > config.max_lines {
+                report(
+                    Rule::TooLong,
+                    section.header_line,
+                    format!(
+                        "section is {} lines long, more than the maximum of {}",
+                        section.body.len(),
+                        config.max_lines
+                    ),
+                );
+            }
+
+            let closer_indentation = chop_off_beginning_spaces(source_lines[section.close_line]).0;
+            if closer_indentation != Some(section.indentation) {
+                let closer = match section.closed_by {
+                    Bracket::CloseAndOpen => "closing and opening comment",
+                    _ => "closing comment",
+                };
+                report(
+                    Rule::CloserIndentation,
+                    section.close_line,
+                    format!(
+                        "{} is indented differently from its opening comment",
+                        closer
+                    ),
+                );
+            }
+        }
+
+    //<> check sibling titles
+        fn push_duplicate_titles(sections: &[Section], duplicates: &mut Vec<usize>) {
+            for (i, section) in sections.iter().enumerate() {
+                let seen_before = sections[..i].iter().any(|x| x.title == section.title);
+                if !section.title.is_empty() && seen_before {
+                    duplicates.push(section.header_line);
+                }
+
+                push_duplicate_titles(&section.children, duplicates);
+            }
+        }
+
+        let mut duplicates = Vec::new();
+        push_duplicate_titles(&document.sections, &mut duplicates);
+        for line in duplicates {
+            report(
+                Rule::DuplicateTitle,
+                line,
+                "section has the same title as a section before it".to_owned(),
+            );
+        }
+    //<
+
+    //> remove lints allowed by the line before them
+        lints.retain(|lint| {
+            let allowed = match lint.line.checked_sub(1) {
+                Some(i) => pragma_of_line(source_lines[i], comment_starter),
+                None => None,
+            };
+
+            match allowed {
+                Some(Pragma::Allow(rules)) => !rules.iter().any(|x| x == lint.rule.name()),
+                _ => true,
+            }
+        });
+    //<
+
+    lints.sort_by_key(|x| x.line);
+    Ok(lints)
+}
@@ -0,0 +1,323 @@
+//! A language server, so any editor that speaks the Language Server Protocol can format structured comments
+
+use super::{
+    add_brackets, bracket_errors, document_symbols, document_symbols_lsp_json, folding_ranges,
+    folding_ranges_lsp_json, format_range, format_str, lsp_range, null_existing_brackets,
+    remove_brackets, ScfmtErr,
+};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+/// A function that transforms a string of the given filetype
+type Transform = fn(&str, &str) -> Result<String, ScfmtErr>;
+
+/// Code actions the server offers, and the functions that perform them
+static CODE_ACTIONS: [(&str, Transform); 3] = [
+    ("Add brackets", add_brackets),
+    ("Remove brackets", remove_brackets),
+    ("Nullify brackets", null_existing_brackets),
+];
+
+/// Runs a language server, reading JSON-RPC messages from `reader` and writing messages to `writer`.
+///
+/// Returns when the client sends an ``exit`` notification, or `reader` has no more messages.
+pub fn run(reader: &mut impl BufRead, writer: &mut impl Write) -> io::Result<()> {
+    let mut server = Server {
+        documents: HashMap::new(),
+    };
+
+    while let Some(message) = read_message(reader)? {
+        let message = match message {
+            Ok(x) => x,
+            Err(_) => {
+                write_message(writer, &error_response(Value::Null, -32700, "Parse error"))?;
+                continue;
+            }
+        };
+
+        if message["method"] == "exit" {
+            break;
+        }
+
+        for outgoing in server.handle(&message) {
+            write_message(writer, &outgoing)?;
+        }
+    }
+
+    Ok(())
+}
+
+//> JSON-RPC framing
+    /// Reads one message. Returns ``None`` once there are no more messages to read.
+    fn read_message(reader: &mut impl BufRead) -> io::Result<Option<serde_json::Result<Value>>> {
+        //> read headers
+            let mut content_length = None;
+            loop {
+                let mut header = String::new();
+                if reader.read_line(&mut header)? == 0 {
+                    return Ok(None);
+                }
+
+                let header = header.trim_end();
+                if header.is_empty() {
+                    break;
+                }
+
+                if let Some(length) = header.strip_prefix("Content-Length:") {
+                    content_length = length.trim().parse::<usize>().ok();
+                }
+            }
+
+        //<> read content
+            let content_length = match content_length {
+                Some(x) => x,
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "message has no Content-Length header",
+                    ))
+                }
+            };
+
+            let mut content = vec![0; content_length];
+            reader.read_exact(&mut content)?;
+        //<
+
+        Ok(Some(serde_json::from_slice(&content)))
+    }
+
+    fn write_message(writer: &mut impl Write, message: &Value) -> io::Result<()> {
+        let content = message.to_string();
+        write!(
+            writer,
+            "Content-Length: {}\r\n\r\n{}",
+            content.len(),
+            content
+        )?;
+        writer.flush()
+    }
+
+    fn response(id: Value, result: Value) -> Value {
+        json!({ "jsonrpc": "2.0", "id": id, "result": result })
+    }
+
+    fn error_response(id: Value, code: i64, message: &str) -> Value {
+        json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+    }
+
+    fn notification(method: &str, params: Value) -> Value {
+        json!({ "jsonrpc": "2.0", "method": method, "params": params })
+    }
+//<
+
+//> positions
+    /// Returns the file extension of a document's URI
+    fn filetype_of(uri: &str) -> &str {
+        let file_name = uri.rsplit('/').next().unwrap_or(uri);
+        match file_name.rsplit_once('.') {
+            Some((_, extension)) => extension,
+            None => "",
+        }
+    }
+
+    /// Returns edits that replace all of `text` with `new_text`
+    fn replace_all(text: &str, new_text: &str) -> Value {
+        if text == new_text {
+            return json!([]);
+        }
+
+        let last_line = text.split('\n').next_back().unwrap_or("");
+        json!([{
+            "range": {
+                "start": { "line": 0, "character": 0 },
+                "end": {
+                    "line": text.matches('\n').count(),
+                    "character": last_line.encode_utf16().count(),
+                },
+            },
+            "newText": new_text,
+        }])
+    }
+//<
+
+struct Server {
+    /// Text of every open document, by URI
+    documents: HashMap<String, String>,
+}
+
+impl Server {
+    /// Handles one message from the client, returning the messages to send back
+    fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let method = message["method"].as_str().unwrap_or("");
+        let params = &message["params"];
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or("");
+
+        //> notifications
+            match method {
+                "textDocument/didOpen" => {
+                    let text = params["textDocument"]["text"].as_str().unwrap_or("");
+                    self.documents.insert(uri.to_owned(), text.to_owned());
+                    return vec![self.diagnostics(uri)];
+                }
+                "textDocument/didChange" => {
+                    // the server only asks for full document syncing, so the last change holds the whole text
+                    if let Some(text) = params["contentChanges"]
+                        .as_array()
+                        .and_then(|x| x.last())
+                        .and_then(|x| x["text"].as_str())
+                    {
+                        self.documents.insert(uri.to_owned(), text.to_owned());
+                    }
+                    return vec![self.diagnostics(uri)];
+                }
+                "textDocument/didClose" => {
+                    self.documents.remove(uri);
+                    return vec![notification(
+                        "textDocument/publishDiagnostics",
+                        json!({ "uri": uri, "diagnostics": [] }),
+                    )];
+                }
+                _ => {}
+            }
+
+            // any other message without an id is a notification we don't handle
+            let id = match message.get("id") {
+                Some(x) => x.clone(),
+                None => return Vec::new(),
+            };
+        //<
+
+        //> requests
+            let result = match method {
+                "initialize" => json!({
+                    "capabilities": {
+                        "textDocumentSync": 1,
+                        "documentFormattingProvider": true,
+                        "documentRangeFormattingProvider": true,
+                        "codeActionProvider": true,
+                        "foldingRangeProvider": true,
+                        "documentSymbolProvider": true,
+                    },
+                    "serverInfo": { "name": "scfmt", "version": env!("CARGO_PKG_VERSION") },
+                }),
+                "shutdown" => Value::Null,
+                "textDocument/formatting" => self.formatting(uri, None),
+                "textDocument/rangeFormatting" => {
+                    let start = params["range"]["start"]["line"].as_u64().unwrap_or(0) as usize;
+                    let end_line = params["range"]["end"]["line"].as_u64().unwrap_or(0) as usize;
+                    let end_character = params["range"]["end"]["character"].as_u64().unwrap_or(0);
+
+                    // a range ending at the start of a line doesn't include that line
+                    let end = if end_character == 0 && end_line > start {
+                        end_line
+                    } else {
+                        end_line + 1
+                    };
+
+                    self.formatting(uri, Some(start..end))
+                }
+                "textDocument/codeAction" => self.code_actions(uri),
+                "textDocument/foldingRange" => self.folding_ranges(uri),
+                "textDocument/documentSymbol" => self.document_symbols(uri),
+                _ => return vec![error_response(id, -32601, "Method not found")],
+            };
+        //<
+
+        vec![response(id, result)]
+    }
+
+    /// Returns edits that format a document, or only the given lines of it
+    fn formatting(&self, uri: &str, lines: Option<std::ops::Range<usize>>) -> Value {
+        let text = match self.documents.get(uri) {
+            Some(x) => x,
+            None => return Value::Null,
+        };
+
+        let formatted = match lines {
+            Some(lines) => format_range(text, filetype_of(uri), lines),
+            None => format_str(text, filetype_of(uri)),
+        };
+
+        // unbalanced brackets are already shown as diagnostics, so leave the document alone
+        match formatted {
+            Ok(formatted) => replace_all(text, &formatted),
+            Err(_) => json!([]),
+        }
+    }
+
+    fn code_actions(&self, uri: &str) -> Value {
+        let text = match self.documents.get(uri) {
+            Some(x) => x,
+            None => return json!([]),
+        };
+
+        let mut actions = Vec::new();
+        for (title, transform) in CODE_ACTIONS.iter() {
+            if let Ok(transformed) = transform(text, filetype_of(uri)) {
+                if &transformed != text {
+                    actions.push(json!({
+                        "title": title,
+                        "kind": "refactor.rewrite",
+                        "edit": { "changes": { uri: replace_all(text, &transformed) } },
+                    }));
+                }
+            }
+        }
+
+        Value::Array(actions)
+    }
+
+    fn folding_ranges(&self, uri: &str) -> Value {
+        let text = self.documents.get(uri).map(|x| x.as_str()).unwrap_or("");
+
+        match folding_ranges(text, filetype_of(uri)) {
+            Ok(x) => folding_ranges_lsp_json(&x),
+            Err(_) => json!([]),
+        }
+    }
+
+    fn document_symbols(&self, uri: &str) -> Value {
+        let text = self.documents.get(uri).map(|x| x.as_str()).unwrap_or("");
+        let source_lines: Vec<&str> = text.lines().collect();
+
+        match document_symbols(text, filetype_of(uri)) {
+            Ok(x) => document_symbols_lsp_json(&x, &source_lines),
+            Err(_) => json!([]),
+        }
+    }
+
+    /// Returns a notification holding a diagnostic for every unbalanced bracket in a document
+    fn diagnostics(&self, uri: &str) -> Value {
+        let text = self.documents.get(uri).map(|x| x.as_str()).unwrap_or("");
+        let source_lines: Vec<&str> = text.lines().collect();
+
+        let errors = bracket_errors(text, filetype_of(uri)).unwrap_or_default();
+        let diagnostics: Vec<Value> = errors
+            .iter()
+            .filter_map(|err| {
+                let (line, message) = match err {
+                    ScfmtErr::CommentClosedNothing(line) => (line, "comment closed nothing"),
+                    ScfmtErr::CommentNeverClosed(line) => (line, "comment never closed"),
+                    ScfmtErr::ClosingLabelMismatch(line) => (
+                        line,
+                        "closing comment label doesn't match the comment it closes",
+                    ),
+                    _ => return None,
+                };
+
+                Some(json!({
+                    "range": lsp_range(&source_lines, line - 1..*line),
+                    "severity": 1,
+                    "source": "scfmt",
+                    "message": message,
+                }))
+            })
+            .collect();
+
+        notification(
+            "textDocument/publishDiagnostics",
+            json!({ "uri": uri, "diagnostics": diagnostics }),
+        )
+    }
+}
@@ -0,0 +1,449 @@
+//! Markdown support: formatting the code blocks of Markdown files, and converting source files to literate Markdown documents and back
+//!
+//! In literate documents, section titles become headings, and the code between brackets becomes fenced code blocks. Everything else needed to rebuild the source exactly, like the indentation of brackets and the closing comments, is kept in ``<!-- scfmt: ... -->`` comments, which don't show up when the Markdown is rendered.
+
+use super::{
+    file_is_ignored, join_formatted_lines, parse_leniently, text_after_bracket, Bracket, Document,
+    ScfmtErr, EXTENSION_TO_COMMENT_STARTER_MAP,
+};
+use phf::phf_map;
+use serde_json::Value;
+use std::ops::Range;
+
+/// Extensions of Markdown files
+pub static EXTENSIONS: [&str; 2] = ["md", "markdown"];
+
+/// Extensions of languages whose code block info strings aren't already an extension scfmt knows
+static LANGUAGE_TO_EXTENSION: phf::Map<&'static str, &'static str> = phf_map! {
+    "bash" => "sh",
+    "c++" => "cpp",
+    "c#" => "cs",
+    "clojure" => "clj",
+    "csharp" => "cs",
+    "elixir" => "ex",
+    "gdscript" => "gd",
+    "golang" => "go",
+    "gql" => "graphql",
+    "haskell" => "hs",
+    "javascript" => "js",
+    "julia" => "jl",
+    "jsx" => "js",
+    "objc" => "m",
+    "objective-c" => "m",
+    "pascal" => "pas",
+    "powershell" => "ps1",
+    "racket" => "rkt",
+    "rust" => "rs",
+    "shell" => "sh",
+    "solidity" => "sol",
+    "stylus" => "styl",
+    "typescript" => "ts",
+    "yml" => "yaml",
+    "zsh" => "sh",
+};
+
+/// Returns whether files with the given extension are Markdown
+pub fn is_markdown(filetype: &str) -> bool {
+    EXTENSIONS.contains(&filetype)
+}
+
+//> code blocks
+    /// A fenced code block in a Markdown document
+    #[derive(PartialEq, Debug, Clone)]
+    pub struct Fence {
+        /// Zero based index of the line opening the code block
+        pub line: usize,
+        /// First word of the code block's info string, like ``rust``
+        pub language: String,
+        /// Extension of files in the code block's language, or None if scfmt doesn't know the language
+        pub filetype: Option<&'static str>,
+        /// Zero based range of the lines inside the code block
+        pub body: Range<usize>,
+        /// Leading whitespace of the line opening the code block, which lines inside it are indented by too
+        pub indentation: String,
+    }
+
+    pub(super) fn filetype_of_language(language: &str) -> Option<&'static str> {
+        match EXTENSION_TO_COMMENT_STARTER_MAP.get_entry(language) {
+            Some((extension, _)) => Some(extension),
+            None => LANGUAGE_TO_EXTENSION.get(language).copied(),
+        }
+    }
+
+    /// Returns every fenced code block in a Markdown document, in the order they appear
+    ///
+    /// The language of a code block is the first word of its info string, so ``rust,ignore`` and ``{.rust}`` are both Rust. A code block that is never closed runs to the end of the document.
+    pub fn fences(str: &str) -> Vec<Fence> {
+        let lines: Vec<&str> = str.lines().collect();
+        let mut fences = Vec::new();
+
+        let mut i = 0;
+        while i < lines.len() {
+            let trimmed = lines[i].trim_start();
+            let fence_char = match trimmed.chars().next() {
+                Some(x) if x == '`' || x == '~' => x,
+                _ => {
+                    i += 1;
+                    continue;
+                }
+            };
+            let fence_len = trimmed.len() - trimmed.trim_start_matches(fence_char).len();
+            let info = trimmed[fence_len..].trim();
+
+            // an info string can't contain backticks, so this is inline code instead
+            if fence_len < 3 || (fence_char == '`' && info.contains('`')) {
+                i += 1;
+                continue;
+            }
+
+            let end = (i + 1..lines.len())
+                .find(|&x| {
+                    let closer = lines[x].trim();
+                    closer.len() >= fence_len && closer.chars().all(|x| x == fence_char)
+                })
+                .unwrap_or(lines.len());
+
+            let language = info
+                .split(|x: char| x.is_whitespace() || x == ',')
+                .next()
+                .unwrap_or("")
+                .trim_start_matches(['{', '.'])
+                .trim_end_matches('}')
+                .to_lowercase();
+
+            fences.push(Fence {
+                line: i,
+                filetype: filetype_of_language(&language),
+                language,
+                body: i + 1..end,
+                indentation: lines[i][..lines[i].len() - trimmed.len()].to_owned(),
+            });
+            i = end + 1;
+        }
+
+        fences
+    }
+
+    /// Returns whether a line is an ``<!-- scfmt: off -->`` comment, or an ``<!-- scfmt: on -->`` one
+    fn switch_of_line(line: &str) -> Option<bool> {
+        let switch = line
+            .trim()
+            .strip_prefix("<!--")?
+            .strip_suffix("-->")?
+            .trim()
+            .strip_prefix("scfmt:")?
+            .trim();
+
+        match switch {
+            "off" => Some(false),
+            "on" => Some(true),
+            _ => None,
+        }
+    }
+
+    /// Returns whether each line is between an ``<!-- scfmt: off -->`` comment and an ``<!-- scfmt: on -->`` comment
+    ///
+    /// Comments inside code blocks are code, so they don't turn anything off.
+    fn lines_turned_off(str: &str, fences: &[Fence]) -> Vec<bool> {
+        let mut in_fence = vec![false; str.lines().count()];
+        for fence in fences {
+            in_fence[fence.body.clone()].fill(true);
+        }
+
+        let mut turned_off = false;
+        str.lines()
+            .zip(in_fence)
+            .map(|(line, in_fence)| {
+                match switch_of_line(line) {
+                    Some(on) if !in_fence => turned_off = !on,
+                    _ => {}
+                }
+                turned_off
+            })
+            .collect()
+    }
+
+    /// Moves the line an error is on by `offset` lines
+    pub(super) fn offset_err(err: ScfmtErr, offset: usize) -> ScfmtErr {
+        match err {
+            ScfmtErr::CommentClosedNothing(x) => ScfmtErr::CommentClosedNothing(x + offset),
+            ScfmtErr::CommentNeverClosed(x) => ScfmtErr::CommentNeverClosed(x + offset),
+            ScfmtErr::ClosingLabelMismatch(x) => ScfmtErr::ClosingLabelMismatch(x + offset),
+            _ => err,
+        }
+    }
+
+    /// Runs a transform on the code in every fenced code block of a Markdown document whose language scfmt knows, leaving everything else alone
+    ///
+    /// Code blocks between an ``<!-- scfmt: off -->`` comment and an ``<!-- scfmt: on -->`` comment are left alone too.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use scfmt::scfmt::format_str;
+    /// use scfmt::scfmt::markdown::transform_fences;
+    ///
+    /// let markdown = "Some text\n```rust\n//> a\nlet a = 0;\n//<\n```\n";
+    /// let formatted = transform_fences(markdown, &format_str).unwrap();
+    ///
+    /// assert_eq!(formatted, "Some text\n```rust\n//> a\n    let a = 0;\n//<\n```\n");
+    /// ```
+    pub fn transform_fences(
+        str: &str,
+        transform: &dyn Fn(&str, &str) -> Result<String, ScfmtErr>,
+    ) -> Result<String, ScfmtErr> {
+        transform_fences_as(
+            str,
+            &|fence| fence.filetype.map(str::to_owned),
+            &|code, filetype, _| transform(code, filetype),
+        )
+    }
+
+    /// Like ``transform_fences``, but with the filetype of each code block decided by `filetype_of`
+    ///
+    /// `transform` is also given the zero based index of the line each code block starts on.
+    pub(super) fn transform_fences_as(
+        str: &str,
+        filetype_of: &dyn Fn(&Fence) -> Option<String>,
+        transform: &dyn Fn(&str, &str, usize) -> Result<String, ScfmtErr>,
+    ) -> Result<String, ScfmtErr> {
+        let lines: Vec<&str> = str.lines().collect();
+        let mut transformed: Vec<String> = Vec::new();
+
+        let fences = fences(str);
+        let turned_off = lines_turned_off(str, &fences);
+
+        let mut i = 0;
+        for fence in fences {
+            let filetype = match filetype_of(&fence) {
+                Some(x) if !turned_off[fence.line] => x,
+                _ => continue,
+            };
+
+            transformed.extend(lines[i..fence.body.start].iter().map(|x| x.to_string()));
+
+            //> transform the code, without the indentation of the code block
+                let mut code = String::new();
+                for line in &lines[fence.body.clone()] {
+                    let removable = line.len() - line.trim_start().len();
+                    code.push_str(&line[removable.min(fence.indentation.len())..]);
+                    code.push('\n');
+                }
+
+                let code = transform(&code, &filetype, fence.body.start)
+                    .map_err(|x| offset_err(x, fence.body.start))?;
+
+                transformed.extend(code.lines().map(|line| {
+                    if line.is_empty() {
+                        line.to_owned()
+                    } else {
+                        fence.indentation.clone() + line
+                    }
+                }));
+            //<
+
+            i = fence.body.end;
+        }
+        transformed.extend(lines[i..].iter().map(|x| x.to_string()));
+
+        Ok(join_formatted_lines(
+            transformed.into_iter().map(|x| x + "\n").collect(),
+            str,
+        ))
+    }
+//<
+
+/// Marks a source that didn't end with a '\n'
+static NO_FINAL_NEWLINE: &str = "no-final-newline";
+
+/// Returns an ``<!-- scfmt: ... -->`` comment holding a JSON value
+fn scfmt_comment(value: Value) -> String {
+    // escaped, so the comment can't be ended early by a "-->" in a string
+    let json = value.to_string().replace('>', "\\u003e");
+    format!("<!-- scfmt: {} -->", json)
+}
+
+/// Returns the value of an ``<!-- scfmt: ... -->`` comment, or None if the line isn't one
+fn parse_scfmt_comment(line: &str) -> Option<serde_json::Result<Value>> {
+    let json = line.strip_prefix("<!-- scfmt: ")?.strip_suffix(" -->")?;
+    Some(serde_json::from_str(json))
+}
+
+/// Returns a fence longer than any run of backticks in the lines it fences
+fn fence_for(lines: &[&str]) -> String {
+    let longest_run = lines
+        .iter()
+        .flat_map(|line| line.split(|x| x != '`'))
+        .map(|run| run.len())
+        .max()
+        .unwrap_or(0);
+
+    "`".repeat((longest_run + 1).max(3))
+}
+
+/// Turns a source file into a Markdown document, with a heading for every section and fenced code blocks for the code in them
+///
+/// Headings are nested as deep as their sections. ``from_markdown`` rebuilds the source from the document exactly. Unbalanced brackets are kept as code.
+///
+/// # Examples
+///
+/// ```
+/// use scfmt::scfmt::markdown::{from_markdown, to_markdown};
+///
+/// let source = "//> setup\n    let a = 0;\n//<\n";
+/// let markdown = to_markdown(source, "rs").unwrap();
+///
+/// assert!(markdown.contains("# setup\n"));
+/// assert!(markdown.contains("```rs\n    let a = 0;\n```\n"));
+/// assert_eq!(from_markdown(&markdown).unwrap(), source);
+/// ```
+pub fn to_markdown(str: &str, filetype: &str) -> Result<String, ScfmtErr> {
+    // determine if file compatible
+    let comment_starter = match EXTENSION_TO_COMMENT_STARTER_MAP.get(filetype) {
+        Some(x) => *x,
+        None => return Err(ScfmtErr::IncompatibleFileType),
+    };
+
+    // ignored files are all code
+    let document = if file_is_ignored(str, comment_starter) {
+        Document::default()
+    } else {
+        parse_leniently(str, comment_starter).0
+    };
+
+    //> split the source into lines, keeping any '\r'
+        let mut lines: Vec<&str> = str.split('\n').collect();
+        let final_newline = lines.last() == Some(&"");
+        if final_newline {
+            lines.pop();
+        }
+    //<
+
+    let mut markdown = String::new();
+    let mut code: Vec<&str> = Vec::new();
+    let flush_code = |markdown: &mut String, code: &mut Vec<&str>| {
+        if code.is_empty() {
+            return;
+        }
+
+        let fence = fence_for(code);
+        markdown.push_str(&format!("{}{}\n", fence, filetype));
+        for line in code.drain(..) {
+            markdown.push_str(line);
+            markdown.push('\n');
+        }
+        markdown.push_str(&format!("{}\n\n", fence));
+    };
+
+    for (i, line) in lines.iter().enumerate() {
+        if let Some(section) = document.iter().find(|x| x.header_line == i) {
+            flush_code(&mut markdown, &mut code);
+
+            //> heading, with the text around the title kept in a comment
+                let after = text_after_bracket(line, comment_starter);
+                let start = line.len() - after.trim_start().len();
+                let (prefix, suffix) = if section.title.is_empty() {
+                    (*line, "")
+                } else {
+                    (&line[..start], &line[start + section.title.len()..])
+                };
+
+                let level = "#".repeat(section.depth + 1);
+                markdown.push_str(&level);
+                if !section.title.is_empty() {
+                    markdown.push(' ');
+                    markdown.push_str(&section.title);
+                }
+                markdown.push('\n');
+                markdown.push_str(&scfmt_comment(Value::from(vec![prefix, suffix])));
+                markdown.push_str("\n\n");
+            //<
+        } else if document
+            .iter()
+            .any(|x| x.close_line == i && x.closed_by == Bracket::Close)
+        {
+            flush_code(&mut markdown, &mut code);
+            markdown.push_str(&scfmt_comment(Value::from(*line)));
+            markdown.push_str("\n\n");
+        } else {
+            code.push(line);
+        }
+    }
+    flush_code(&mut markdown, &mut code);
+
+    if !final_newline {
+        markdown.push_str(&scfmt_comment(Value::from(NO_FINAL_NEWLINE)));
+        markdown.push('\n');
+    }
+
+    Ok(markdown)
+}
+
+/// Rebuilds a source file from a Markdown document made by ``to_markdown``
+///
+/// Titles are taken from the headings, so sections can be renamed by editing the document. Text that isn't in a heading, a fenced code block or an ``<!-- scfmt: ... -->`` comment is left out.
+pub fn from_markdown(str: &str) -> Result<String, ScfmtErr> {
+    // split on '\n' only, so code keeps any '\r'
+    let mut markdown_lines: Vec<&str> = str.split('\n').collect();
+    if markdown_lines.last() == Some(&"") {
+        markdown_lines.pop();
+    }
+    let mut lines: Vec<String> = Vec::new();
+    let mut final_newline = true;
+
+    let mut i = 0;
+    while i < markdown_lines.len() {
+        let line = markdown_lines[i];
+        let fence_len = line.len() - line.trim_start_matches('`').len();
+
+        if fence_len >= 3 {
+            //> fenced code block
+                let fence = &line[..fence_len];
+                let end = (i + 1..markdown_lines.len())
+                    .find(|&x| markdown_lines[x] == fence)
+                    .ok_or(ScfmtErr::InvalidMarkdown(i + 1))?;
+
+                lines.extend(markdown_lines[i + 1..end].iter().map(|x| x.to_string()));
+                i = end;
+            //<
+        } else if line.starts_with('#') {
+            //> heading, followed by the text around its title
+                let title = line.trim_start_matches('#');
+                let title = title.strip_prefix(' ').unwrap_or(title);
+
+                let around = match markdown_lines
+                    .get(i + 1)
+                    .and_then(|x| parse_scfmt_comment(x))
+                {
+                    Some(Ok(x)) => x,
+                    Some(Err(_)) => return Err(ScfmtErr::InvalidMarkdown(i + 2)),
+                    // a heading without one is only documentation
+                    None => Value::Null,
+                };
+
+                if let Some([Value::String(prefix), Value::String(suffix)]) =
+                    around.as_array().map(|x| x.as_slice())
+                {
+                    lines.push(prefix.to_owned() + title + suffix);
+                    i += 1;
+                }
+            //<
+        } else if switch_of_line(line).is_some() {
+            // only formatting reads these, so they're documentation here
+        } else if let Some(comment) = parse_scfmt_comment(line) {
+            match comment {
+                Ok(Value::String(x)) if x == NO_FINAL_NEWLINE => final_newline = false,
+                Ok(Value::String(x)) => lines.push(x),
+                _ => return Err(ScfmtErr::InvalidMarkdown(i + 1)),
+            }
+        }
+
+        i += 1;
+    }
+
+    let mut source = lines.join("\n");
+    if final_newline && !lines.is_empty() {
+        source.push('\n');
+    }
+    Ok(source)
+}
@@ -0,0 +1,279 @@
+//! Jupyter notebooks, whose code cells are processed as code in the notebook's kernel language
+//!
+//! Notebooks are edited in place: only the sources of code cells that changed are rewritten, so key order, outputs and the notebook's JSON formatting are all kept.
+
+use super::markdown::filetype_of_language;
+use super::ScfmtErr;
+use phf::phf_map;
+use serde_json::Value;
+use std::ops::Range;
+
+/// A function that transforms code of the given filetype
+type Transform<'a> = &'a dyn Fn(&str, &str) -> Result<String, ScfmtErr>;
+
+/// Extensions of Jupyter notebooks
+pub static EXTENSIONS: [&str; 1] = ["ipynb"];
+
+/// Kernel languages whose indentation is part of their syntax, with the extension of a language commented the same way
+///
+/// Their cells can't be formatted, but transforms that leave indentation alone process them as code in the other language.
+static INDENTATION_SENSITIVE_KERNELS: phf::Map<&'static str, &'static str> = phf_map! {
+    "python" => "sh",
+};
+
+/// Returns whether files with the given extension are Jupyter notebooks
+pub fn is_notebook(filetype: &str) -> bool {
+    EXTENSIONS.contains(&filetype)
+}
+
+//> finding values in JSON text
+    fn skip_whitespace(text: &str, mut i: usize) -> usize {
+        let bytes = text.as_bytes();
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        i
+    }
+
+    /// Returns the end of the JSON value starting at `start`
+    fn value_end(text: &str, start: usize) -> Option<usize> {
+        let bytes = text.as_bytes();
+        let mut depth = 0;
+        let mut in_string = false;
+
+        let mut i = start;
+        while i < bytes.len() {
+            match (in_string, bytes[i]) {
+                (true, b'\\') => i += 1,
+                (true, b'"') => {
+                    in_string = false;
+                    if depth == 0 {
+                        return Some(i + 1);
+                    }
+                }
+                (true, _) => {}
+                (false, b',' | b'}' | b']' | b' ' | b'\t' | b'\r' | b'\n') if depth == 0 => {
+                    return Some(i)
+                }
+                (false, b'"') => in_string = true,
+                (false, b'{' | b'[') => depth += 1,
+                (false, b'}' | b']') => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(i + 1);
+                    }
+                }
+                (false, _) => {}
+            }
+            i += 1;
+        }
+
+        (depth == 0 && !in_string).then_some(i)
+    }
+
+    /// Returns the ranges of the values in the JSON array or object at `range`, along with the keys of an object's values
+    fn children(text: &str, range: Range<usize>) -> Option<Vec<(Option<String>, Range<usize>)>> {
+        let is_object = text[range.clone()].starts_with('{');
+        let mut children = Vec::new();
+
+        let mut i = skip_whitespace(text, range.start + 1);
+        while i < range.end - 1 {
+            let mut key = None;
+            if is_object {
+                let key_end = value_end(text, i)?;
+                key = Some(serde_json::from_str(&text[i..key_end]).ok()?);
+                i = skip_whitespace(text, key_end);
+                if !text[i..].starts_with(':') {
+                    return None;
+                }
+                i = skip_whitespace(text, i + 1);
+            }
+
+            let end = value_end(text, i)?;
+            children.push((key, i..end));
+
+            i = skip_whitespace(text, end);
+            if text[i..].starts_with(',') {
+                i = skip_whitespace(text, i + 1);
+            }
+        }
+
+        Some(children)
+    }
+
+    /// Returns the range of the value of an object's key
+    fn member(text: &str, object: Range<usize>, name: &str) -> Option<Range<usize>> {
+        children(text, object)?
+            .into_iter()
+            .find(|(key, _)| key.as_deref() == Some(name))
+            .map(|(_, range)| range)
+    }
+//<
+
+/// Returns the range of the ``source`` value of every code cell, along with the cell's zero based index among all cells
+fn code_cell_sources(str: &str) -> Option<Vec<(usize, Range<usize>)>> {
+    let start = skip_whitespace(str, 0);
+    let root = start..value_end(str, start)?;
+    let cells = member(str, root, "cells")?;
+
+    let mut sources = Vec::new();
+    for (i, (_, cell)) in children(str, cells)?.into_iter().enumerate() {
+        let cell_type = member(str, cell.clone(), "cell_type")?;
+        if &str[cell_type] == "\"code\"" {
+            sources.push((i, member(str, cell, "source")?));
+        }
+    }
+
+    Some(sources)
+}
+
+/// Returns the JSON for a cell's new source, in the same shape as its old one
+///
+/// Sources split into a list of lines stay that way, keeping the whitespace between lines and the text of lines that didn't change.
+fn source_json(str: &str, old_source: Range<usize>, old_code: &str, code: &str) -> String {
+    let old_lines = match children(str, old_source.clone()) {
+        Some(x) if str[old_source.clone()].starts_with('[') && !x.is_empty() => x,
+        _ => return Value::from(code).to_string(),
+    };
+
+    let opening = &str[old_source.start + 1..old_lines[0].1.start];
+    let closing = &str[old_lines[old_lines.len() - 1].1.end..old_source.end - 1];
+    let separator = match old_lines.get(1) {
+        Some((_, second)) => str[old_lines[0].1.end..second.start].to_owned(),
+        None => ",".to_owned() + opening,
+    };
+    let old_code_lines: Vec<&str> = old_code.split_inclusive('\n').collect();
+
+    let lines: Vec<String> = code
+        .split_inclusive('\n')
+        .enumerate()
+        .map(|(i, line)| match old_code_lines.get(i) {
+            Some(old_line) if *old_line == line => str[old_lines[i].1.clone()].to_owned(),
+            _ => Value::from(line).to_string(),
+        })
+        .collect();
+
+    format!("[{}{}{}]", opening, lines.join(&separator), closing)
+}
+
+/// Returns the extension of the language of a notebook's kernel, and whether its indentation is part of its syntax
+///
+/// Kernels whose indentation is part of their syntax are reported as ``IndentationSensitiveKernel``, unless `keeps_indentation` is true. Kernels in languages scfmt doesn't know, or without a language, are reported as ``UnsupportedKernel``.
+fn kernel_filetype(
+    notebook: &Value,
+    keeps_indentation: bool,
+) -> Result<(&'static str, bool), ScfmtErr> {
+    let metadata = &notebook["metadata"];
+    let language = metadata["language_info"]["name"]
+        .as_str()
+        .or_else(|| metadata["kernelspec"]["language"].as_str())
+        .unwrap_or_default();
+
+    let lowercase = language.to_ascii_lowercase();
+    if let Some(x) = filetype_of_language(&lowercase) {
+        return Ok((x, false));
+    }
+
+    match INDENTATION_SENSITIVE_KERNELS.get(&lowercase) {
+        Some(x) if keeps_indentation => Ok((*x, true)),
+        Some(_) => Err(ScfmtErr::IndentationSensitiveKernel(language.to_owned())),
+        None => Err(ScfmtErr::UnsupportedKernel(language.to_owned())),
+    }
+}
+
+/// Runs a transform on the source of every code cell in a Jupyter notebook, as code in the notebook's kernel language
+///
+/// Each cell is transformed on its own, so a structured comment can't span cells. Notebooks whose kernel language scfmt doesn't know are returned as ``UnsupportedKernel``, and ones whose kernel language is sensitive to indentation, like Python, as ``IndentationSensitiveKernel``. Errors in a cell are returned as ``InNotebookCell``, holding the one based index of the cell among all cells, and the error with lines counted from the start of the cell. Cells starting with a cell magic like ``%%bash`` are in another language, and are left alone.
+///
+/// # Examples
+///
+/// ```
+/// use scfmt::scfmt::format_str;
+/// use scfmt::scfmt::notebook::transform_cells;
+///
+/// let notebook = concat!(
+///     r##"{"cells": [{"cell_type": "code", "source": ["#> a\n", "a <- 0\n", "#<"]}], "##,
+///     r##""metadata": {"kernelspec": {"language": "R"}}}"##,
+/// );
+/// let formatted = transform_cells(notebook, &format_str).unwrap();
+///
+/// assert!(formatted.contains(r##""source": ["#> a\n", "    a <- 0\n", "#<"]"##));
+/// ```
+pub fn transform_cells(
+    str: &str,
+    transform: &dyn Fn(&str, &str) -> Result<String, ScfmtErr>,
+) -> Result<String, ScfmtErr> {
+    transform_cells_as(str, transform, None)
+}
+
+/// Like ``transform_cells``, also running `unindented_transform` on the cells of notebooks whose kernel language is sensitive to indentation, like Python
+///
+/// `unindented_transform` must leave the indentation of every line alone. It gets the cells as code in a language commented the same way, like ``sh`` for Python.
+///
+/// # Examples
+///
+/// ```
+/// use scfmt::scfmt::add_brackets;
+/// use scfmt::scfmt::notebook::transform_cells_with;
+///
+/// let notebook = concat!(
+///     r##"{"cells": [{"cell_type": "code", "source": "# a\n    a = 0"}], "##,
+///     r##""metadata": {"kernelspec": {"language": "python"}}}"##,
+/// );
+/// let added = transform_cells_with(notebook, &add_brackets, &add_brackets).unwrap();
+///
+/// assert!(added.contains(r##""source": "#> a\n    a = 0\n#<""##));
+/// ```
+pub fn transform_cells_with(
+    str: &str,
+    transform: &dyn Fn(&str, &str) -> Result<String, ScfmtErr>,
+    unindented_transform: &dyn Fn(&str, &str) -> Result<String, ScfmtErr>,
+) -> Result<String, ScfmtErr> {
+    transform_cells_as(str, transform, Some(unindented_transform))
+}
+
+fn transform_cells_as(
+    str: &str,
+    transform: Transform,
+    unindented_transform: Option<Transform>,
+) -> Result<String, ScfmtErr> {
+    let notebook: Value = match serde_json::from_str(str) {
+        Ok(x) => x,
+        Err(_) => return Err(ScfmtErr::InvalidNotebook),
+    };
+    let (filetype, indentation_sensitive) =
+        kernel_filetype(&notebook, unindented_transform.is_some())?;
+    let transform = match unindented_transform {
+        Some(x) if indentation_sensitive => x,
+        _ => transform,
+    };
+    let sources = match code_cell_sources(str) {
+        Some(x) => x,
+        None => return Err(ScfmtErr::InvalidNotebook),
+    };
+
+    let mut transformed = String::new();
+    let mut i = 0;
+    for (cell, source) in sources {
+        let code = match serde_json::from_str(&str[source.clone()]) {
+            Ok(Value::String(x)) => x,
+            Ok(Value::Array(lines)) => lines.iter().filter_map(Value::as_str).collect(),
+            _ => return Err(ScfmtErr::InvalidNotebook),
+        };
+        if code.starts_with("%%") {
+            continue;
+        }
+
+        let new_code = transform(&code, filetype)
+            .map_err(|x| ScfmtErr::InNotebookCell(cell + 1, Box::new(x)))?;
+
+        if new_code != code {
+            transformed.push_str(&str[i..source.start]);
+            transformed.push_str(&source_json(str, source.clone(), &code, &new_code));
+            i = source.end;
+        }
+    }
+    transformed.push_str(&str[i..]);
+
+    Ok(transformed)
+}
@@ -0,0 +1,786 @@
+//! Refactorings that change the structure of sections, while keeping every bracket balanced
+
+use super::{
+    chop_off_beginning_spaces, closers_are_labeled, determine_turned_on_whitespace_type,
+    file_is_ignored, format_str, join_formatted_lines, line_is_only_whitepace, lines_turned_off,
+    parse_with_comment_starter, remove_comment_notation_if_it_exists, text_after_bracket,
+    transform_file, Bracket, Document, ScfmtErr, Section, EXTENSION_TO_COMMENT_STARTER_MAP,
+};
+use regex::Regex;
+use std::cell::RefCell;
+use std::ops::Range;
+use std::path::PathBuf;
+
+/// A string being refactored, along with what is known about it
+struct Source<'a> {
+    str: &'a str,
+    comment_starter: &'a str,
+    lines: Vec<&'a str>,
+    document: Document,
+    turned_off: Vec<bool>,
+    /// Whitespace added for each level of indentation
+    indent_unit: String,
+    labeled: bool,
+}
+
+impl<'a> Source<'a> {
+    /// Parses a string to be refactored. Returns None if the string should be left alone.
+    fn new(str: &'a str, filetype: &str) -> Result<Option<Source<'a>>, ScfmtErr> {
+        // determine if file compatible
+        let comment_starter = match EXTENSION_TO_COMMENT_STARTER_MAP.get(filetype) {
+            Some(x) => *x,
+            None => return Err(ScfmtErr::IncompatibleFileType),
+        };
+
+        // leave ignored files alone
+        if file_is_ignored(str, comment_starter) {
+            return Ok(None);
+        }
+
+        let document = parse_with_comment_starter(str, comment_starter)?;
+        let turned_off = lines_turned_off(str, comment_starter);
+        let (whitespace_char, tab_spaces) = determine_turned_on_whitespace_type(str, &turned_off);
+
+        Ok(Some(Source {
+            str,
+            comment_starter,
+            lines: str.lines().collect(),
+            document,
+            turned_off,
+            indent_unit: whitespace_char.to_string().repeat(tab_spaces),
+            labeled: closers_are_labeled(str, comment_starter),
+        }))
+    }
+
+    /// Finds the first section at a path of titles, which may contain glob patterns
+    fn find(&self, path: &str) -> Result<&Section, ScfmtErr> {
+        match self.document.find_matching(path).first() {
+            Some(x) => Ok(x),
+            None => Err(ScfmtErr::SectionNotFound(path.to_owned())),
+        }
+    }
+
+    /// Returns a bracketed comment like the one on `like_line`, with a different bracket and text
+    fn bracket_line(&self, like_line: usize, bracket: &str, text: &str) -> String {
+        let line = self.lines[like_line];
+        let (_, line_no_leading_spaces) = chop_off_beginning_spaces(line);
+        let (_, space_after_comment_starter, _) =
+            remove_comment_notation_if_it_exists(line_no_leading_spaces, self.comment_starter);
+        let possible_space = if space_after_comment_starter { " " } else { "" };
+
+        leading_whitespace(line).to_owned()
+            + self.comment_starter
+            + possible_space
+            + bracket
+            + &with_space_before(text)
+    }
+
+    /// Returns a line with the text after its bracket replaced, keeping the whitespace around that text
+    fn retitled_line(&self, i: usize, text: &str) -> String {
+        let line = self.lines[i];
+        let after = text_after_bracket(line, self.comment_starter);
+        if after.trim().is_empty() {
+            return line.trim_end().to_owned() + &with_space_before(text);
+        }
+
+        let start = line.len() - after.trim_start().len();
+        let end = line.len() - (after.len() - after.trim_end().len());
+        line[..start].to_owned() + text + &line[end..]
+    }
+
+    /// Zero based range of lines covered by the chain of ``//<>`` joined sections a section is part of
+    fn chain_span(&self, section: &Section) -> Range<usize> {
+        let mut first = section;
+        while first.opened_by == Bracket::CloseAndOpen {
+            match self
+                .document
+                .iter()
+                .find(|x| x.close_line == first.header_line)
+            {
+                Some(previous) => first = previous,
+                None => break,
+            }
+        }
+
+        let mut last = section;
+        while last.closed_by == Bracket::CloseAndOpen {
+            match self
+                .document
+                .iter()
+                .find(|x| x.header_line == last.close_line)
+            {
+                Some(next) => last = next,
+                None => break,
+            }
+        }
+
+        first.header_line..last.close_line + 1
+    }
+
+    /// Returns a line's text after adding one level of indentation, leaving lines that are empty or turned off alone
+    fn indented(&self, i: usize) -> String {
+        let line = self.lines[i];
+        if self.turned_off[i] || line_is_only_whitepace(line) {
+            line.to_owned()
+        } else {
+            self.indent_unit.clone() + line
+        }
+    }
+
+    /// Returns a line's text after removing one level of indentation, or as much of it as the line has
+    fn dedented(&self, i: usize) -> String {
+        let line = self.lines[i];
+        if self.turned_off[i] {
+            return line.to_owned();
+        }
+
+        let removable = leading_whitespace(line).len().min(self.indent_unit.len());
+        line[removable..].to_owned()
+    }
+
+    /// Joins lines into the refactored string, keeping whether the original ended with a '\n'
+    fn join(&self, lines: Vec<String>) -> String {
+        join_formatted_lines(lines.into_iter().map(|x| x + "\n").collect(), self.str)
+    }
+}
+
+fn leading_whitespace(line: &str) -> &str {
+    match chop_off_beginning_spaces(line).0 {
+        Some(x) => &line[..x],
+        None => line,
+    }
+}
+
+/// Returns `text` with a space before it, or nothing if `text` is empty
+fn with_space_before(text: &str) -> String {
+    if text.is_empty() {
+        String::new()
+    } else {
+        " ".to_owned() + text
+    }
+}
+
+/// Puts lines into a new section, indenting them one level
+///
+/// `lines` is a zero based range of line indexes, where the end is exclusive. The lines can't contain part of a section without all of it.
+///
+/// # Examples
+///
+/// ```
+/// use scfmt::scfmt::refactor::wrap_lines;
+///
+/// let to_wrap = "let a = 0;\nlet b = 0;\n";
+/// let wrapped = wrap_lines(to_wrap, "rs", 1..2, "b").unwrap();
+///
+/// assert_eq!(wrapped, "let a = 0;\n//> b\n    let b = 0;\n//<\n");
+/// ```
+pub fn wrap_lines(
+    str: &str,
+    filetype: &str,
+    lines: Range<usize>,
+    title: &str,
+) -> Result<String, ScfmtErr> {
+    let source = match Source::new(str, filetype)? {
+        Some(x) => x,
+        None => return Ok(str.to_owned()),
+    };
+
+    if lines.is_empty() || lines.end > source.lines.len() {
+        return Err(ScfmtErr::InvalidLineRange);
+    }
+
+    //> make sure no section would be cut in two
+        for section in source.document.iter() {
+            let span = section.span();
+            let inside = section.body.start <= lines.start && lines.end <= section.body.end;
+            let around = lines.start <= span.start && span.end <= lines.end;
+            let apart = lines.end <= span.start || span.end <= lines.start;
+
+            if !(inside || around || apart) {
+                return Err(ScfmtErr::LinesCrossSection(section.header_line + 1));
+            }
+        }
+
+    //<> indent the new section's brackets like the least indented line it wraps
+        let indentation = lines
+            .clone()
+            .filter(|&i| !source.turned_off[i] && !line_is_only_whitepace(source.lines[i]))
+            .map(|i| leading_whitespace(source.lines[i]))
+            .min_by_key(|x| x.len())
+            .unwrap_or_default();
+
+        let opener = indentation.to_owned() + source.comment_starter + ">";
+        let opener = opener + &with_space_before(title);
+        let mut closer = indentation.to_owned() + source.comment_starter + "<";
+        if source.labeled {
+            closer += &with_space_before(title);
+        }
+    //<
+
+    let mut wrapped = Vec::new();
+    for i in 0..source.lines.len() {
+        if i == lines.start {
+            wrapped.push(opener.clone());
+        }
+
+        if lines.contains(&i) {
+            wrapped.push(source.indented(i));
+        } else {
+            wrapped.push(source.lines[i].to_owned());
+        }
+
+        if i + 1 == lines.end {
+            wrapped.push(closer.clone());
+        }
+    }
+
+    Ok(source.join(wrapped))
+}
+
+/// Removes the brackets of the first section at a path of titles, and removes one level of indentation from its body
+///
+/// Titles in `path` may be glob patterns. Sections the removed section was chained to with ``//<>`` keep balanced brackets.
+///
+/// # Examples
+///
+/// ```
+/// use scfmt::scfmt::refactor::unwrap_section;
+///
+/// let to_unwrap = "//> a\n    //> b\n        let b = 0;\n    //<\n//<";
+/// let unwrapped = unwrap_section(to_unwrap, "rs", "a/b").unwrap();
+///
+/// assert_eq!(unwrapped, "//> a\n    let b = 0;\n//<");
+/// ```
+pub fn unwrap_section(str: &str, filetype: &str, path: &str) -> Result<String, ScfmtErr> {
+    let source = match Source::new(str, filetype)? {
+        Some(x) => x,
+        None => return Ok(str.to_owned()),
+    };
+    let section = source.find(path)?;
+
+    let mut unwrapped = Vec::new();
+    for i in 0..source.lines.len() {
+        if i == section.header_line {
+            // a //<> header still has to close the section before it
+            if section.opened_by == Bracket::CloseAndOpen {
+                let label = match source.document.iter().find(|x| x.close_line == i) {
+                    Some(previous) if source.labeled => previous.title.clone(),
+                    _ => String::new(),
+                };
+                unwrapped.push(source.bracket_line(i, "<", &label));
+            }
+        } else if i == section.close_line {
+            // a //<> closer still has to open the section after it
+            if section.closed_by == Bracket::CloseAndOpen {
+                unwrapped.push(source.lines[i].replacen("<>", ">", 1));
+            }
+        } else if section.body.contains(&i) {
+            unwrapped.push(source.dedented(i));
+        } else {
+            unwrapped.push(source.lines[i].to_owned());
+        }
+    }
+
+    Ok(source.join(unwrapped))
+}
+
+/// Splits the section a line is in into two sections, by putting a ``//<>`` before the line
+///
+/// `at` is a zero based line index, and must be directly in a section rather than in one of its children. The result is formatted, so the new sections are indented correctly.
+///
+/// # Examples
+///
+/// ```
+/// use scfmt::scfmt::refactor::split_section;
+///
+/// let to_split = "//> a\n    let a = 0;\n    let b = 0;\n//<";
+/// let split = split_section(to_split, "rs", 2, "b").unwrap();
+///
+/// assert_eq!(split, "//> a\n    let a = 0;\n//<> b\n    let b = 0;\n//<");
+/// ```
+pub fn split_section(
+    str: &str,
+    filetype: &str,
+    at: usize,
+    title: &str,
+) -> Result<String, ScfmtErr> {
+    let source = match Source::new(str, filetype)? {
+        Some(x) => x,
+        None => return Ok(str.to_owned()),
+    };
+
+    //> find the section directly holding the line
+        let section = source
+            .document
+            .iter()
+            .filter(|x| x.body.contains(&at))
+            .max_by_key(|x| x.depth)
+            .ok_or(ScfmtErr::LineNotInSection(at + 1))?;
+
+        // splitting before a child's closer would cut the child in two
+        if let Some(child) = section
+            .children
+            .iter()
+            .find(|x| x.header_line < at && at <= x.close_line)
+        {
+            return Err(ScfmtErr::LinesCrossSection(child.header_line + 1));
+        }
+    //<
+
+    let mut split = Vec::new();
+    for i in 0..source.lines.len() {
+        if i == at {
+            split.push(source.bracket_line(section.header_line, "<>", title));
+        }
+
+        // a labeled closer now closes the new section
+        if i == section.close_line && section.closed_by == Bracket::Close && source.labeled {
+            split.push(source.bracket_line(i, "<", title));
+        } else {
+            split.push(source.lines[i].to_owned());
+        }
+    }
+
+    format_str(&source.join(split), filetype)
+}
+
+/// Merges the first section at a path of titles with the section after it, by removing the ``//<>`` between them
+///
+/// If `combine_titles` is true, the merged section is titled with both titles separated by ", ". Otherwise it keeps the first section's title. The result is formatted.
+///
+/// # Examples
+///
+/// ```
+/// use scfmt::scfmt::refactor::merge_sections;
+///
+/// let to_merge = "//> a\n    let a = 0;\n//<> b\n    let b = 0;\n//<";
+///
+/// let merged = merge_sections(to_merge, "rs", "a", true).unwrap();
+/// assert_eq!(merged, "//> a, b\n    let a = 0;\n    let b = 0;\n//<");
+///
+/// let merged = merge_sections(to_merge, "rs", "a", false).unwrap();
+/// assert_eq!(merged, "//> a\n    let a = 0;\n    let b = 0;\n//<");
+/// ```
+pub fn merge_sections(
+    str: &str,
+    filetype: &str,
+    path: &str,
+    combine_titles: bool,
+) -> Result<String, ScfmtErr> {
+    let source = match Source::new(str, filetype)? {
+        Some(x) => x,
+        None => return Ok(str.to_owned()),
+    };
+    let section = source.find(path)?;
+
+    let next =
+        match source.document.iter().find(|x| {
+            section.closed_by == Bracket::CloseAndOpen && x.header_line == section.close_line
+        }) {
+            Some(x) => x,
+            None => return Err(ScfmtErr::NoNextSection(section.header_line + 1)),
+        };
+
+    let title = if !combine_titles || next.title.is_empty() {
+        section.title.clone()
+    } else if section.title.is_empty() {
+        next.title.clone()
+    } else {
+        format!("{}, {}", section.title, next.title)
+    };
+    let opener = match section.opened_by {
+        Bracket::CloseAndOpen => "<>",
+        _ => ">",
+    };
+
+    let mut merged = Vec::new();
+    for i in 0..source.lines.len() {
+        if i == section.header_line && title != section.title {
+            merged.push(source.bracket_line(i, opener, &title));
+        } else if i == next.close_line && next.closed_by == Bracket::Close && source.labeled {
+            // a labeled closer now closes the merged section
+            merged.push(source.bracket_line(i, "<", &title));
+        } else if i != section.close_line {
+            merged.push(source.lines[i].to_owned());
+        }
+    }
+
+    format_str(&source.join(merged), filetype)
+}
+
+/// Returns the lines of a string after moving one section to just before another
+///
+/// A section moved before a section in a ``//<>`` chain joins the chain. Otherwise it is given its own brackets, and the blank lines separating it from its siblings move with it.
+fn move_before(
+    source: &Source,
+    moved: &Section,
+    target: &Section,
+) -> Result<Vec<String>, ScfmtErr> {
+    let mut lines: Vec<Option<String>> = source.lines.iter().map(|x| Some(x.to_string())).collect();
+
+    // moving a section to where it already is changes nothing
+    if moved.header_line == target.header_line || moved.lines().end == target.header_line {
+        return Ok(lines.into_iter().flatten().collect());
+    }
+
+    if moved.span().contains(&target.header_line) {
+        return Err(ScfmtErr::SectionMovedIntoItself(moved.header_line + 1));
+    }
+
+    let joins_chain =
+        target.opened_by == Bracket::CloseAndOpen || target.closed_by == Bracket::CloseAndOpen;
+    let is_blank = |i: usize| !source.turned_off[i] && line_is_only_whitepace(source.lines[i]);
+
+    //> build the moved block, with its own brackets
+        let closer = match moved.closed_by {
+            Bracket::CloseAndOpen => {
+                let label = if source.labeled {
+                    moved.title.as_str()
+                } else {
+                    ""
+                };
+                source.bracket_line(moved.close_line, "<", label)
+            }
+            _ => source.lines[moved.close_line].to_owned(),
+        };
+
+        let opener = if target.opened_by == Bracket::CloseAndOpen {
+            "<>"
+        } else {
+            ">"
+        };
+        let mut block = vec![source.bracket_line(moved.header_line, opener, &moved.title)];
+        block.extend(moved.body.clone().map(|i| source.lines[i].to_owned()));
+        if !joins_chain {
+            block.push(closer);
+        }
+    //<
+
+    //> take the moved section out, keeping the sections chained to it balanced
+        let mut removed = moved.lines();
+        match (moved.opened_by, moved.closed_by) {
+            (Bracket::CloseAndOpen, Bracket::Close) => {
+                // the section before it is now the end of the chain
+                let label = match source
+                    .document
+                    .iter()
+                    .find(|x| x.close_line == moved.header_line)
+                {
+                    Some(previous) if source.labeled => previous.title.clone(),
+                    _ => String::new(),
+                };
+                let closer = source.bracket_line(moved.header_line, "<", &label);
+                lines[moved.header_line] = Some(closer);
+                removed.start += 1;
+            }
+            (Bracket::Open, Bracket::CloseAndOpen) => {
+                // the section after it is now the start of the chain
+                let opener = source.lines[moved.close_line].replacen("<>", ">", 1);
+                lines[moved.close_line] = Some(opener);
+            }
+            _ => {}
+        }
+
+        //> blank lines separating the section move with it
+            // a section chained to others leaves the chain's separation in place, and gets a copy of it
+            let chained =
+                moved.opened_by == Bracket::CloseAndOpen || moved.closed_by == Bracket::CloseAndOpen;
+            let around = if chained {
+                source.chain_span(moved)
+            } else {
+                removed.clone()
+            };
+            let after = (around.end..source.lines.len())
+                .take_while(|&i| is_blank(i))
+                .count();
+            let before = (0..around.start).rev().take_while(|&i| is_blank(i)).count();
+
+            let separation = if after > 0 && around.end + after < source.lines.len() {
+                if !chained {
+                    removed.end += after;
+                }
+                after
+            } else {
+                if !chained {
+                    removed.start -= before;
+                }
+                before
+            };
+            if !joins_chain {
+                block.extend(std::iter::repeat_n(String::new(), separation));
+            }
+        //<
+
+        for line in &mut lines[removed] {
+            *line = None;
+        }
+    //<
+
+    //> put the moved section before the target, indented to the target's depth
+        if joins_chain && target.opened_by == Bracket::Open {
+            let header = source.bracket_line(target.header_line, "<>", &target.title);
+            lines[target.header_line] = Some(header);
+        }
+
+        let block = block.into_iter().map(|line| {
+            if line_is_only_whitepace(&line) {
+                line
+            } else if target.depth >= moved.depth {
+                source.indent_unit.repeat(target.depth - moved.depth) + &line
+            } else {
+                let removable = source.indent_unit.len() * (moved.depth - target.depth);
+                line[leading_whitespace(&line).len().min(removable)..].to_owned()
+            }
+        });
+
+        let mut moved_lines = Vec::new();
+        for (i, line) in lines.into_iter().enumerate() {
+            if i == target.header_line {
+                moved_lines.extend(block.clone());
+            }
+            moved_lines.extend(line);
+        }
+    //<
+
+    Ok(moved_lines)
+}
+
+/// Moves the first section at a path of titles to just before the first section at another path
+///
+/// The moved section is indented to its new depth, and any ``//<>`` chains it leaves or joins keep balanced brackets. The result is formatted.
+///
+/// # Examples
+///
+/// ```
+/// use scfmt::scfmt::refactor::move_section;
+///
+/// let to_move = "//> a\n    let a = 0;\n//<\n\n//> b\n    let b = 0;\n//<\n";
+/// let moved = move_section(to_move, "rs", "b", "a").unwrap();
+///
+/// assert_eq!(moved, "//> b\n    let b = 0;\n//<\n\n//> a\n    let a = 0;\n//<\n");
+/// ```
+pub fn move_section(
+    str: &str,
+    filetype: &str,
+    path: &str,
+    before: &str,
+) -> Result<String, ScfmtErr> {
+    let source = match Source::new(str, filetype)? {
+        Some(x) => x,
+        None => return Ok(str.to_owned()),
+    };
+
+    let moved = move_before(&source, source.find(path)?, source.find(before)?)?;
+    format_str(&source.join(moved), filetype)
+}
+
+/// Sorts the sections nested directly inside the first section at a path of titles, by their titles
+///
+/// Titles are compared ignoring case, and sections with the same title keep their order. The result is formatted.
+///
+/// # Examples
+///
+/// ```
+/// use scfmt::scfmt::refactor::sort_sections;
+///
+/// let to_sort = "//> tests\n    //> b\n    //<\n    //> a\n    //<\n//<";
+/// let sorted = sort_sections(to_sort, "rs", "tests").unwrap();
+///
+/// assert_eq!(sorted, "//> tests\n    //> a\n    //<\n    //> b\n    //<\n//<");
+/// ```
+pub fn sort_sections(str: &str, filetype: &str, path: &str) -> Result<String, ScfmtErr> {
+    let mut sorted = str.to_owned();
+
+    // moves the first section of the unsorted ones in front of them, until none are left
+    for k in 0.. {
+        let moved = {
+            let source = match Source::new(&sorted, filetype)? {
+                Some(x) => x,
+                None => return Ok(str.to_owned()),
+            };
+            let children = &source.find(path)?.children;
+            if k >= children.len() {
+                break;
+            }
+
+            let first = children[k..]
+                .iter()
+                .min_by_key(|x| x.title.to_lowercase())
+                .unwrap_or(&children[k]);
+            source.join(move_before(&source, first, &children[k])?)
+        };
+        sorted = moved;
+    }
+
+    format_str(&sorted, filetype)
+}
+
+/// How ``rename_titles`` decides which titles to rename
+#[derive(Debug, Clone)]
+pub enum TitleMatch {
+    /// Titles equal to the text
+    Exact(String),
+    /// Titles equal to the text, ignoring case
+    CaseInsensitive(String),
+    /// Titles the regex matches part of. Only the matched parts are replaced, and the new title may refer to capture groups like ``$1``.
+    Regex(Regex),
+}
+
+impl TitleMatch {
+    /// Returns the new title for `title`, or None if it doesn't match
+    fn renamed(&self, title: &str, to: &str) -> Option<String> {
+        match self {
+            TitleMatch::Exact(x) if title == x => Some(to.to_owned()),
+            TitleMatch::CaseInsensitive(x) if title.to_lowercase() == x.to_lowercase() => {
+                Some(to.to_owned())
+            }
+            TitleMatch::Regex(x) if x.is_match(title) => {
+                Some(x.replace_all(title, to).into_owned())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A title changed by ``rename_titles``
+#[derive(PartialEq, Debug, Clone)]
+pub struct Renamed {
+    /// Zero based index of the line opening the renamed section
+    pub line: usize,
+    pub from: String,
+    pub to: String,
+}
+
+/// Renames the titles of sections, returning the renamed string along with every title that changed
+///
+/// If `within` is given, only sections at that path of titles and sections nested inside them are renamed. Only the text after each ``//>`` or ``//<>`` is changed, along with the labels of ``//<`` when closers are labeled.
+///
+/// # Examples
+///
+/// ```
+/// use scfmt::scfmt::refactor::{rename_titles, TitleMatch};
+///
+/// let to_rename = "//> Setup\n    let setup = 0;\n//<";
+/// let matcher = TitleMatch::CaseInsensitive("setup".to_owned());
+/// let (renamed, sites) = rename_titles(to_rename, "rs", &matcher, "arrange", None).unwrap();
+///
+/// assert_eq!(renamed, "//> arrange\n    let setup = 0;\n//<");
+/// assert_eq!(sites[0].from, "Setup");
+/// ```
+pub fn rename_titles(
+    str: &str,
+    filetype: &str,
+    matcher: &TitleMatch,
+    to: &str,
+    within: Option<&str>,
+) -> Result<(String, Vec<Renamed>), ScfmtErr> {
+    let source = match Source::new(str, filetype)? {
+        Some(x) => x,
+        None => return Ok((str.to_owned(), Vec::new())),
+    };
+
+    let sections: Vec<&Section> = match within {
+        Some(path) => source
+            .document
+            .find_matching(path)
+            .into_iter()
+            .flat_map(|x| x.iter())
+            .collect(),
+        None => source.document.iter().collect(),
+    };
+
+    let mut lines: Vec<String> = source.lines.iter().map(|x| x.to_string()).collect();
+    let mut sites = Vec::new();
+    for section in sections {
+        // a section nested in two matching sections is only renamed once
+        if sites
+            .iter()
+            .any(|x: &Renamed| x.line == section.header_line)
+        {
+            continue;
+        }
+
+        if let Some(title) = matcher.renamed(&section.title, to) {
+            if title == section.title {
+                continue;
+            }
+
+            lines[section.header_line] = source.retitled_line(section.header_line, &title);
+            if source.labeled && section.closed_by == Bracket::Close {
+                lines[section.close_line] = source.retitled_line(section.close_line, &title);
+            }
+
+            sites.push(Renamed {
+                line: section.header_line,
+                from: section.title.clone(),
+                to: title,
+            });
+        }
+    }
+
+    sites.sort_by_key(|x| x.line);
+    Ok((source.join(lines), sites))
+}
+
+/// Runs ``wrap_lines`` on contents of given file
+pub fn wrap_lines_file(file: PathBuf, lines: Range<usize>, title: &str) -> Result<(), ScfmtErr> {
+    transform_file(file, &|contents, extension| {
+        wrap_lines(contents, extension, lines.clone(), title)
+    })
+}
+
+/// Runs ``unwrap_section`` on contents of given file
+pub fn unwrap_section_file(file: PathBuf, path: &str) -> Result<(), ScfmtErr> {
+    transform_file(file, &|contents, extension| {
+        unwrap_section(contents, extension, path)
+    })
+}
+
+/// Runs ``split_section`` on contents of given file
+pub fn split_section_file(file: PathBuf, at: usize, title: &str) -> Result<(), ScfmtErr> {
+    transform_file(file, &|contents, extension| {
+        split_section(contents, extension, at, title)
+    })
+}
+
+/// Runs ``merge_sections`` on contents of given file
+pub fn merge_sections_file(
+    file: PathBuf,
+    path: &str,
+    combine_titles: bool,
+) -> Result<(), ScfmtErr> {
+    transform_file(file, &|contents, extension| {
+        merge_sections(contents, extension, path, combine_titles)
+    })
+}
+
+/// Runs ``move_section`` on contents of given file
+pub fn move_section_file(file: PathBuf, path: &str, before: &str) -> Result<(), ScfmtErr> {
+    transform_file(file, &|contents, extension| {
+        move_section(contents, extension, path, before)
+    })
+}
+
+/// Runs ``sort_sections`` on contents of given file
+pub fn sort_sections_file(file: PathBuf, path: &str) -> Result<(), ScfmtErr> {
+    transform_file(file, &|contents, extension| {
+        sort_sections(contents, extension, path)
+    })
+}
+
+/// Runs ``rename_titles`` on contents of given file, returning the titles that changed
+pub fn rename_titles_file(
+    file: PathBuf,
+    matcher: &TitleMatch,
+    to: &str,
+    within: Option<&str>,
+) -> Result<Vec<Renamed>, ScfmtErr> {
+    let sites = RefCell::new(Vec::new());
+    transform_file(file, &|contents, extension| {
+        let (renamed, file_sites) = rename_titles(contents, extension, matcher, to, within)?;
+        sites.replace(file_sites);
+        Ok(renamed)
+    })?;
+
+    Ok(sites.into_inner())
+}
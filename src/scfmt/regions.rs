@@ -0,0 +1,220 @@
+//! Files mixing languages, like Vue and Svelte components, HTML pages and PHP templates
+//!
+//! Only the code in embedded regions is processed: ``<script>`` and ``<style>`` blocks, and ``<?php ?>`` blocks in PHP files. Each region is processed on its own, as code in its language with its own indentation, so sections can't cross from one region into another.
+
+use super::markdown::{filetype_of_language, offset_err};
+use super::{file_is_ignored, join_formatted_lines, ScfmtErr};
+use regex::Regex;
+use std::ops::Range;
+
+/// Extensions of files made of regions in different languages
+pub static EXTENSIONS: [&str; 5] = ["vue", "svelte", "html", "htm", "php"];
+
+/// Returns whether a file is made of regions in different languages
+///
+/// PHP files only are when they have a ``<?php`` tag, so the code inside ``<?php ?>`` regions is processed as plain PHP.
+pub fn is_multi_language(str: &str, filetype: &str) -> bool {
+    match filetype {
+        "php" => str.to_ascii_lowercase().contains("<?php"),
+        _ => EXTENSIONS.contains(&filetype),
+    }
+}
+
+/// A block of code embedded in a file of another language
+#[derive(PartialEq, Debug, Clone)]
+pub struct Region {
+    /// Zero based index of the line opening the region, like a ``<script>`` tag
+    pub line: usize,
+    /// Extension of files in the region's language
+    pub filetype: &'static str,
+    /// Zero based range of the lines inside the region
+    pub body: Range<usize>,
+}
+
+/// Returns the value of an attribute in an opening tag, lowercased
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let pattern = format!(r#"(?i)\s{}\s*=\s*["']?([^"'\s>]*)"#, name);
+    let captures = Regex::new(&pattern).ok()?.captures(tag)?;
+
+    Some(captures[1].to_ascii_lowercase())
+}
+
+/// Returns the language of the code inside a ``<script>`` or ``<style>`` tag, if scfmt knows it
+fn tag_filetype(element: &str, tag: &str) -> Option<&'static str> {
+    match (element, attribute(tag, "lang")) {
+        (_, Some(lang)) => filetype_of_language(&lang),
+        ("script", None) => match attribute(tag, "type") {
+            // other types hold things like templates and JSON
+            Some(x) if !(x == "module" || x.contains("javascript")) => None,
+            _ => Some("js"),
+        },
+        // plain CSS has no line comments
+        _ => None,
+    }
+}
+
+/// Returns every ``<script>`` and ``<style>`` region in lines, along with ``<?php ?>`` regions if `php` is true
+///
+/// Regions start on the line after their opening tag, and end on the line before their closing tag, so tags with code on the same line as them only have the lines between them processed. Regions scfmt can't process, like plain CSS, are left out.
+fn find_regions(lines: &[&str], php: bool) -> Vec<Region> {
+    let mut regions = Vec::new();
+
+    let mut i = 0;
+    while i < lines.len() {
+        let trimmed = lines[i].trim_start();
+        let lowercase = trimmed.to_ascii_lowercase();
+
+        //> find the region opened on this line, and what closes it
+            let mut opened = None;
+            if php && lowercase.contains("<?php") {
+                let after_tag = &lowercase[lowercase.find("<?php").unwrap_or(0)..];
+                if !after_tag.contains("?>") {
+                    opened = Some(("php", "?>"));
+                }
+            } else {
+                for (element, closing) in [("script", "</script"), ("style", "</style")] {
+                    let is_tag = lowercase.starts_with(&format!("<{}", element))
+                        && lowercase[element.len() + 1..].starts_with([' ', '\t', '>']);
+                    let tag_end = match trimmed.find('>') {
+                        Some(x) => x,
+                        None => continue,
+                    };
+
+                    if is_tag && !lowercase[tag_end..].contains(closing) {
+                        opened = tag_filetype(element, &trimmed[..tag_end])
+                            .map(|filetype| (filetype, closing));
+                        if opened.is_none() {
+                            // skip the region, so tags inside it aren't read
+                            while i + 1 < lines.len()
+                                && !lines[i + 1].to_ascii_lowercase().contains(closing)
+                            {
+                                i += 1;
+                            }
+                        }
+                    }
+                }
+            }
+        //<
+
+        let (filetype, closing) = match opened {
+            Some(x) => x,
+            None => {
+                i += 1;
+                continue;
+            }
+        };
+
+        let start = i + 1;
+        let mut end = start;
+        while end < lines.len() && !lines[end].to_ascii_lowercase().contains(closing) {
+            end += 1;
+        }
+
+        regions.push(Region {
+            line: i,
+            filetype,
+            body: start..end,
+        });
+        i = end + 1;
+    }
+
+    regions
+}
+
+/// Returns every region of code in a file mixing languages, in the order they appear
+///
+/// # Examples
+///
+/// ```
+/// use scfmt::scfmt::regions::regions;
+///
+/// let component = "<template>\n</template>\n<script lang=\"ts\">\nlet a = 0;\n</script>\n";
+/// let regions = regions(component, "vue");
+///
+/// assert_eq!(regions[0].filetype, "ts");
+/// assert_eq!(regions[0].body, 3..4);
+/// ```
+pub fn regions(str: &str, filetype: &str) -> Vec<Region> {
+    let lines: Vec<&str> = str.lines().collect();
+    find_regions(&lines, filetype == "php")
+}
+
+/// Runs a transform on the code in every region of a file mixing languages, leaving everything else alone
+///
+/// Each region is transformed on its own, so a section opened in one region and closed in another is reported as never closed. The indentation shared by a region's lines is removed before the transform, and put back after.
+///
+/// # Examples
+///
+/// ```
+/// use scfmt::scfmt::format_str;
+/// use scfmt::scfmt::regions::transform_regions;
+///
+/// let page = "<script>\n  //> a\n  let a = 0;\n  //<\n</script>\n";
+/// let formatted = transform_regions(page, "html", &format_str).unwrap();
+///
+/// assert_eq!(formatted, "<script>\n  //> a\n      let a = 0;\n  //<\n</script>\n");
+/// ```
+pub fn transform_regions(
+    str: &str,
+    filetype: &str,
+    transform: &dyn Fn(&str, &str) -> Result<String, ScfmtErr>,
+) -> Result<String, ScfmtErr> {
+    transform_regions_at(str, filetype, &|code, filetype, _| {
+        transform(code, filetype)
+    })
+}
+
+/// Like ``transform_regions``, but `transform` is also given the zero based index of the line each region's code starts on
+pub(super) fn transform_regions_at(
+    str: &str,
+    filetype: &str,
+    transform: &dyn Fn(&str, &str, usize) -> Result<String, ScfmtErr>,
+) -> Result<String, ScfmtErr> {
+    // leave ignored files alone
+    if file_is_ignored(str, "//") {
+        return Ok(str.to_owned());
+    }
+
+    let lines: Vec<&str> = str.lines().collect();
+    let mut transformed: Vec<String> = Vec::new();
+
+    let mut i = 0;
+    for region in regions(str, filetype) {
+        transformed.extend(lines[i..region.body.start].iter().map(|x| x.to_string()));
+
+        //> transform the code, without the indentation its lines share
+            let indentation = lines[region.body.clone()]
+                .iter()
+                .filter(|line| !line.trim().is_empty())
+                .map(|line| &line[..line.len() - line.trim_start().len()])
+                .min_by_key(|x| x.len())
+                .unwrap_or("");
+
+            let mut code = String::new();
+            for line in &lines[region.body.clone()] {
+                let removable = line.len() - line.trim_start().len();
+                code.push_str(&line[removable.min(indentation.len())..]);
+                code.push('\n');
+            }
+
+            let code = transform(&code, region.filetype, region.body.start)
+                .map_err(|x| offset_err(x, region.body.start))?;
+
+            transformed.extend(code.lines().map(|line| {
+                if line.is_empty() {
+                    line.to_owned()
+                } else {
+                    indentation.to_owned() + line
+                }
+            }));
+        //<
+
+        i = region.body.end;
+    }
+    transformed.extend(lines[i..].iter().map(|x| x.to_string()));
+
+    Ok(join_formatted_lines(
+        transformed.into_iter().map(|x| x + "\n").collect(),
+        str,
+    ))
+}
@@ -0,0 +1,173 @@
+//! Tag file entries for section titles, so editors that navigate by tags can jump to sections
+
+use super::{
+    file_is_ignored, parse_leniently, ScfmtErr, Section, EXTENSION_TO_COMMENT_STARTER_MAP,
+};
+
+/// A section that can be jumped to by its title
+#[derive(PartialEq, Debug, Clone)]
+pub struct Tag {
+    /// Title of the section
+    pub name: String,
+    /// Zero based index of the line opening the section
+    pub line: usize,
+    /// Byte offset of the start of that line
+    pub offset: usize,
+    /// Text of that line, without its line ending
+    pub text: String,
+    /// Titles of the sections enclosing the section, from the outermost section to the innermost
+    pub scope: Vec<String>,
+}
+
+/// Returns a tag for every section with a title, in the order they appear
+///
+/// Unbalanced brackets are skipped, so files being edited still get tags.
+///
+/// # Examples
+///
+/// ```
+/// use scfmt::scfmt::tags::tags;
+///
+/// let tags = tags("//> tests\n    //> validation\n    //<\n//<", "rs").unwrap();
+///
+/// assert_eq!(tags[1].name, "validation");
+/// assert_eq!(tags[1].line, 1);
+/// assert_eq!(tags[1].scope, vec!["tests"]);
+/// ```
+pub fn tags(str: &str, filetype: &str) -> Result<Vec<Tag>, ScfmtErr> {
+    // determine if file compatible
+    let comment_starter = match EXTENSION_TO_COMMENT_STARTER_MAP.get(filetype) {
+        Some(x) => *x,
+        None => return Err(ScfmtErr::IncompatibleFileType),
+    };
+
+    // leave ignored files alone
+    if file_is_ignored(str, comment_starter) {
+        return Ok(Vec::new());
+    }
+
+    let mut offsets = vec![0];
+    for line in str.split_inclusive('\n') {
+        offsets.push(offsets.last().unwrap_or(&0) + line.len());
+    }
+    let source_lines: Vec<&str> = str.lines().collect();
+
+    fn push_tags(
+        sections: &[Section],
+        scope: &mut Vec<String>,
+        source: (&[&str], &[usize]),
+        tags: &mut Vec<Tag>,
+    ) {
+        let (source_lines, offsets) = source;
+        for section in sections {
+            if !section.title.is_empty() {
+                tags.push(Tag {
+                    name: section.title.clone(),
+                    line: section.header_line,
+                    offset: offsets[section.header_line],
+                    text: source_lines[section.header_line].to_owned(),
+                    scope: scope.clone(),
+                });
+            }
+
+            scope.push(section.title.clone());
+            push_tags(&section.children, scope, source, tags);
+            scope.pop();
+        }
+    }
+
+    let document = parse_leniently(str, comment_starter).0;
+    let mut tags = Vec::new();
+    push_tags(
+        &document.sections,
+        &mut Vec::new(),
+        (&source_lines, &offsets),
+        &mut tags,
+    );
+
+    Ok(tags)
+}
+
+/// Returns a Universal Ctags tag file holding the tags of each file, sorted by name
+///
+/// Every tag has the kind ``s`` for section, and sections nested inside others have a ``section`` scope field holding the path of titles of the sections enclosing them.
+///
+/// # Examples
+///
+/// ```
+/// use scfmt::scfmt::tags::{ctags, tags};
+///
+/// let file_tags = tags("//> tests\n    //> validation\n    //<\n//<", "rs").unwrap();
+/// let tag_file = ctags(&[("src/lib.rs".to_owned(), file_tags)]);
+///
+/// assert!(tag_file.ends_with(
+///     "validation\tsrc/lib.rs\t/^    \\/\\/> validation$/;\"\ts\tline:2\tsection:tests\n"
+/// ));
+/// ```
+pub fn ctags(files: &[(String, Vec<Tag>)]) -> String {
+    let mut entries: Vec<(&str, &str, usize, String)> = Vec::new();
+    for (file, tags) in files {
+        for tag in tags {
+            let mut entry = format!(
+                "{}\t{}\t/^{}$/;\"\ts\tline:{}",
+                tag.name,
+                file,
+                tag.text.replace('\\', "\\\\").replace('/', "\\/"),
+                tag.line + 1
+            );
+            if !tag.scope.is_empty() {
+                entry += &format!("\tsection:{}", tag.scope.join("/"));
+            }
+
+            entries.push((&tag.name, file, tag.line, entry));
+        }
+    }
+
+    // sorted by name, so editors can binary search the file
+    entries.sort_by(|a, b| (a.0, a.1, a.2).cmp(&(b.0, b.1, b.2)));
+
+    let mut tag_file = concat!(
+        "!_TAG_FILE_FORMAT\t2\t/extended format; --format=1 will not append ;\" to lines/\n",
+        "!_TAG_FILE_SORTED\t1\t/0=unsorted, 1=sorted, 2=foldcase/\n",
+        "!_TAG_PROGRAM_NAME\tscfmt\t//\n",
+    )
+    .to_owned();
+    for (_, _, _, entry) in entries {
+        tag_file += &entry;
+        tag_file.push('\n');
+    }
+
+    tag_file
+}
+
+/// Returns an Emacs etags file holding the tags of each file, in the order they are given
+///
+/// # Examples
+///
+/// ```
+/// use scfmt::scfmt::tags::{etags, tags};
+///
+/// let file_tags = tags("let a = 0;\n//> setup\n//<", "rs").unwrap();
+/// let tag_file = etags(&[("src/lib.rs".to_owned(), file_tags)]);
+///
+/// assert_eq!(tag_file, "\x0c\nsrc/lib.rs,21\n//> setup\x7fsetup\x012,11\n");
+/// ```
+pub fn etags(files: &[(String, Vec<Tag>)]) -> String {
+    let mut tag_file = String::new();
+    for (file, tags) in files {
+        let mut section = String::new();
+        for tag in tags {
+            section += &format!(
+                "{}\x7f{}\x01{},{}\n",
+                tag.text,
+                tag.name,
+                tag.line + 1,
+                tag.offset
+            );
+        }
+
+        tag_file += &format!("\x0c\n{},{}\n{}", file, section.len(), section);
+    }
+
+    tag_file
+}
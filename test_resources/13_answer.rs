@@ -0,0 +1,40 @@
+//! Crate docs, with an example:
+//!
+//! ```
+//! //> setup
+//!     let a = 0;
+//! //<
+//! ```
+
+//> items
+    /// Doc comments of items in sections are formatted too
+    ///
+    /// ```no_run
+    /// //> call
+    ///     a();
+    ///     //> nested
+    ///         b();
+    ///     //<
+    /// //<
+    /// ```
+    ///
+    /// ```text
+    /// //>
+    /// left alone
+    /// //<
+    /// ```
+    fn a() {
+        a();
+    }
+//<
+
+// scfmt: off
+/// ```
+/// //>
+/// turned off
+/// //<
+/// ```
+// scfmt: on
+fn c() {
+    c();
+}